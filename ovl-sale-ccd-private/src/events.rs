@@ -0,0 +1,62 @@
+use crate::state::*;
+use concordium_std::*;
+
+/// Events logged by the sale contract so that indexers can follow
+/// every state transition without diffing the views.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub enum SaleEvent {
+    /// An investor has been given an allocation.
+    Whitelisted(WhitelistedEvent),
+    /// An investor has deposited CCD to fix their allocation.
+    Deposited(DepositedEvent),
+    /// An investor has claimed vested project tokens.
+    Claimed(ClaimedEvent),
+    /// A sale fee has been paid out in project tokens.
+    FeeClaimed(FeeClaimedEvent),
+    /// The sale status has changed.
+    StatusChanged(SaleStatus),
+    /// The contract has been paused(`true`) or unpaused(`false`).
+    Paused(bool),
+    /// TGE(the beginning of the vesting period) has been set.
+    TgeSet(Timestamp),
+    /// Project token contract has been set.
+    ProjectTokenSet(ContractAddress),
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct WhitelistedEvent {
+    /// Whitelisted investor
+    pub(crate) investor: Address,
+    /// Amount of project tokens the investor can purchase
+    pub(crate) allocation: ContractTokenAmount,
+    /// Price in ccd per a project token
+    pub(crate) price_per_token: MicroCcd,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct DepositedEvent {
+    /// Investor who deposited
+    pub(crate) investor: Address,
+    /// Deposited CCD
+    pub(crate) amount: Amount,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct ClaimedEvent {
+    /// Investor who claimed
+    pub(crate) investor: Address,
+    /// Amount of project tokens transferred
+    pub(crate) amount: ContractTokenAmount,
+    /// Number of vesting steps received so far
+    pub(crate) inc: u8,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct FeeClaimedEvent {
+    /// Receiver of the fee
+    pub(crate) to: Address,
+    /// Amount of project tokens transferred
+    pub(crate) amount: ContractTokenAmount,
+    /// Number of vesting steps received so far
+    pub(crate) inc: u8,
+}
@@ -0,0 +1,560 @@
+//! This Contract is used for private sale with CCD on the Overlay IDO platform.
+//! Each investor is whitelisted with their own allocation and price,
+//! and the vesting schedule is set independently from the public round.
+#[cfg(any(feature = "wasm-test", test))]
+mod sctest;
+mod events;
+mod state;
+mod view;
+
+use concordium_cis2::OnReceivingCis2Params;
+use concordium_std::{collections::BTreeMap, *};
+use events::*;
+use sale_utils::{
+    rido::{self, FeeReceiver},
+    token::transfer_token,
+    BPS_DENOMINATOR, PRIVATE_RIDO_FEE,
+};
+use state::{State, *};
+
+/// The parameter schema for `init` function.
+#[derive(Debug, Serialize, SchemaType)]
+pub struct InitParams {
+    /// Account of the administrator of the entity running the IDO
+    pub proj_admin: AccountAddress,
+    /// Address of Overlay for receiving sale fee
+    pub addr_ovl: Address,
+    /// Address of Overlay for buy back burn
+    pub addr_bbb: Address,
+    /// Sale Start Time
+    pub open_at: Timestamp,
+    /// Sale End Time
+    pub close_at: Timestamp,
+    /// Investors can withdraw assets according to the vesting period
+    /// (unlocked shares in basis points, adding up to 10,000)
+    pub vesting_period: BTreeMap<Duration, AllowedPercentage>,
+}
+
+/// # Init Function
+/// everyone can init this module, but need to be initialized by ovl_team
+/// since contract_id is needed to record into project contract.
+#[init(contract = "priv_rido_ccd", parameter = "InitParams")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+
+    // Only whitelisted investors can deposit, so there is a single phase.
+    let schedule = SaleSchedule::new(
+        ctx.metadata().slot_time(),
+        BTreeMap::from([(params.open_at, Prior::TOP)]),
+        params.close_at,
+        VestingSchedule::Stepped(params.vesting_period),
+    )?;
+
+    Ok(State::new(
+        state_builder,
+        params.proj_admin,
+        params.addr_ovl,
+        params.addr_bbb,
+        schedule,
+    ))
+}
+
+// ==============================================
+// For ovl team
+// ==========================================
+
+/// Some transferable functions (createPool, projectClaim, deposit, userClaim)
+/// cannot be executed when the contract is paused.
+///
+/// Caller: contract instance owner only
+/// Reject if:
+/// - The sender is not the contract owner.
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "setPaused",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_paused<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    rido::set_paused(ctx, host.state_mut(), true)?;
+    logger.log(&SaleEvent::Paused(true))?;
+    Ok(())
+}
+
+/// The contract is unpaused.
+///
+/// Caller: contract instance owner only
+/// Reject if:
+/// - The sender is not the contract owner.
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "setUnpaused",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_unpaused<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    rido::set_paused(ctx, host.state_mut(), false)?;
+    logger.log(&SaleEvent::Paused(false))?;
+    Ok(())
+}
+
+/// Set status to fix for next stage(claim).
+/// Note: if no investor has deposited, the sale will be cancelled.
+///
+/// Caller: contract instance owner only
+/// Reject if:
+/// - The sender is not the contract owner.
+/// - Status is not Ready
+/// - Called before the end of the sale
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "setFixed",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_fixed<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(
+        ctx.sender().matches_account(&ctx.owner()),
+        ContractError::Unauthorized
+    );
+
+    let state = host.state_mut();
+    rido::ensure_sale_closed(ctx, state)?;
+
+    if state.sold_amount.0 > 0 {
+        state.status = SaleStatus::Fixed;
+    } else {
+        state.status = SaleStatus::Suspend;
+    }
+    logger.log(&SaleEvent::StatusChanged(state.status.clone()))?;
+
+    Ok(())
+}
+
+/// Parameter type for the contract function `whitelisting`.
+#[derive(Debug, Serialize, SchemaType)]
+struct WhitelistingParams {
+    /// the whitelist
+    wl: Vec<AllowedInvestorParams>,
+    /// If true, it means no further registration
+    ready: bool,
+}
+
+#[derive(Debug, Serialize, SchemaType)]
+struct AllowedInvestorParams {
+    /// Investors address to be whitelisted
+    investor: Address,
+    /// Amount of project tokens the investor can purchase
+    allocation: ContractTokenAmount,
+    /// Price in ccd per a project token agreed with the investor
+    price_per_token: MicroCcd,
+}
+
+/// Whitelist investors with their individual allocation and price.
+/// Note: Whitelisting an investor again overwrites their allocation.
+///
+/// Caller: contract instance owner only
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is not the contract owner.
+/// - Status is not Prepare
+/// - The price of an allocation overflows
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "whitelisting",
+    parameter = "WhitelistingParams",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_whitelisting<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(
+        ctx.sender().matches_account(&ctx.owner()),
+        ContractError::Unauthorized
+    );
+
+    let state = host.state_mut();
+    ensure_eq!(
+        state.status,
+        SaleStatus::Prepare,
+        CustomContractError::AlreadySaleStarted.into()
+    );
+
+    let params: WhitelistingParams = ctx.parameter_cursor().get()?;
+
+    for AllowedInvestorParams {
+        investor,
+        allocation,
+        price_per_token,
+    } in params.wl
+    {
+        if let Address::Account(_) = investor {
+            state.whitelisting(&investor, allocation, price_per_token)?;
+            logger.log(&SaleEvent::Whitelisted(WhitelistedEvent {
+                investor,
+                allocation,
+                price_per_token,
+            }))?;
+        } else {
+            // [#TODO] Only support AccountAddress for now.
+            bail!(CustomContractError::AccountOnly.into())
+        };
+    }
+
+    if params.ready {
+        state.status = SaleStatus::Ready;
+        logger.log(&SaleEvent::StatusChanged(SaleStatus::Ready))?;
+    }
+
+    Ok(())
+}
+
+/// To claim sale fee for overlay team.
+/// Note: 5% for now.
+///
+/// Caller: contract instance owner only
+/// Reject if:
+/// - The sender is not the contract owner.
+/// - Status is not Fixed
+/// - Project admin has not yet registered the project token
+/// - Project admin has not yet registered the TGE
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "ovlClaim",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_ovl_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let (to, amount, inc) = rido::claim_fee(ctx, host, FeeReceiver::Ovl)?;
+
+    if amount.0 > 0 {
+        logger.log(&SaleEvent::FeeClaimed(FeeClaimedEvent { to, amount, inc }))?;
+    }
+
+    Ok(())
+}
+
+/// To claim sale fee for Buy Back Burn.
+/// Note: 5% for now.
+///
+/// Caller: contract instance owner only
+/// Reject if:
+/// - The sender is not the contract owner.
+/// - Status is not Fixed
+/// - Project admin has not yet registered the project token
+/// - Project admin has not yet registered the TGE
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "bbbClaim",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_bbb_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let (to, amount, inc) = rido::claim_fee(ctx, host, FeeReceiver::Bbb)?;
+
+    if amount.0 > 0 {
+        logger.log(&SaleEvent::FeeClaimed(FeeClaimedEvent { to, amount, inc }))?;
+    }
+
+    Ok(())
+}
+
+/// Callback function to call when CIS2 is called
+/// but no callback is needed.
+/// Caller: Anyone
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "callback",
+    parameter = "OnReceivingCis2Params<ContractTokenId, ContractTokenAmount>",
+    mutable
+)]
+fn callback<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    _host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<()> {
+    Ok(())
+}
+
+// ==============================================
+// For project admin
+// ==========================================
+
+/// Set project token contract.
+///
+/// Caller: Project Admin only
+/// Reject if:
+/// - Fails to parse parameter
+/// - Already set the contract address
+/// - The sender is not the project admin
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "setPjtoken",
+    parameter = "ContractAddress",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_pjtoken<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(
+        ctx.sender().matches_account(&host.state().proj_admin),
+        ContractError::Unauthorized
+    );
+    let addr = rido::set_project_token(ctx, host.state_mut())?;
+    logger.log(&SaleEvent::ProjectTokenSet(addr))?;
+
+    Ok(())
+}
+
+/// Set TGE, which means it determines the beginning of the vesting period.
+///
+/// Caller: Project Admin only
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is not the project admin
+/// - Already set the TGE
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "setTGE",
+    parameter = "Timestamp",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_tge<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(
+        ctx.sender().matches_account(&host.state().proj_admin),
+        ContractError::Unauthorized
+    );
+    let ts = rido::set_tge(ctx, host.state_mut())?;
+    logger.log(&SaleEvent::TgeSet(ts))?;
+
+    Ok(())
+}
+
+/// Project Administrator should this function once project token are generated.
+/// The amount to be deposited must be the same as the amount sold at the sale
+/// Note: This contract is supposed to be called from a CIS2 contract
+///
+/// Caller: Project Token Contract only
+/// Invoker: Project Admin only
+/// Reject if:
+/// - Contract is paused
+/// - Fails to parse parameter
+/// - Status is not Fixed
+/// - The sender is not the project token contract
+/// - The quantity to be deposited differs from the quantity sold in the sale.
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "createPool",
+    parameter = "OnReceivingCis2Params<ContractTokenId, ContractTokenAmount>",
+    error = "ContractError"
+)]
+fn contract_create_pool<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    rido::create_pool(ctx, host.state())
+}
+
+/// Project admin can claim CCD sold at the sale.
+/// Note: No sale fee is charged to the project.
+///
+/// Caller: Project Admin only
+/// Reject if:
+/// - Contract is paused
+/// - Status is not Fixed
+/// - The sender is not the project admin
+/// - Fails to invoke transfer from this contract to the admin
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "projectClaim",
+    error = "ContractError"
+)]
+fn contract_project_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let proj_admin = rido::ensure_project_claimable(ctx, host.state())?;
+
+    // Transfer the whole balance to the project admin.
+    let balance = host.self_balance();
+    let transfer_result = host.invoke_transfer(&proj_admin, balance);
+    ensure!(
+        transfer_result.is_ok(),
+        CustomContractError::TransferError.into()
+    );
+
+    Ok(())
+}
+
+// ==============================================
+// For investors
+// ==========================================
+
+/// Investors call this function to fix their allocation
+/// by depositing its whole price in CCD.
+///
+/// Caller: Investors on the whitelist
+/// Reject if:
+/// - Contract is paused
+/// - Status is not Ready
+/// - Not on sale
+/// - The sender is not on the whitelist
+/// - The sender have already deposited
+/// - Sended CCD not match the price of the allocation
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "userDeposit",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    payable,
+    enable_logger
+)]
+fn contract_user_deposit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    amount: Amount,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+
+    ensure!(!state.paused, CustomContractError::ContractPaused.into());
+    ensure!(
+        state.status == SaleStatus::Ready,
+        CustomContractError::SaleNotReady.into()
+    );
+    ensure!(
+        state.schedule.is_on_sale(ctx.metadata().slot_time()),
+        CustomContractError::InvalidSchedule.into()
+    );
+
+    let sender = ctx.sender();
+    let investor = state.get_investor(&sender)?;
+
+    ensure!(
+        !investor.is_deposited(),
+        CustomContractError::AlreadyDeposited.into()
+    );
+    ensure!(
+        amount == investor.price()?,
+        CustomContractError::InvalidCcdInput.into()
+    );
+    state.deposit(&sender, amount)?;
+
+    logger.log(&SaleEvent::Deposited(DepositedEvent {
+        investor: sender,
+        amount,
+    }))?;
+
+    Ok(())
+}
+
+/// Investors can claim project token when the vesting period arrives.
+/// Note: If an investor claims many times within a certain period of time,
+/// they will just get 0 back.
+///
+/// Caller: Investors on the whitelist
+/// Reject if:
+/// - Contract is paused
+/// - Status is not Fixed
+/// - Project admin has not yet registered the project token
+/// - Project admin has not yet registered the TGE
+/// - The sender is not on the whitelist
+/// - The sender has not deposited.
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "userClaim",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_user_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+
+    ensure!(!state.paused, CustomContractError::ContractPaused.into());
+    let (project_token, vesting_start) = rido::ensure_claimable(state)?;
+
+    let investor = ctx.sender();
+    let investor_state = state.get_investor(&investor)?;
+    ensure!(
+        investor_state.is_deposited(),
+        CustomContractError::NotDeposited.into()
+    );
+
+    let (amount, inc): (ContractTokenAmount, u8) = state.calc_vesting_amount(
+        ctx.metadata().slot_time(),
+        vesting_start,
+        investor_state.allocation,
+        BPS_DENOMINATOR - PRIVATE_RIDO_FEE,
+        investor_state.claimed_inc,
+    )?;
+
+    if inc > investor_state.claimed_inc {
+        state.increment_investor_claimed(&investor, inc)?;
+    }
+
+    if amount.0 > 0 {
+        transfer_token(ctx, host, project_token, investor, amount)?;
+        logger.log(&SaleEvent::Claimed(ClaimedEvent {
+            investor,
+            amount,
+            inc,
+        }))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,2 @@
+mod overlay_team;
+mod participant;
@@ -0,0 +1,239 @@
+use concordium_std::concordium_cfg_test;
+
+#[concordium_cfg_test]
+mod tests {
+    use crate::*;
+    use concordium_std::test_infrastructure::*;
+
+    #[concordium_test]
+    /// Test that init succeeds.
+    fn test_init() {
+        let mut state_builder = TestStateBuilder::new();
+        let invoker = AccountAddress([0u8; 32]);
+        let slot_time = Timestamp::from_timestamp_millis(1);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
+        let addr_bbb = Address::Contract(ContractAddress {
+            index: 100,
+            subindex: 0,
+        });
+        let open_at = Timestamp::from_timestamp_millis(10);
+        let close_at = Timestamp::from_timestamp_millis(30);
+        let vesting_period = BTreeMap::from([
+            (Duration::from_days(30), 1_000),
+            (Duration::from_days(180), 4_000),
+            (Duration::from_days(360), 5_000),
+        ]);
+
+        let expected_state = State {
+            proj_admin,
+            status: SaleStatus::Prepare,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(open_at, Prior::TOP)]),
+                close_at,
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(vesting_period.clone()),
+            },
+            sold_amount: ContractTokenAmount::from(0),
+            investors: state_builder.new_map(),
+        };
+
+        // set init context
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(invoker);
+        ctx.set_metadata_slot_time(slot_time);
+
+        // create params
+        let params = InitParams {
+            proj_admin,
+            addr_ovl,
+            addr_bbb,
+            open_at,
+            close_at,
+            vesting_period,
+        };
+        let params_byte = to_bytes(&params);
+        ctx.set_parameter(&params_byte);
+
+        // execute init
+        let result = contract_init(&ctx, &mut state_builder);
+        claim!(result.is_ok());
+        let actual_state = result.unwrap();
+        claim_eq!(
+            actual_state,
+            expected_state,
+            "state has been initialized unexpectedly..."
+        );
+    }
+
+    #[concordium_test]
+    /// Test that whitelisting registers each investor with their own allocation and price.
+    fn test_whitelisting() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
+        let addr_bbb = Address::Account(AccountAddress([3u8; 32]));
+        let first_investor = Address::Account(AccountAddress([10u8; 32]));
+        let second_investor = Address::Account(AccountAddress([11u8; 32]));
+        let schedule = SaleSchedule {
+            open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+            close_at: Timestamp::from_timestamp_millis(30),
+            vesting_start: None,
+            vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                Duration::from_days(30),
+                10_000,
+            )])),
+        };
+
+        let initial_state = State {
+            proj_admin,
+            status: SaleStatus::Prepare,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            project_token: None,
+            schedule: schedule.clone(),
+            sold_amount: ContractTokenAmount::from(0),
+            investors: state_builder.new_map(),
+        };
+        let mut expected_investors = state_builder.new_map();
+        expected_investors.insert(
+            first_investor,
+            InvestorState::new(ContractTokenAmount::from(1_000), 20_000),
+        );
+        expected_investors.insert(
+            second_investor,
+            InvestorState::new(ContractTokenAmount::from(5_000), 15_000),
+        );
+        let expected_state = State {
+            proj_admin,
+            status: SaleStatus::Ready,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            project_token: None,
+            schedule,
+            sold_amount: ContractTokenAmount::from(0),
+            investors: expected_investors,
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+
+        let params = WhitelistingParams {
+            wl: vec![
+                AllowedInvestorParams {
+                    investor: first_investor,
+                    allocation: ContractTokenAmount::from(1_000),
+                    price_per_token: 20_000,
+                },
+                AllowedInvestorParams {
+                    investor: second_investor,
+                    allocation: ContractTokenAmount::from(5_000),
+                    price_per_token: 15_000,
+                },
+            ],
+            ready: true,
+        };
+        let params_byte = to_bytes(&params);
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_parameter(&params_byte);
+        let mut logger = TestLogger::init();
+
+        let result = contract_whitelisting(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(
+            *host.state(),
+            expected_state,
+            "state has been changed unexpectedly..."
+        );
+        claim_eq!(
+            logger.logs,
+            vec![
+                to_bytes(&SaleEvent::Whitelisted(WhitelistedEvent {
+                    investor: first_investor,
+                    allocation: ContractTokenAmount::from(1_000),
+                    price_per_token: 20_000,
+                })),
+                to_bytes(&SaleEvent::Whitelisted(WhitelistedEvent {
+                    investor: second_investor,
+                    allocation: ContractTokenAmount::from(5_000),
+                    price_per_token: 15_000,
+                })),
+                to_bytes(&SaleEvent::StatusChanged(SaleStatus::Ready)),
+            ],
+            "events have been logged unexpectedly..."
+        );
+    }
+
+    #[concordium_test]
+    /// Test that setFixed fixes the result of the sale only once.
+    fn test_set_fixed() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
+        let addr_bbb = Address::Account(AccountAddress([3u8; 32]));
+        let schedule = SaleSchedule {
+            open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+            close_at: Timestamp::from_timestamp_millis(30),
+            vesting_start: None,
+            vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                Duration::from_days(30),
+                10_000,
+            )])),
+        };
+
+        let initial_state = State {
+            proj_admin,
+            status: SaleStatus::Ready,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            project_token: None,
+            schedule,
+            sold_amount: ContractTokenAmount::from(1_000),
+            investors: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(31));
+        let mut logger = TestLogger::init();
+
+        let result = contract_set_fixed(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(host.state().status, SaleStatus::Fixed);
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::StatusChanged(SaleStatus::Fixed))],
+            "events have been logged unexpectedly..."
+        );
+
+        // the fixed sale cannot be fixed or cancelled again
+        let result = contract_set_fixed(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("setFixed should reject a sale already fixed");
+        claim_eq!(
+            err,
+            CustomContractError::SaleNotReady.into(),
+            "setFixed should reject a sale already fixed"
+        );
+        claim_eq!(host.state().status, SaleStatus::Fixed);
+    }
+}
@@ -0,0 +1,237 @@
+use concordium_std::concordium_cfg_test;
+
+#[concordium_cfg_test]
+mod tests {
+    use crate::*;
+    use concordium_cis2::{AdditionalData, Receiver, TokenIdUnit, Transfer, TransferParams};
+    use concordium_std::test_infrastructure::*;
+
+    fn schedule(vesting_start: Option<Timestamp>) -> SaleSchedule {
+        SaleSchedule {
+            open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+            close_at: Timestamp::from_timestamp_millis(30),
+            vesting_start,
+            vesting_period: VestingSchedule::Stepped(BTreeMap::from([
+                (Duration::from_days(30), 1_000),
+                (Duration::from_days(180), 4_000),
+                (Duration::from_days(360), 5_000),
+            ])),
+        }
+    }
+
+    #[concordium_test]
+    /// Test that userDeposit only accepts the agreed price of the whole allocation.
+    fn test_user_deposit() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
+        let addr_bbb = Address::Account(AccountAddress([3u8; 32]));
+        let first_investor = AccountAddress([10u8; 32]);
+        let allocation = ContractTokenAmount::from(1_000);
+        let price_per_token = 20_000;
+        let deposit_amount = Amount::from_micro_ccd(20_000 * 1_000);
+
+        let mut investors = state_builder.new_map();
+        investors.insert(
+            Address::Account(first_investor),
+            InvestorState::new(allocation, price_per_token),
+        );
+        let initial_state = State {
+            proj_admin,
+            status: SaleStatus::Ready,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            project_token: None,
+            schedule: schedule(None),
+            sold_amount: ContractTokenAmount::from(0),
+            investors,
+        };
+        let mut expected_investors = state_builder.new_map();
+        expected_investors.insert(
+            Address::Account(first_investor),
+            InvestorState {
+                allocation,
+                price_per_token,
+                deposit_ccd: deposit_amount,
+                claimed_inc: 0,
+            },
+        );
+        let expected_state = State {
+            proj_admin,
+            status: SaleStatus::Ready,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            project_token: None,
+            schedule: schedule(None),
+            sold_amount: allocation,
+            investors: expected_investors,
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(first_investor));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(15));
+
+        // amount must match the price of the allocation
+        let mut logger = TestLogger::init();
+        let result = contract_user_deposit(&ctx, &mut host, Amount::from_micro_ccd(1), &mut logger);
+        let err = result.expect_err_report("userDeposit should reject a partial payment");
+        claim_eq!(
+            err,
+            CustomContractError::InvalidCcdInput.into(),
+            "userDeposit should reject a partial payment"
+        );
+
+        let mut logger = TestLogger::init();
+        let result = contract_user_deposit(&ctx, &mut host, deposit_amount, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(
+            *host.state(),
+            expected_state,
+            "state has been changed unexpectedly..."
+        );
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::Deposited(DepositedEvent {
+                investor: Address::Account(first_investor),
+                amount: deposit_amount,
+            }))],
+            "events have been logged unexpectedly..."
+        );
+
+        // investors can deposit only once
+        let mut logger = TestLogger::init();
+        let result = contract_user_deposit(&ctx, &mut host, deposit_amount, &mut logger);
+        let err = result.expect_err_report("userDeposit should reject a second deposit");
+        claim_eq!(
+            err,
+            CustomContractError::AlreadyDeposited.into(),
+            "userDeposit should reject a second deposit"
+        );
+    }
+
+    #[concordium_test]
+    /// Test that userClaim pays out the vested part of the allocation net of the fee.
+    fn test_user_claim() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
+        let addr_bbb = Address::Account(AccountAddress([3u8; 32]));
+        let first_investor = AccountAddress([10u8; 32]);
+        let self_address = ContractAddress {
+            index: 1000,
+            subindex: 0,
+        };
+        let project_token_address = ContractAddress {
+            index: 2000,
+            subindex: 0,
+        };
+        let allocation = ContractTokenAmount::from(1_000);
+        let price_per_token = 20_000;
+        let deposit_amount = Amount::from_micro_ccd(20_000 * 1_000);
+        let vesting_start = Timestamp::from_timestamp_millis(100);
+        let slot_time = vesting_start.checked_add(Duration::from_days(200)).unwrap();
+        // (1000 * 90%) * (10% + 40%)
+        let expected_claim_amount = ContractTokenAmount::from(450);
+
+        let mut investors = state_builder.new_map();
+        investors.insert(
+            Address::Account(first_investor),
+            InvestorState {
+                allocation,
+                price_per_token,
+                deposit_ccd: deposit_amount,
+                claimed_inc: 0,
+            },
+        );
+        let initial_state = State {
+            proj_admin,
+            status: SaleStatus::Fixed,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            project_token: Some(project_token_address),
+            schedule: schedule(Some(vesting_start)),
+            sold_amount: allocation,
+            investors,
+        };
+        let mut expected_investors = state_builder.new_map();
+        expected_investors.insert(
+            Address::Account(first_investor),
+            InvestorState {
+                allocation,
+                price_per_token,
+                deposit_ccd: deposit_amount,
+                claimed_inc: 2,
+            },
+        );
+        let expected_state = State {
+            proj_admin,
+            status: SaleStatus::Fixed,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            project_token: Some(project_token_address),
+            schedule: schedule(Some(vesting_start)),
+            sold_amount: allocation,
+            investors: expected_investors,
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+
+        host.setup_mock_entrypoint(
+            project_token_address,
+            OwnedEntrypointName::new_unchecked("transfer".into()),
+            MockFn::new_v1(move |parameter, _amount, _balance, _state| {
+                let transfer = Transfer {
+                    from: Address::from(self_address),
+                    to: Receiver::Account(first_investor),
+                    token_id: TokenIdUnit(),
+                    amount: expected_claim_amount,
+                    data: AdditionalData::empty(),
+                };
+                let transfer_params = TransferParams::from(vec![transfer]);
+                let expected_bytes = to_bytes(&transfer_params);
+                let param_bytes = parameter.as_ref();
+                claim_eq!(param_bytes, expected_bytes);
+                Ok((false, ()))
+            }),
+        );
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_self_address(self_address);
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(first_investor));
+        ctx.set_metadata_slot_time(slot_time);
+        let mut logger = TestLogger::init();
+
+        let result = contract_user_claim(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(
+            *host.state(),
+            expected_state,
+            "state has been changed unexpectedly..."
+        );
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::Claimed(ClaimedEvent {
+                investor: Address::Account(first_investor),
+                amount: expected_claim_amount,
+                inc: 2,
+            }))],
+            "events have been logged unexpectedly..."
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use concordium_std::{SchemaType, Serialize, *};
+pub use sale_utils::{
+    error::{ContractError, ContractResult, CustomContractError},
+    sale::*,
+    types::*,
+};
+use sale_utils::{
+    rido::{FeeReceiver, RidoFees, RidoState},
+    PRIVATE_RIDO_FEE_BBB, PRIVATE_RIDO_FEE_OVL,
+};
+
+/// The contract state
+#[derive(Debug, Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+pub struct State<S: HasStateApi> {
+    /// Account of the administrator of the entity running the IDO
+    pub(crate) proj_admin: AccountAddress,
+    /// Enum for sale status
+    pub(crate) status: SaleStatus,
+    /// If `true`, some functions will stop working
+    pub(crate) paused: bool,
+    /// Address of Overlay for receiving sale fee
+    pub(crate) addr_ovl: Address,
+    /// Address of Overlay for buy back burn
+    pub(crate) addr_bbb: Address,
+    /// Number of how many fee received
+    pub(crate) ovl_claimed_inc: u8,
+    /// Number of how many fee for BBB received
+    pub(crate) bbb_claimed_inc: u8,
+    /// Project token contract address for RIDO
+    pub(crate) project_token: Option<ContractAddress>,
+    /// Sale schedule(only one phase, vesting independent from the public round)
+    pub(crate) schedule: SaleSchedule,
+    /// Total project tokens allocated to the investors who have deposited
+    pub(crate) sold_amount: ContractTokenAmount,
+    /// Whitelisted investors
+    pub(crate) investors: StateMap<Address, InvestorState, S>,
+}
+
+impl<S: HasStateApi> State<S> {
+    pub(crate) fn new(
+        state_builder: &mut StateBuilder<S>,
+        proj_admin: AccountAddress,
+        addr_ovl: Address,
+        addr_bbb: Address,
+        schedule: SaleSchedule,
+    ) -> Self {
+        State {
+            proj_admin,
+            paused: false,
+            status: SaleStatus::Prepare,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            project_token: None,
+            schedule,
+            sold_amount: ContractTokenAmount::from(0),
+            investors: state_builder.new_map(),
+        }
+    }
+
+    pub(crate) fn calc_vesting_amount(
+        &self,
+        now: Timestamp,
+        vesting_start: Timestamp,
+        allocation: ContractTokenAmount,
+        shared: BasisPoints,
+        cur_inc: u8,
+    ) -> ContractResult<(ContractTokenAmount, u8)> {
+        self.schedule.calc_vesting_amount_of(
+            now,
+            vesting_start,
+            u128::from(allocation.0),
+            shared,
+            cur_inc,
+        )
+    }
+
+    pub(crate) fn whitelisting(
+        &mut self,
+        investor: &Address,
+        allocation: ContractTokenAmount,
+        price_per_token: MicroCcd,
+    ) -> Result<(), CustomContractError> {
+        let investor_state = InvestorState::new(allocation, price_per_token);
+        // Reject allocations which could never be paid for
+        ensure!(
+            investor_state.price()? > Amount::zero(),
+            CustomContractError::InvalidCcdInput
+        );
+        self.investors.insert(*investor, investor_state);
+        Ok(())
+    }
+
+    pub(crate) fn get_investor(&self, investor: &Address) -> ContractResult<InvestorState> {
+        let investor = self
+            .investors
+            .get(investor)
+            .ok_or(ContractError::Unauthorized)?;
+        Ok(investor.clone())
+    }
+
+    pub(crate) fn deposit(&mut self, investor: &Address, amount: Amount) -> ContractResult<()> {
+        let mut investor = self
+            .investors
+            .get_mut(investor)
+            .ok_or(ContractError::Unauthorized)?;
+        investor.deposit_ccd = amount;
+
+        self.sold_amount.0 = self
+            .sold_amount
+            .0
+            .checked_add(investor.allocation.0)
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+        Ok(())
+    }
+
+    pub(crate) fn increment_investor_claimed(
+        &mut self,
+        investor: &Address,
+        n: u8,
+    ) -> ContractResult<()> {
+        let mut investor = self
+            .investors
+            .get_mut(investor)
+            .ok_or(ContractError::Unauthorized)?;
+        investor.claimed_inc = n;
+        Ok(())
+    }
+}
+
+impl<S: HasStateApi> RidoState for State<S> {
+    fn proj_admin(&self) -> AccountAddress {
+        self.proj_admin
+    }
+
+    fn status(&self) -> &SaleStatus {
+        &self.status
+    }
+
+    fn paused(&self) -> bool {
+        self.paused
+    }
+
+    fn paused_mut(&mut self) -> &mut bool {
+        &mut self.paused
+    }
+
+    fn project_token(&self) -> Option<ContractAddress> {
+        self.project_token
+    }
+
+    fn project_token_mut(&mut self) -> &mut Option<ContractAddress> {
+        &mut self.project_token
+    }
+
+    fn schedule(&self) -> &SaleSchedule {
+        &self.schedule
+    }
+
+    fn schedule_mut(&mut self) -> &mut SaleSchedule {
+        &mut self.schedule
+    }
+}
+
+impl<S: HasStateApi> RidoFees for State<S> {
+    fn sold_tokens(&self) -> ContractResult<ContractTokenAmount> {
+        Ok(self.sold_amount)
+    }
+
+    fn fee_receiver(&self, fee: FeeReceiver) -> Address {
+        match fee {
+            FeeReceiver::Ovl => self.addr_ovl,
+            FeeReceiver::Bbb => self.addr_bbb,
+        }
+    }
+
+    fn fee_share(&self, fee: FeeReceiver) -> BasisPoints {
+        match fee {
+            FeeReceiver::Ovl => PRIVATE_RIDO_FEE_OVL,
+            FeeReceiver::Bbb => PRIVATE_RIDO_FEE_BBB,
+        }
+    }
+
+    fn fee_claimed_inc(&self, fee: FeeReceiver) -> u8 {
+        match fee {
+            FeeReceiver::Ovl => self.ovl_claimed_inc,
+            FeeReceiver::Bbb => self.bbb_claimed_inc,
+        }
+    }
+
+    fn set_fee_claimed_inc(&mut self, fee: FeeReceiver, inc: u8) {
+        match fee {
+            FeeReceiver::Ovl => self.ovl_claimed_inc = inc,
+            FeeReceiver::Bbb => self.bbb_claimed_inc = inc,
+        }
+    }
+}
+
+#[cfg(any(feature = "wasm-test", test))]
+/// implements PartialEq for `claim_eq` inside test functions.
+/// this implementation will be build only when `concordium-std/wasm-test` feature is active.
+/// (e.g. when launched by `cargo concordium test`)
+impl<S: HasStateApi> PartialEq for State<S> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.proj_admin != other.proj_admin
+            || self.status != other.status
+            || self.paused != other.paused
+            || self.addr_ovl != other.addr_ovl
+            || self.addr_bbb != other.addr_bbb
+            || self.ovl_claimed_inc != other.ovl_claimed_inc
+            || self.bbb_claimed_inc != other.bbb_claimed_inc
+            || self.project_token != other.project_token
+            || self.schedule != other.schedule
+            || self.sold_amount != other.sold_amount
+        {
+            return false;
+        }
+        if self.investors.iter().count() != other.investors.iter().count() {
+            return false;
+        }
+        for (addr, investor_state) in self.investors.iter() {
+            match other.investors.get(&addr) {
+                Some(other_state) if investor_state.clone() == other_state.clone() => {},
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+/// About private sale investors
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct InvestorState {
+    /// Amount of project tokens the investor can purchase
+    pub(crate) allocation: ContractTokenAmount,
+    /// Price in ccd per a project token agreed with the investor
+    pub(crate) price_per_token: MicroCcd,
+    /// If deposited, their right to receive tokens will be confirmed.
+    pub(crate) deposit_ccd: Amount,
+    /// Number of tokens received during the vesting period(neither Amount or number of claim)
+    pub(crate) claimed_inc: u8,
+}
+
+impl InvestorState {
+    pub fn new(allocation: ContractTokenAmount, price_per_token: MicroCcd) -> Self {
+        InvestorState {
+            allocation,
+            price_per_token,
+            deposit_ccd: Amount::zero(),
+            claimed_inc: 0,
+        }
+    }
+
+    /// CCD to be deposited for the whole allocation.
+    pub(crate) fn price(&self) -> Result<Amount, CustomContractError> {
+        let price = self
+            .price_per_token
+            .checked_mul(self.allocation.0)
+            .ok_or(CustomContractError::OverflowError)?;
+        Ok(Amount::from_micro_ccd(price))
+    }
+
+    pub(crate) fn is_deposited(&self) -> bool {
+        self.deposit_ccd > Amount::zero()
+    }
+}
@@ -0,0 +1,64 @@
+use crate::state::{State, *};
+use concordium_std::*;
+
+#[derive(Debug, Serialize, SchemaType)]
+struct ViewResponse {
+    proj_admin: AccountAddress,
+    status: SaleStatus,
+    paused: bool,
+    addr_ovl: Address,
+    addr_bbb: Address,
+    ovl_claimed_inc: u8,
+    bbb_claimed_inc: u8,
+    project_token: Option<ContractAddress>,
+    schedule: SaleSchedule,
+    sold_amount: ContractTokenAmount,
+}
+
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "view",
+    return_value = "ViewResponse"
+)]
+fn contract_view<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewResponse> {
+    let state = host.state();
+
+    Ok(ViewResponse {
+        proj_admin: state.proj_admin,
+        status: state.status.clone(),
+        paused: state.paused,
+        addr_ovl: state.addr_ovl,
+        addr_bbb: state.addr_bbb,
+        ovl_claimed_inc: state.ovl_claimed_inc,
+        bbb_claimed_inc: state.bbb_claimed_inc,
+        project_token: state.project_token,
+        schedule: state.schedule.clone(),
+        sold_amount: state.sold_amount,
+    })
+}
+
+// ------------------------------------------
+
+type ViewInvestorsResponse = Vec<(Address, InvestorState)>;
+
+#[receive(
+    contract = "priv_rido_ccd",
+    name = "viewInvestors",
+    return_value = "ViewInvestorsResponse"
+)]
+fn contract_view_investors<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewInvestorsResponse> {
+    let state = host.state();
+
+    let mut ret: Vec<(Address, InvestorState)> = Vec::new();
+    for (addr, investor_state) in state.investors.iter() {
+        ret.push((*addr, investor_state.clone()));
+    }
+
+    Ok(ret)
+}
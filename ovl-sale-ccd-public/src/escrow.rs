@@ -0,0 +1,291 @@
+use crate::state::{State, *};
+use concordium_std::*;
+use sale_utils::BPS_DENOMINATOR;
+
+/// Maximum number of tranches, as the votes of each participant are kept in a bitmap.
+pub(crate) const MAX_TRANCHES: usize = 64;
+
+/// Condition to be met before a tranche of the raised CCD is released.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub enum Milestone {
+    /// Project tokens for every unit sold have been deposited to the pool
+    PoolFunded,
+    /// TGE has been set
+    TgeSet,
+    /// The given time has passed
+    At(Timestamp),
+}
+
+/// The parameter schema for the escrow mode of `InitParams`.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct EscrowParams {
+    /// Milestones and the share of the raised CCD released at each, in basis points.
+    /// The shares must add up to 100%.
+    pub tranches: Vec<(Milestone, BasisPoints)>,
+    /// If set, participants can approve a tranche by voting,
+    /// once the voters have paid this share of the raised CCD(in basis points).
+    pub vote_quorum: Option<BasisPoints>,
+}
+
+/// A part of the raised CCD released when its milestone is reached and it is approved.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct Tranche {
+    /// Condition to be met before release
+    pub(crate) milestone: Milestone,
+    /// Share of the raised CCD, in basis points
+    pub(crate) bps: BasisPoints,
+    /// CCD paid by the participants who voted for this tranche
+    pub(crate) votes: Amount,
+    /// Whether the Overlay team or the participants have approved this tranche
+    pub(crate) approved: bool,
+    /// Whether this tranche has been paid out by `projectClaim`
+    pub(crate) released: bool,
+}
+
+/// Escrow of the raised CCD, released to the project in tranches tied to milestones
+/// instead of all at once at `projectClaim`.
+/// If the project is declared failed, CCD not yet released is refunded to the participants
+/// in proportion to what they paid.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct Escrow {
+    pub(crate) tranches: Vec<Tranche>,
+    pub(crate) vote_quorum: Option<BasisPoints>,
+    /// CCD raised by the sale(fixed at `setFixed`)
+    pub(crate) raised: Amount,
+    /// CCD released to the project so far
+    pub(crate) released: Amount,
+    /// Whether the project has been declared failed
+    pub(crate) failed: bool,
+    /// CCD not yet refunded after the failure
+    pub(crate) refundable: Amount,
+}
+
+impl Escrow {
+    pub(crate) fn new(params: EscrowParams) -> Result<Self, CustomContractError> {
+        let total = params
+            .tranches
+            .iter()
+            .fold(0u32, |total, (_, bps)| total + u32::from(*bps));
+        ensure!(
+            !params.tranches.is_empty()
+                && params.tranches.len() <= MAX_TRANCHES
+                && total == u32::from(BPS_DENOMINATOR)
+                && params.tranches.iter().all(|(_, bps)| *bps > 0)
+                && params
+                    .vote_quorum
+                    .map_or(true, |quorum| quorum > 0 && quorum <= BPS_DENOMINATOR),
+            CustomContractError::InvalidEscrow
+        );
+
+        Ok(Escrow {
+            tranches: params
+                .tranches
+                .into_iter()
+                .map(|(milestone, bps)| Tranche {
+                    milestone,
+                    bps,
+                    votes: Amount::zero(),
+                    approved: false,
+                    released: false,
+                })
+                .collect(),
+            vote_quorum: params.vote_quorum,
+            raised: Amount::zero(),
+            released: Amount::zero(),
+            failed: false,
+            refundable: Amount::zero(),
+        })
+    }
+
+    /// CCD which is not released to the project.
+    fn unreleased(&self) -> Amount {
+        self.raised - self.released
+    }
+}
+
+impl<S: HasStateApi> State<S> {
+    pub(crate) fn get_escrow(&self) -> ContractResult<&Escrow> {
+        self.escrow
+            .as_ref()
+            .ok_or(ContractError::from(CustomContractError::NotEscrow))
+    }
+
+    /// Tranche at `index`, which is neither approved nor failed with the escrow.
+    fn pending_tranche(&mut self, index: u8) -> ContractResult<&mut Tranche> {
+        let escrow = self
+            .escrow
+            .as_mut()
+            .ok_or(ContractError::from(CustomContractError::NotEscrow))?;
+        ensure!(!escrow.failed, CustomContractError::EscrowFailed.into());
+        let tranche = escrow
+            .tranches
+            .get_mut(usize::from(index))
+            .ok_or(ContractError::from(CustomContractError::InvalidEscrow))?;
+        ensure!(!tranche.approved, CustomContractError::Inappropriate.into());
+        Ok(tranche)
+    }
+
+    /// Fixes the CCD raised once the sale has reached the softcap.
+    /// It cannot be fixed again, as the tranches released so far are shares of it.
+    pub(crate) fn fix_escrow(&mut self, raised: Amount) -> ContractResult<()> {
+        if let Some(escrow) = self.escrow.as_mut() {
+            ensure!(
+                escrow.raised == Amount::zero(),
+                CustomContractError::Inappropriate.into()
+            );
+            escrow.raised = raised;
+        }
+        Ok(())
+    }
+
+    /// CCD paid by a user for the tokens purchased. Rejects users who have not purchased any.
+    fn paid_ccd_of(&self, user: &Address) -> ContractResult<u128> {
+        let user = self
+            .participants
+            .get(user)
+            .ok_or(ContractError::Unauthorized)?;
+        let tokens = if self.overflow.is_some() {
+            u128::from(user.alloc_tokens.0)
+        } else {
+            u128::from(self.saleinfo.token_per_unit.0)
+                .checked_mul(u128::from(user.win_units))
+                .ok_or(ContractError::from(CustomContractError::OverflowError))?
+        };
+        ensure!(tokens > 0, CustomContractError::NotDeposited.into());
+        tokens
+            .checked_mul(u128::from(self.saleinfo.price_per_token))
+            .ok_or(ContractError::from(CustomContractError::OverflowError))
+    }
+
+    /// CCD paid by all users for the tokens sold.
+    fn total_paid(&self) -> ContractResult<u128> {
+        u128::from(self.sold_tokens()?.0)
+            .checked_mul(u128::from(self.saleinfo.price_per_token))
+            .ok_or(ContractError::from(CustomContractError::OverflowError))
+    }
+
+    /// Approves a tranche on behalf of the Overlay team.
+    pub(crate) fn approve_tranche(&mut self, index: u8) -> ContractResult<()> {
+        self.pending_tranche(index)?.approved = true;
+        Ok(())
+    }
+
+    /// Adds the vote of a user weighted by the CCD they paid.
+    /// Returns the votes for the tranche and whether they have reached the quorum.
+    pub(crate) fn vote_tranche(
+        &mut self,
+        voter: &Address,
+        index: u8,
+    ) -> ContractResult<(Amount, bool)> {
+        let quorum = self
+            .get_escrow()?
+            .vote_quorum
+            .ok_or(ContractError::from(CustomContractError::Inappropriate))?;
+        let total_paid = self.total_paid()?;
+        self.pending_tranche(index)?;
+        let paid = self.paid_ccd_of(voter)?;
+
+        // the index is below MAX_TRANCHES as the tranche exists
+        let bit = 1u64 << index;
+        let mut user = self
+            .participants
+            .get_mut(voter)
+            .ok_or(ContractError::Unauthorized)?;
+        ensure!(
+            user.escrow_votes & bit == 0,
+            CustomContractError::AlreadyVoted.into()
+        );
+        user.escrow_votes |= bit;
+        drop(user);
+
+        let tranche = self.pending_tranche(index)?;
+        tranche.votes +=
+            Amount::from_micro_ccd(u64::try_from(paid).map_err(CustomContractError::from)?);
+        let votes = u128::from(tranche.votes.micro_ccd);
+        tranche.approved = votes * u128::from(BPS_DENOMINATOR) >= total_paid * u128::from(quorum);
+        Ok((tranche.votes, tranche.approved))
+    }
+
+    fn is_milestone_reached(&self, milestone: &Milestone, now: Timestamp) -> ContractResult<bool> {
+        Ok(match milestone {
+            Milestone::PoolFunded => self.is_pool_funded()?,
+            Milestone::TgeSet => self.schedule.vesting_start.is_some(),
+            Milestone::At(time) => now >= *time,
+        })
+    }
+
+    /// Releases every approved tranche whose milestone has been reached.
+    /// Returns the indexes of the tranches released and the CCD to be paid to the project.
+    pub(crate) fn release_tranches(&mut self, now: Timestamp) -> ContractResult<(Vec<u8>, Amount)> {
+        let escrow = self.get_escrow()?;
+        ensure!(!escrow.failed, CustomContractError::EscrowFailed.into());
+
+        let mut indexes: Vec<u8> = Vec::new();
+        let mut released_bps: u128 = 0;
+        for (index, tranche) in escrow.tranches.iter().enumerate() {
+            if tranche.released {
+                released_bps += u128::from(tranche.bps);
+            } else if tranche.approved && self.is_milestone_reached(&tranche.milestone, now)? {
+                released_bps += u128::from(tranche.bps);
+                indexes.push(index as u8);
+            }
+        }
+        ensure!(
+            !indexes.is_empty(),
+            CustomContractError::MilestoneNotReached.into()
+        );
+
+        // Released amounts are cumulative, so the last tranche pays out the rounding remainder.
+        let target =
+            u128::from(escrow.raised.micro_ccd) * released_bps / u128::from(BPS_DENOMINATOR);
+        let target =
+            Amount::from_micro_ccd(u64::try_from(target).map_err(CustomContractError::from)?);
+        let amount = target
+            .checked_sub(escrow.released)
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+
+        if let Some(escrow) = self.escrow.as_mut() {
+            for index in indexes.iter() {
+                escrow.tranches[usize::from(*index)].released = true;
+            }
+            escrow.released = target;
+        }
+        Ok((indexes, amount))
+    }
+
+    /// Declares the project failed. Returns the CCD to be refunded to the participants.
+    pub(crate) fn fail_escrow(&mut self) -> ContractResult<Amount> {
+        let escrow = self
+            .escrow
+            .as_mut()
+            .ok_or(ContractError::from(CustomContractError::NotEscrow))?;
+        ensure!(!escrow.failed, CustomContractError::EscrowFailed.into());
+        escrow.failed = true;
+        escrow.refundable = escrow.unreleased();
+        Ok(escrow.refundable)
+    }
+
+    /// Marks a user refunded and returns their share of the CCD not released.
+    pub(crate) fn refund_escrow(&mut self, user: &Address) -> ContractResult<Amount> {
+        let escrow = self.get_escrow()?;
+        ensure!(escrow.failed, CustomContractError::Inappropriate.into());
+        let unreleased = u128::from(escrow.unreleased().micro_ccd);
+        let total_paid = self.total_paid()?;
+        let paid = self.paid_ccd_of(user)?;
+
+        let mut user = self
+            .participants
+            .get_mut(user)
+            .ok_or(ContractError::Unauthorized)?;
+        ensure!(!user.refunded, CustomContractError::AlreadyRefunded.into());
+        user.refunded = true;
+        drop(user);
+
+        // paid never exceeds total_paid, so the share fits in u64
+        let amount = Amount::from_micro_ccd((paid * unreleased / total_paid) as u64);
+        if let Some(escrow) = self.escrow.as_mut() {
+            escrow.refundable -= amount;
+        }
+        Ok(amount)
+    }
+}
@@ -0,0 +1,266 @@
+use crate::{lottery::LotterySeed, multisig::*, roles::Role, state::*, timelock::ChangeId};
+use concordium_cis2::TokenIdVec;
+use concordium_std::*;
+use sale_utils::merkle::MerkleRoot;
+
+/// Events logged by the sale contract so that indexers can follow
+/// every state transition without diffing the views.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+#[concordium(repr(u8))]
+pub enum SaleEvent {
+    /// A user has been added to the whitelist.
+    Whitelisted(WhitelistedEvent),
+    /// A participant has deposited CCD to fix their allocation.
+    Deposited(DepositedEvent),
+    /// A participant has quit the sale and been refunded.
+    Quit(QuitEvent),
+    /// A participant has claimed vested project tokens.
+    Claimed(ClaimedEvent),
+    /// A sale fee has been paid out in project tokens.
+    FeeClaimed(FeeClaimedEvent),
+    /// The sale status has changed.
+    StatusChanged(SaleStatus),
+    /// The contract has been paused(`true`) or unpaused(`false`).
+    Paused(bool),
+    /// TGE(the beginning of the vesting period) has been set.
+    TgeSet(Timestamp),
+    /// Project token contract has been set.
+    ProjectTokenSet(ContractAddress),
+    /// An emergency admin action has been proposed.
+    ProposalCreated(ProposalCreatedEvent),
+    /// A signer has approved a proposal.
+    ProposalApproved(ProposalApprovedEvent),
+    /// A proposal has been executed.
+    ProposalExecuted(ProposalExecutedEvent),
+    /// A proposal has been cancelled.
+    ProposalCancelled(ProposalId),
+    /// A role has been granted.
+    RoleGranted(RoleEvent),
+    /// A role has been revoked.
+    RoleRevoked(RoleEvent),
+    /// An approved change has been queued until its effective time.
+    ChangeQueued(ChangeQueuedEvent),
+    /// A queued change has been applied.
+    ChangeExecuted(ChangeExecutedEvent),
+    /// A queued change has been cancelled.
+    ChangeCancelled(ChangeId),
+    /// A user has deposited CCD whose units are fixed after the sale closes
+    /// (lottery or overflow mode).
+    Registered(DepositedEvent),
+    /// The lottery seed has been revealed and the winners drawn.
+    LotteryDrawn(LotteryDrawnEvent),
+    /// A lottery loser has been refunded their deposit.
+    Reclaimed(QuitEvent),
+    /// Tokens have been allocated pro-rata to the deposits.
+    OverflowSettled(OverflowSettledEvent),
+    /// A user has been refunded CCD which did not buy any token.
+    ExcessRefunded(QuitEvent),
+    /// The entrypoint through which contract participants are refunded has been set.
+    RefundHookSet(Option<OwnedEntrypointName>),
+    /// A participant of a suspended sale has been refunded by `refundBatch`.
+    Refunded(QuitEvent),
+    /// Project tokens have been deposited to the pool.
+    PoolFunded(PoolFundedEvent),
+    /// CIS-2 tokens not needed for the claims have been withdrawn.
+    TokensRecovered(TokensRecoveredEvent),
+    /// Project tokens left in the pool by rounding have been swept after every claim.
+    DustSwept(DustSweptEvent),
+    /// CCD raised has been paid out to the project admin or a CCD fee recipient by `projectClaim`.
+    CcdPaid(CcdPaidEvent),
+    /// A tranche of the escrow has been approved by the Overlay team or the participants.
+    TrancheApproved(u8),
+    /// A participant has voted for a tranche of the escrow.
+    TrancheVoted(TrancheVotedEvent),
+    /// A tranche of the escrow has been released to the project.
+    TrancheReleased(u8),
+    /// The project has been declared failed, with the CCD left in the escrow to be refunded.
+    EscrowFailed(Amount),
+    /// A participant has been refunded their share of the escrow.
+    EscrowRefunded(QuitEvent),
+    /// The entrypoint through which contracts receive claimed tokens has been set.
+    ClaimHookSet(Option<OwnedEntrypointName>),
+    /// The root of the Merkle tree over the whitelist has been set.
+    WhitelistRootSet(Option<MerkleRoot>),
+    /// The nonce of an account has been used by `permit`.
+    /// Tagged 250 as CIS-3 requires.
+    #[concordium(tag = 250)]
+    Nonce(NonceEvent),
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct WhitelistedEvent {
+    /// Whitelisted user
+    pub(crate) user: Address,
+    /// Priority for participation in the sale
+    pub(crate) prior: Prior,
+    /// Tier of the user
+    pub(crate) tier: Tier,
+    /// Number of units the user can purchase
+    pub(crate) tgt_units: u8,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct DepositedEvent {
+    /// Participant who deposited
+    pub(crate) user: Address,
+    /// Deposited CCD
+    pub(crate) amount: Amount,
+    /// Number of units fixed by the deposit
+    pub(crate) units: u8,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct QuitEvent {
+    /// Participant who quit
+    pub(crate) user: Address,
+    /// Refunded CCD
+    pub(crate) amount: Amount,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct ClaimedEvent {
+    /// Participant who claimed
+    pub(crate) user: Address,
+    /// Amount of project tokens transferred
+    pub(crate) amount: ContractTokenAmount,
+    /// Number of vesting steps received so far
+    pub(crate) inc: u8,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct FeeClaimedEvent {
+    /// Receiver of the fee
+    pub(crate) to: Address,
+    /// Amount of project tokens transferred
+    pub(crate) amount: ContractTokenAmount,
+    /// Number of vesting steps received so far
+    pub(crate) inc: u8,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct ProposalCreatedEvent {
+    /// Id of the new proposal
+    pub(crate) id: ProposalId,
+    /// Signer who proposed
+    pub(crate) proposer: AccountAddress,
+    /// Proposed action
+    pub(crate) action: AdminAction,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct ProposalApprovedEvent {
+    /// Id of the approved proposal
+    pub(crate) id: ProposalId,
+    /// Signer who approved
+    pub(crate) signer: AccountAddress,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct ProposalExecutedEvent {
+    /// Id of the executed proposal
+    pub(crate) id: ProposalId,
+    /// Executed action
+    pub(crate) action: AdminAction,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct RoleEvent {
+    /// Address the role is granted to(revoked from)
+    pub(crate) address: Address,
+    /// Granted(revoked) role
+    pub(crate) role: Role,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct ChangeQueuedEvent {
+    /// Id of the queued change
+    pub(crate) id: ChangeId,
+    /// Queued action
+    pub(crate) action: AdminAction,
+    /// The change can be applied at or after this time
+    pub(crate) effective_at: Timestamp,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct ChangeExecutedEvent {
+    /// Id of the applied change
+    pub(crate) id: ChangeId,
+    /// Applied action
+    pub(crate) action: AdminAction,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct LotteryDrawnEvent {
+    /// Revealed seed, whose sha256 hash was committed at init
+    pub(crate) seed: LotterySeed,
+    /// Number of winners
+    pub(crate) winners: u32,
+    /// Total units fixed by the draw
+    pub(crate) applied_units: UnitsAmount,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct OverflowSettledEvent {
+    /// Project tokens allocated to all users
+    pub(crate) allocated_tokens: ContractTokenAmount,
+    /// CCD to be refunded to all users
+    pub(crate) refundable: Amount,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct PoolFundedEvent {
+    /// Address the project tokens were transferred from
+    pub(crate) from: Address,
+    /// Project tokens deposited by this transfer
+    pub(crate) amount: ContractTokenAmount,
+    /// Project tokens deposited in total
+    pub(crate) funded: ContractTokenAmount,
+    /// Project tokens to be deposited in total(the amount sold)
+    pub(crate) required: ContractTokenAmount,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct TokensRecoveredEvent {
+    /// CIS-2 contract of the tokens
+    pub(crate) token: ContractAddress,
+    /// Id of the tokens
+    pub(crate) token_id: TokenIdVec,
+    /// Withdrawn amount
+    pub(crate) amount: ContractTokenAmount,
+    /// Receiver of the tokens
+    pub(crate) to: Address,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct DustSweptEvent {
+    /// Swept amount of the project token
+    pub(crate) amount: ContractTokenAmount,
+    /// Receiver of the tokens
+    pub(crate) to: Address,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct CcdPaidEvent {
+    /// Receiver of the CCD
+    pub(crate) to: Address,
+    /// Amount of CCD transferred
+    pub(crate) amount: Amount,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct TrancheVotedEvent {
+    /// Voter
+    pub(crate) user: Address,
+    /// Index of the tranche
+    pub(crate) index: u8,
+    /// CCD paid by all voters for the tranche so far
+    pub(crate) votes: Amount,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct NonceEvent {
+    /// Account which signed the message
+    pub(crate) account: AccountAddress,
+    /// Nonce used
+    pub(crate) nonce: u64,
+}
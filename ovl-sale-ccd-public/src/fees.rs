@@ -0,0 +1,123 @@
+use crate::state::{State, *};
+use concordium_std::{collections::BTreeMap, *};
+use sale_utils::BPS_DENOMINATOR;
+
+/// A recipient of the sale fee and its claim progress.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct FeeState {
+    /// Share of the tokens sold, in basis points
+    pub(crate) bps: BasisPoints,
+    /// Share of the CCD raised, paid out at `projectClaim`, in basis points
+    pub(crate) ccd_bps: BasisPoints,
+    /// Number of vesting steps claimed
+    pub(crate) claimed_inc: u8,
+    /// Amount of the fee received
+    pub(crate) claimed_amount: ContractTokenAmount,
+}
+
+impl FeeState {
+    pub(crate) fn new(bps: BasisPoints) -> Self {
+        FeeState {
+            bps,
+            ccd_bps: 0,
+            claimed_inc: 0,
+            claimed_amount: ContractTokenAmount::from(0),
+        }
+    }
+}
+
+/// The parameter schema for `ChangeFeeRecipient` admin action.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct ChangeFeeRecipientParams {
+    /// Current recipient in the fee table
+    pub(crate) from: Address,
+    /// New recipient taking over its share and claim progress
+    pub(crate) to: Address,
+}
+
+/// Entrypoint called on contract recipients to pay their CCD fee.
+pub(crate) const CCD_FEE_ENTRYPOINT: EntrypointName =
+    EntrypointName::new_unchecked("receiveCcdFee");
+
+/// Users' share and the fees must add up to `BPS_DENOMINATOR`(100%).
+/// The CCD fees must not exceed 100%, the rest of the CCD raised goes to the project admin.
+pub(crate) fn validate_fees(
+    user_share: BasisPoints,
+    fees: &BTreeMap<Address, BasisPoints>,
+    ccd_fees: &BTreeMap<Address, BasisPoints>,
+) -> Result<(), CustomContractError> {
+    let sum = |init: u32, shares: &BTreeMap<Address, BasisPoints>| {
+        shares
+            .values()
+            .fold(init, |total, bps| total + u32::from(*bps))
+    };
+    ensure!(
+        sum(u32::from(user_share), fees) == u32::from(BPS_DENOMINATOR)
+            && sum(0, ccd_fees) <= u32::from(BPS_DENOMINATOR)
+            && fees.values().chain(ccd_fees.values()).all(|bps| *bps > 0),
+        CustomContractError::InvalidFeeTable
+    );
+    Ok(())
+}
+
+impl<S: HasStateApi> State<S> {
+    /// Splits the CCD raised between the CCD fee recipients and the project admin.
+    /// The project admin receives what is left after rounding down every fee.
+    pub(crate) fn ccd_payouts(&self, balance: Amount) -> Vec<(Address, Amount)> {
+        let mut rest = balance;
+        let mut payouts: Vec<(Address, Amount)> = self
+            .fees
+            .iter()
+            .filter(|(_, fee)| fee.ccd_bps > 0)
+            .map(|(recipient, fee)| {
+                // bps never exceeds BPS_DENOMINATOR, so the share fits in u64
+                let amount = (u128::from(balance.micro_ccd) * u128::from(fee.ccd_bps)
+                    / u128::from(BPS_DENOMINATOR)) as u64;
+                rest -= Amount::from_micro_ccd(amount);
+                (*recipient, Amount::from_micro_ccd(amount))
+            })
+            .collect();
+        payouts.push((Address::Account(self.proj_admin), rest));
+        payouts
+    }
+
+    pub(crate) fn get_fee(&self, recipient: &Address) -> ContractResult<FeeState> {
+        self.fees.get(recipient).cloned().ok_or(ContractError::from(
+            CustomContractError::UnknownFeeRecipient,
+        ))
+    }
+
+    /// Records the fee paid to a recipient.
+    pub(crate) fn increment_fee_claimed(
+        &mut self,
+        recipient: &Address,
+        inc: u8,
+        amount: ContractTokenAmount,
+    ) -> ContractResult<()> {
+        let fee = self.fees.get_mut(recipient).ok_or(ContractError::from(
+            CustomContractError::UnknownFeeRecipient,
+        ))?;
+        if inc > fee.claimed_inc {
+            fee.claimed_inc = inc;
+        }
+        fee.claimed_amount += amount;
+        Ok(())
+    }
+
+    /// Moves the share and claim progress of a fee recipient to another address.
+    pub(crate) fn change_fee_recipient(
+        &mut self,
+        from: &Address,
+        to: Address,
+    ) -> ContractResult<()> {
+        ensure!(
+            !self.fees.contains_key(&to),
+            CustomContractError::InvalidFeeTable.into()
+        );
+        let fee = self.fees.remove(from).ok_or(ContractError::from(
+            CustomContractError::UnknownFeeRecipient,
+        ))?;
+        self.fees.insert(to, fee);
+        Ok(())
+    }
+}
@@ -1,31 +1,70 @@
 //! This Contract is used for public sale with CCD on the Overlay IDO platform.
 #[cfg(any(feature = "wasm-test", test))]
 mod sctest;
+mod escrow;
+mod events;
+mod fees;
+mod lottery;
+mod merkle;
+mod migration;
+mod multisig;
+mod overflow;
+mod participants;
+mod permit;
+mod recovery;
+mod refund;
+mod roles;
 mod state;
+mod ticket;
+mod timelock;
 mod view;
 
-use concordium_cis2::{
-    AdditionalData, OnReceivingCis2Params, Receiver, TokenIdUnit, Transfer, TransferParams,
+use concordium_cis2::{OnReceivingCis2Params, TokenIdVec};
+use concordium_std::{
+    collections::{BTreeMap, BTreeSet},
+    *,
+};
+use escrow::*;
+use events::*;
+use fees::*;
+use lottery::*;
+use merkle::*;
+use migration::{StateV0, StateV1};
+use multisig::*;
+use overflow::*;
+use permit::*;
+use recovery::*;
+use refund::*;
+use roles::*;
+use sale_utils::{
+    merkle::MerkleRoot,
+    rido,
+    token::{transfer_token, transfer_token_of, transfer_token_via},
 };
-use concordium_std::{collections::BTreeMap, *};
-use sale_utils::{PUBLIC_RIDO_FEE, PUBLIC_RIDO_FEE_BBB, PUBLIC_RIDO_FEE_OVL};
 use state::{State, *};
+use ticket::*;
+use timelock::*;
 
 /// The parameter schema for `init` function.
 #[derive(Debug, Serialize, SchemaType)]
 pub struct InitParams {
     /// Account of the administrator of the entity running the IDO
     pub proj_admin: AccountAddress,
-    /// Address of Overlay for receiving sale fee
-    pub addr_ovl: Address,
-    /// Address of Overlay for buy back burn
-    pub addr_bbb: Address,
+    /// Users' share of the tokens sold, in basis points
+    pub user_share: BasisPoints,
+    /// Recipients of the sale fee(e.g. Overlay and buy back burn) and their share in basis points.
+    /// The shares and `user_share` must add up to 100%.
+    pub fees: BTreeMap<Address, BasisPoints>,
+    /// Recipients of a sale fee charged in CCD at `projectClaim` and their share in basis points.
+    /// Empty if the project admin receives all CCD raised.
+    pub ccd_fees: BTreeMap<Address, BasisPoints>,
     /// IDO schedule(The process is split into some phases)
     pub open_at: BTreeMap<Timestamp, Prior>,
     /// Sale End Time
     pub close_at: Timestamp,
     /// User(sale particicants) can withdraw assets according to the vesting period
-    pub vesting_period: BTreeMap<Duration, AllowedPercentage>,
+    /// (stepped unlocks, or a cliff followed by a linear release, with shares in basis points)
+    pub vesting_period: VestingSchedule,
     /// Swap price of the project token
     pub price_per_token: MicroCcd,
     /// Amount of project tokens contained in a unit
@@ -34,6 +73,30 @@ pub struct InitParams {
     pub max_units: UnitsAmount,
     /// Softcap
     pub min_units: UnitsAmount,
+    /// Number of units each tier can purchase.
+    /// `Tier::T0` is required, as users out of the whitelist purchase as T0.
+    pub tier_units: BTreeMap<Tier, u8>,
+    /// Accounts allowed to propose and approve emergency admin actions
+    pub signers: BTreeSet<AccountAddress>,
+    /// Number of approvals needed to execute an emergency admin action
+    pub threshold: u8,
+    /// How long approved changes of TGE and project token wait before being applied
+    pub timelock_delay: Duration,
+    /// If set, the sale is run as a lottery and this is the sha256 hash of the seed
+    /// to be revealed at `drawLottery`.
+    pub lottery_commitment: Option<HashSha2256>,
+    /// If set, the sale is run in overflow mode and users can deposit any amount of CCD
+    /// from this minimum. Cannot be combined with the lottery.
+    pub overflow_min_deposit: Option<Amount>,
+    /// If set, the raised CCD is held in escrow and released to the project in tranches
+    /// as milestones are reached and approved.
+    pub escrow: Option<EscrowParams>,
+    /// If set, the sale fails unless the project sets TGE and funds the pool by this time,
+    /// and the depositors can be refunded.
+    pub delivery_deadline: Option<Timestamp>,
+    /// If set, users can deposit with an allocation ticket signed by this backend key
+    /// instead of being added by `whitelisting`.
+    pub ticket_key: Option<PublicKeyEd25519>,
 }
 
 /// # Init Function
@@ -46,6 +109,11 @@ fn contract_init<S: HasStateApi>(
 ) -> InitResult<State<S>> {
     let params: InitParams = ctx.parameter_cursor().get()?;
 
+    ensure!(
+        params.delivery_deadline.map_or(true, |deadline| deadline > params.close_at),
+        CustomContractError::InvalidSchedule.into()
+    );
+
     let schedule = SaleSchedule::new(
         ctx.metadata().slot_time(),
         params.open_at,
@@ -60,13 +128,36 @@ fn contract_init<S: HasStateApi>(
         params.min_units,
     )?;
 
+    ensure!(
+        params.tier_units.contains_key(&Tier::T0) && params.tier_units.values().all(|u| *u > 0),
+        CustomContractError::InvalidTierUnits.into()
+    );
+
+    ensure!(
+        params.lottery_commitment.is_none() || params.overflow_min_deposit.is_none(),
+        CustomContractError::InvalidSaleMode.into()
+    );
+
+    validate_fees(params.user_share, &params.fees, &params.ccd_fees)?;
+
+    let multisig = Multisig::new(state_builder, params.signers, params.threshold)?;
+
     Ok(State::new(
         state_builder,
         params.proj_admin,
-        params.addr_ovl,
-        params.addr_bbb,
+        params.user_share,
+        params.fees,
+        params.ccd_fees,
         schedule,
         saleinfo,
+        params.tier_units,
+        multisig,
+        params.timelock_delay,
+        params.lottery_commitment.map(Lottery::new),
+        params.overflow_min_deposit.map(Overflow::new),
+        params.escrow.map(Escrow::new).transpose()?,
+        params.delivery_deadline,
+        params.ticket_key,
     ))
 }
 
@@ -77,119 +168,334 @@ fn contract_init<S: HasStateApi>(
 /// Some transferable functions (createPool, projectClaim, deposit, quit, userClaim)
 /// cannot be executed when the contract is paused.
 ///
-/// Caller: contract instance owner only
+/// Caller: contract instance owner or Pauser role
 /// Reject if:
-/// - The sender is not the contract owner.
+/// - The sender is neither the contract owner nor a Pauser.
 #[receive(
     contract = "pub_rido_ccd",
     name = "setPaused",
     error = "ContractError",
-    mutable
+    event = "SaleEvent",
+    mutable,
+    enable_logger
 )]
 fn contract_set_paused<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
-    ensure!(
-        ctx.sender().matches_account(&ctx.owner()),
-        ContractError::Unauthorized
-    );
+    ensure_role(ctx, host.state(), Role::Pauser)?;
     host.state_mut().paused = true;
+    logger.log(&SaleEvent::Paused(true))?;
     Ok(())
 }
 
 /// The contract is unpaused.
 ///
-/// Caller: contract instance owner only
+/// Caller: contract instance owner or Pauser role
 /// Reject if:
-/// - The sender is not the contract owner.
+/// - The sender is neither the contract owner nor a Pauser.
 #[receive(
     contract = "pub_rido_ccd",
     name = "setUnpaused",
     error = "ContractError",
-    mutable
+    event = "SaleEvent",
+    mutable,
+    enable_logger
 )]
 fn contract_set_unpaused<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
-    ensure!(
-        ctx.sender().matches_account(&ctx.owner()),
-        ContractError::Unauthorized
-    );
+    ensure_role(ctx, host.state(), Role::Pauser)?;
     host.state_mut().paused = false;
+    logger.log(&SaleEvent::Paused(false))?;
     Ok(())
 }
 
-/// To change the status to something arbitrary, but is not normally used.
+/// Set status to fix for next stage(claim).
+/// Note: if not reached softcap, the sale will be cancelled.
+/// In overflow mode, tokens have to be allocated to the users by `settleOverflow` beforehand.
 ///
-/// Caller: contract owner only
+/// Caller: contract instance owner or Admin role
 /// Reject if:
-/// - The sender is not the contract instance owner.
-/// - Fails to parse parameter
+/// - The sender is neither the contract owner nor an Admin.
+/// - Status is not Ready
+/// - Called before the end of the sale
+/// - The lottery has not been drawn yet(lottery mode only)
+/// - Tokens have not been allocated to every user yet(overflow mode only)
 #[receive(
     contract = "pub_rido_ccd",
-    name = "setStatus",
-    parameter = "SaleStatus",
+    name = "setFixed",
     error = "ContractError",
-    mutable
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_fixed<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure_role(ctx, host.state(), Role::Admin)?;
+
+    let balance = host.self_balance();
+    let mut state = host.state_mut();
+    rido::ensure_sale_closed(ctx, state)?;
+
+    if let Some(lottery) = &state.lottery {
+        ensure!(lottery.is_drawn(), CustomContractError::NotDrawn.into());
+    }
+
+    if let Some(overflow) = &state.overflow {
+        ensure!(overflow.settled, CustomContractError::NotSettled.into());
+    }
+
+    if state.saleinfo.is_reached_sc() {
+        state.status = SaleStatus::Fixed;
+        let raised = balance
+            .checked_sub(state.refundable())
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+        state.fix_escrow(raised)?;
+    } else {
+        state.status = SaleStatus::Suspend;
+    }
+    logger.log(&SaleEvent::StatusChanged(state.status.clone()))?;
+
+    Ok(())
+}
+
+/// Mark the sale failed when the project has not delivered by the deadline,
+/// so that the depositors can be refunded by `userQuit` and `refundBatch`.
+///
+/// Caller: Anyone
+/// Reject if:
+/// - Status is not Fixed
+/// - No delivery deadline is set or it has not passed yet
+/// - The project has set TGE and funded the pool
+/// - The escrow has been declared failed(refunds go through `userEscrowRefund` instead)
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "setFailed",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
 )]
-fn contract_set_status<S: HasStateApi>(
+fn contract_set_failed<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
+    let state = host.state_mut();
+    ensure_eq!(
+        state.status,
+        SaleStatus::Fixed,
+        CustomContractError::SaleNotFixed.into()
+    );
     ensure!(
-        ctx.sender().matches_account(&ctx.owner()),
-        ContractError::Unauthorized
+        state.is_delivery_overdue(ctx.metadata().slot_time())?,
+        CustomContractError::Inappropriate.into()
+    );
+    ensure!(
+        !state.escrow.as_ref().map_or(false, |escrow| escrow.failed),
+        CustomContractError::EscrowFailed.into()
     );
-    let status: SaleStatus = ctx.parameter_cursor().get()?;
-    host.state_mut().status = status;
+
+    state.status = SaleStatus::Failed;
+    logger.log(&SaleEvent::StatusChanged(state.status.clone()))?;
 
     Ok(())
 }
 
-/// Set status to fix for next stage(claim).
-/// Note: if not reached softcap, the sale will be cancelled.
+/// Reveal the committed seed and draw the winners of the lottery.
+/// Winners get the units of their tier fixed, and losers can reclaim their CCD.
+/// Users are ranked by the seed together with the set of users who have entered the draw,
+/// so the seed alone does not decide the winners.
+/// Note: whoever committed the seed knows it during the sale.
+/// By entering last, they can pick the addresses of their own entries, and thereby
+/// the final entrant set, so as to rank those entries first.
+/// Users have to trust the owner not to enter the draw.
+/// The commitment only keeps the seed from being changed once the entrants are known,
+/// and if the seed is not revealed in time, everyone is refunded.
 ///
-/// Caller: contract instance owner only
+/// Caller: contract instance owner or Admin role
 /// Reject if:
-/// - The sender is not the contract owner.
+/// - Fails to parse parameter
+/// - The sender is neither the contract owner nor an Admin.
+/// - The sale is not in lottery mode
+/// - Status is not Ready
 /// - Called before the end of the sale
+/// - Already drawn
+/// - More than `REVEAL_PERIOD_DAYS` have passed since the end of the sale
+/// - The seed does not match the commitment
 #[receive(
     contract = "pub_rido_ccd",
-    name = "setFixed",
+    name = "drawLottery",
+    parameter = "LotterySeed",
     error = "ContractError",
-    mutable
+    event = "SaleEvent",
+    mutable,
+    enable_logger,
+    crypto_primitives
 )]
-fn contract_set_fixed<S: HasStateApi>(
+fn contract_draw_lottery<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+    crypto_primitives: &impl HasCryptoPrimitives,
 ) -> ContractResult<()> {
+    ensure_role(ctx, host.state(), Role::Admin)?;
+    let seed: LotterySeed = ctx.parameter_cursor().get()?;
+
+    let state = host.state_mut();
+    ensure_eq!(
+        state.status,
+        SaleStatus::Ready,
+        CustomContractError::SaleNotReady.into()
+    );
     ensure!(
-        ctx.sender().matches_account(&ctx.owner()),
-        ContractError::Unauthorized
+        state.schedule.is_sale_closed(ctx.metadata().slot_time()),
+        CustomContractError::InvalidSchedule.into()
     );
 
-    let mut state = host.state_mut();
+    let winners = state.draw_lottery(ctx.metadata().slot_time(), seed, crypto_primitives)?;
+
+    logger.log(&SaleEvent::LotteryDrawn(LotteryDrawnEvent {
+        seed,
+        winners: winners.len() as u32,
+        applied_units: state.saleinfo.applied_units,
+    }))?;
+
+    Ok(())
+}
+
+/// Allocate the tokens to a page of users in proportion to their deposit in overflow mode.
+/// Each call continues from where the previous one stopped,
+/// and the allocation is fixed once every user has been allocated.
+/// Returns whether every user has been allocated.
+///
+/// Caller: Anyone
+/// Reject if:
+/// - Fails to parse parameter
+/// - Contract is paused
+/// - The sale is not in overflow mode
+/// - Status is not Ready
+/// - Called before the end of the sale
+/// - Already settled
+/// - The limit is 0
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "settleOverflow",
+    parameter = "SettleOverflowParams",
+    return_value = "bool",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_settle_overflow<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<bool> {
+    let params: SettleOverflowParams = ctx.parameter_cursor().get()?;
 
-    // This func does not work until the sale is closed.
+    let state = host.state_mut();
+    ensure!(!state.paused, CustomContractError::ContractPaused.into());
+    ensure!(
+        state.overflow.is_some(),
+        CustomContractError::NotOverflow.into()
+    );
+    ensure_eq!(
+        state.status,
+        SaleStatus::Ready,
+        CustomContractError::SaleNotReady.into()
+    );
     ensure!(
         state.schedule.is_sale_closed(ctx.metadata().slot_time()),
         CustomContractError::InvalidSchedule.into()
     );
 
-    if state.saleinfo.is_reached_sc() {
-        state.status = SaleStatus::Fixed;
-    } else {
-        state.status = SaleStatus::Suspend;
+    let settled = state.settle_overflow(params.limit)?;
+
+    if settled {
+        let overflow = state.get_overflow()?;
+        logger.log(&SaleEvent::OverflowSettled(OverflowSettledEvent {
+            allocated_tokens: overflow.allocated_tokens,
+            refundable: overflow.refundable,
+        }))?;
     }
 
+    Ok(settled)
+}
+
+/// Set the entrypoint through which contract participants are refunded by `refundBatch`.
+/// The entrypoint is invoked with the deposit attached and no parameter.
+/// `None` leaves contract participants out of the batch.
+///
+/// Caller: contract instance owner or Admin role
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is neither the contract owner nor an Admin.
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "setRefundHook",
+    parameter = "Option<OwnedEntrypointName>",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_refund_hook<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure_role(ctx, host.state(), Role::Admin)?;
+    let hook: Option<OwnedEntrypointName> = ctx.parameter_cursor().get()?;
+
+    host.state_mut().refund_hook = hook.clone();
+    logger.log(&SaleEvent::RefundHookSet(hook))?;
+
+    Ok(())
+}
+
+/// Set the entrypoint through which contract participants and fee recipients
+/// receive the tokens claimed by `userClaim` and `feeClaim`.
+/// `None` restores the default `callback`.
+///
+/// Caller: contract instance owner or Admin role
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is neither the contract owner nor an Admin.
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "setClaimHook",
+    parameter = "Option<OwnedEntrypointName>",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_claim_hook<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure_role(ctx, host.state(), Role::Admin)?;
+    let hook: Option<OwnedEntrypointName> = ctx.parameter_cursor().get()?;
+
+    host.state_mut().claim_hook = hook.clone();
+    logger.log(&SaleEvent::ClaimHookSet(hook))?;
+
     Ok(())
 }
 
 /// Parameter type for the contract function `whitelisting`.
-/// Currently user can be both account and contract.
-/// [#TODO] But need to consider when user can be contract.
+/// Users can be both accounts and contracts.
+/// Contracts are refunded through the refund hook and receive tokens through the claim hook.
 #[derive(Debug, Serialize, SchemaType)]
 struct WhitelistingParams {
     /// the whitelist
@@ -198,37 +504,30 @@ struct WhitelistingParams {
     ready: bool,
 }
 
-#[derive(Debug, Serialize, SchemaType)]
-struct AllowedUserParams {
-    /// Users address to be whitelisted
-    user: Address,
-    /// Priority for participation in the sale
-    prior: Prior,
-}
-
 /// Whitelist users who can participate in the sale
-/// Note: All user can be allocated just one unit.
+/// Note: Users are allocated the units configured for their tier at init.
 ///
-/// Caller: contract instance owner only
+/// Caller: contract instance owner or WhitelistManager role
 /// Reject if:
 /// - Fails to parse parameter
-/// - The sender is not the contract owner.
+/// - The sender is neither the contract owner nor a WhitelistManager.
 /// - Status is not Prepare
+/// - The tier of a user has no units configured
 #[receive(
     contract = "pub_rido_ccd",
     name = "whitelisting",
     parameter = "WhitelistingParams",
     error = "ContractError",
-    mutable
+    event = "SaleEvent",
+    mutable,
+    enable_logger
 )]
 fn contract_whitelisting<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
-    ensure!(
-        ctx.sender().matches_account(&ctx.owner()),
-        ContractError::Unauthorized
-    );
+    ensure_role(ctx, host.state(), Role::WhitelistManager)?;
 
     let mut state = host.state_mut();
     ensure_eq!(
@@ -239,133 +538,92 @@ fn contract_whitelisting<S: HasStateApi>(
 
     let params: WhitelistingParams = ctx.parameter_cursor().get()?;
 
-    // all can purchase only 1 unit;
-    for AllowedUserParams { user, prior } in params.wl {
-        if let Address::Account(_) = user {
-            state.whitelisting(&user, prior);
-        } else {
-            // [#TODO] Only support AccountAddress for now.
-            bail!(CustomContractError::AccountOnly.into())
-        };
+    for AllowedUserParams { user, prior, tier } in params.wl {
+        let tgt_units = state.whitelisting(&user, prior.clone(), &tier)?;
+        logger.log(&SaleEvent::Whitelisted(WhitelistedEvent {
+            user,
+            prior,
+            tier,
+            tgt_units,
+        }))?;
     }
 
     if params.ready {
         state.status = SaleStatus::Ready;
+        logger.log(&SaleEvent::StatusChanged(SaleStatus::Ready))?;
     }
 
     Ok(())
 }
 
-/// To claim sale fee for overlay team.
-/// Note: 5% for now.
+/// Commit the root of a Merkle tree over the whitelist(built by `sale_utils::merkle::MerkleTree`),
+/// so that users prove their membership at `userDeposit` instead of being added by `whitelisting`.
+/// `None` removes the commitment.
 ///
-/// Caller: contract instance owner only
+/// Caller: contract instance owner or WhitelistManager role
 /// Reject if:
-/// - The sender is not the contract owner
-/// - Status is not Fixed
-/// - Project admin has not yet registered the project token
-/// - Project admin has not yet registered the TGE
+/// - Fails to parse parameter
+/// - The sender is neither the contract owner nor a WhitelistManager.
+/// - Status is not Prepare
 #[receive(
     contract = "pub_rido_ccd",
-    name = "ovlClaim",
+    name = "setWhitelistRoot",
+    parameter = "Option<MerkleRoot>",
     error = "ContractError",
-    mutable
+    event = "SaleEvent",
+    mutable,
+    enable_logger
 )]
-fn contract_ovl_claim<S: HasStateApi>(
+fn contract_set_whitelist_root<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
-    ensure!(
-        ctx.sender().matches_account(&ctx.owner()),
-        ContractError::Unauthorized
-    );
-
-    let mut state = host.state_mut();
-
-    ensure!(
-        state.status == SaleStatus::Fixed,
-        CustomContractError::SaleNotFixed.into()
-    );
+    ensure_role(ctx, host.state(), Role::WhitelistManager)?;
 
-    ensure!(
-        state.project_token.is_some(),
-        CustomContractError::NotSetProjectToken.into()
-    );
-    ensure!(
-        state.schedule.vesting_start.is_some(),
-        CustomContractError::NotSetTge.into()
+    let state = host.state_mut();
+    ensure_eq!(
+        state.status,
+        SaleStatus::Prepare,
+        CustomContractError::AlreadySaleStarted.into()
     );
 
-    let vesting_start = state.schedule.vesting_start.unwrap();
-
-    let now = ctx.metadata().slot_time();
-    let total_units = cmp::min(state.saleinfo.max_units, state.saleinfo.applied_units);
-
-    let (amount, inc): (ContractTokenAmount, u8) = state.calc_vesting_amount(
-        now,
-        vesting_start,
-        total_units as u64,
-        PUBLIC_RIDO_FEE_OVL,
-        state.ovl_claimed_inc,
-    )?;
-
-    if inc > state.ovl_claimed_inc {
-        state.ovl_claimed_inc = inc;
-    }
-
-    if amount.0 > 0 {
-        let to = match state.addr_ovl {
-            Address::Account(account_addr) => Receiver::from_account(account_addr),
-            Address::Contract(contract_addr) => Receiver::from_contract(
-                contract_addr,
-                OwnedEntrypointName::new_unchecked("callback".to_owned()),
-            ),
-        };
-
-        let transfer = Transfer {
-            from: Address::from(ctx.self_address()),
-            to,
-            token_id: TokenIdUnit(),
-            amount,
-            data: AdditionalData::empty(),
-        };
-        let project_token = state.project_token.unwrap();
-        let _ = host.invoke_contract(
-            &project_token,
-            &TransferParams::from(vec![transfer]),
-            EntrypointName::new_unchecked("transfer"),
-            Amount::zero(),
-        )?;
-    }
+    let root: Option<MerkleRoot> = ctx.parameter_cursor().get()?;
+    state.whitelist_root = root.clone();
+    logger.log(&SaleEvent::WhitelistRootSet(root))?;
 
     Ok(())
 }
 
-/// To claim sale fee for Buy Back Burn.
-/// Note: 5% for now.
+/// To claim the sale fee of a recipient in the fee table.
 ///
-/// Caller: contract instance owner only
+/// Caller: contract instance owner or FeeClaimer role
 /// Reject if:
-/// - The sender is not the contract owner
+/// - Fails to parse parameter
+/// - The sender is neither the contract owner nor a FeeClaimer.
 /// - Status is not Fixed
 /// - Project admin has not yet registered the project token
 /// - Project admin has not yet registered the TGE
+/// - The pool has not been funded with all the tokens sold
+/// - The recipient is not in the fee table
 #[receive(
     contract = "pub_rido_ccd",
-    name = "bbbClaim",
+    name = "feeClaim",
+    parameter = "Address",
     error = "ContractError",
-    mutable
+    event = "SaleEvent",
+    mutable,
+    enable_logger
 )]
-fn contract_bbb_claim<S: HasStateApi>(
+fn contract_fee_claim<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
-    ensure!(
-        ctx.sender().matches_account(&ctx.owner()),
-        ContractError::Unauthorized
-    );
+    ensure_role(ctx, host.state(), Role::FeeClaimer)?;
+    let recipient: Address = ctx.parameter_cursor().get()?;
 
-    let mut state = host.state_mut();
+    let state = host.state_mut();
 
     ensure!(
         state.status == SaleStatus::Fixed,
@@ -380,124 +638,661 @@ fn contract_bbb_claim<S: HasStateApi>(
         state.schedule.vesting_start.is_some(),
         CustomContractError::NotSetTge.into()
     );
+    ensure!(
+        state.is_pool_funded()?,
+        CustomContractError::NotDeposited.into()
+    );
 
     let vesting_start = state.schedule.vesting_start.unwrap();
+    let fee = state.get_fee(&recipient)?;
 
     let now = ctx.metadata().slot_time();
-    let total_units = cmp::min(state.saleinfo.max_units, state.saleinfo.applied_units);
 
-    let (amount, inc): (ContractTokenAmount, u8) = state.calc_vesting_amount(
+    let (amount, inc): (ContractTokenAmount, u8) = state.calc_vesting_amount_sold(
         now,
         vesting_start,
-        total_units as u64,
-        PUBLIC_RIDO_FEE_BBB,
-        state.bbb_claimed_inc,
+        fee.bps,
+        fee.claimed_inc,
+        fee.claimed_amount,
     )?;
 
-    if inc > state.bbb_claimed_inc {
-        state.bbb_claimed_inc = inc;
-    }
+    state.increment_fee_claimed(&recipient, inc, amount)?;
 
     if amount.0 > 0 {
-        let to = match state.addr_bbb {
-            Address::Account(account_addr) => Receiver::from_account(account_addr),
-            Address::Contract(contract_addr) => Receiver::from_contract(
-                contract_addr,
-                OwnedEntrypointName::new_unchecked("callback".to_owned()),
-            ),
-        };
+        let project_token = state.project_token.unwrap();
+        let hook = state.claim_hook();
+        transfer_token_via(ctx, host, project_token, recipient, amount, hook)?;
+        host.state_mut().record_claimed(project_token, amount)?;
 
-        let transfer = Transfer {
-            from: Address::from(ctx.self_address()),
-            to,
-            token_id: TokenIdUnit(),
+        logger.log(&SaleEvent::FeeClaimed(FeeClaimedEvent {
+            to: recipient,
             amount,
-            data: AdditionalData::empty(),
-        };
-        let project_token = state.project_token.unwrap();
-        let _ = host.invoke_contract(
-            &project_token,
-            &TransferParams::from(vec![transfer]),
-            EntrypointName::new_unchecked("transfer"),
-            Amount::zero(),
-        )?;
+            inc,
+        }))?;
     }
 
     Ok(())
 }
 
-/// Callback function to call when CIS2 is called
-/// but no callback is needed.
-/// Caller: Anyone
+/// Callback function to call when CIS2 is called.
+/// Received tokens are recorded per (contract, token id) so that
+/// the ones not needed for the claims can be recovered by `recoverTokens`.
+///
+/// Caller: Any CIS2 contract
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is not a contract
 #[receive(
     contract = "pub_rido_ccd",
     name = "callback",
-    parameter = "OnReceivingCis2Params<ContractTokenId, ContractTokenAmount>",
+    parameter = "OnReceivingCis2Params<TokenIdVec, ContractTokenAmount>",
+    error = "ContractError",
     mutable
 )]
 fn callback<S: HasStateApi>(
-    _ctx: &impl HasReceiveContext,
-    _host: &mut impl HasHost<State<S>, StateApiType = S>,
-) -> ReceiveResult<()> {
-    Ok(())
-}
-
-/// Change TGE(vesting period)
-/// Note: should not be called except in case of emergency.
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    let token = if let Address::Contract(contract) = ctx.sender() {
+        contract
+    } else {
+        bail!(CustomContractError::ContractOnly.into())
+    };
+
+    let params: OnReceivingCis2Params<TokenIdVec, ContractTokenAmount> =
+        ctx.parameter_cursor().get()?;
+    host.state_mut().receive_tokens(token, params.token_id, params.amount)?;
+
+    Ok(())
+}
+
+/// Withdraw CIS-2 tokens held by this contract, e.g. tokens sent by mistake
+/// or project tokens beyond what the outstanding claims need.
+/// Once the escrow has failed, users are refunded by `userEscrowRefund` instead of claiming,
+/// and the project tokens not needed for the fees can be returned to the depositor of the pool.
 ///
-/// Caller: contract instance owner only
+/// Caller: contract instance owner or Admin role
 /// Reject if:
 /// - Fails to parse parameter
-/// - The sender is not the contract owner.
+/// - The sender is neither the contract owner nor an Admin.
+/// - The amount exceeds the balance not reserved for the user and fee claims
+/// - Fails to transfer the tokens
 #[receive(
     contract = "pub_rido_ccd",
-    name = "changeTGE",
-    parameter = "Timestamp",
+    name = "recoverTokens",
+    parameter = "RecoverTokensParams",
     error = "ContractError",
-    mutable
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_recover_tokens<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure_role(ctx, host.state(), Role::Admin)?;
+    let params: RecoverTokensParams = ctx.parameter_cursor().get()?;
+
+    host.state_mut().recover_tokens(params.token, params.token_id.clone(), params.amount)?;
+    transfer_token_of(
+        ctx,
+        host,
+        params.token,
+        params.token_id.clone(),
+        params.to,
+        params.amount,
+    )?;
+
+    logger.log(&SaleEvent::TokensRecovered(TokensRecoveredEvent {
+        token: params.token,
+        token_id: params.token_id,
+        amount: params.amount,
+        to: params.to,
+    }))?;
+
+    Ok(())
+}
+
+/// Move the project tokens left in the pool by rounding to the given address.
+/// Claims are accounted by amount so this is only a few tokens, if any.
+///
+/// Caller: contract instance owner or Admin role
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is neither the contract owner nor an Admin.
+/// - Status is not Fixed
+/// - The vesting is not over or some user or fee claim is outstanding
+/// - Fails to transfer the tokens
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "sweepDust",
+    parameter = "Address",
+    return_value = "ContractTokenAmount",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_sweep_dust<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<ContractTokenAmount> {
+    ensure_role(ctx, host.state(), Role::Admin)?;
+    let to: Address = ctx.parameter_cursor().get()?;
+
+    let amount = host.state_mut().sweep_dust(ctx.metadata().slot_time())?;
+
+    if amount.0 > 0 {
+        let project_token = host.state().project_token.unwrap();
+        transfer_token(ctx, host, project_token, to, amount)?;
+
+        logger.log(&SaleEvent::DustSwept(DustSweptEvent { amount, to }))?;
+    }
+
+    Ok(amount)
+}
+
+/// Approve a tranche of the escrow on behalf of the Overlay team.
+/// It is released at the next `projectClaim` once its milestone is reached.
+///
+/// Caller: contract instance owner or Admin role
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is neither the contract owner nor an Admin.
+/// - Status is not Fixed
+/// - The sale is not in escrow mode or the project has been declared failed
+/// - The tranche does not exist or has already been approved
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "approveTranche",
+    parameter = "u8",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_approve_tranche<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure_role(ctx, host.state(), Role::Admin)?;
+    let index: u8 = ctx.parameter_cursor().get()?;
+
+    let state = host.state_mut();
+    ensure_eq!(
+        state.status,
+        SaleStatus::Fixed,
+        CustomContractError::SaleNotFixed.into()
+    );
+    state.approve_tranche(index)?;
+
+    logger.log(&SaleEvent::TrancheApproved(index))?;
+
+    Ok(())
+}
+
+/// Declare the project failed. CCD not released from the escrow yet
+/// becomes refundable to the participants in proportion to what they paid.
+///
+/// Caller: contract instance owner or Admin role
+/// Reject if:
+/// - The sender is neither the contract owner nor an Admin.
+/// - Status is not Fixed
+/// - The sale is not in escrow mode or the project has already been declared failed
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "setEscrowFailed",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_escrow_failed<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure_role(ctx, host.state(), Role::Admin)?;
+
+    let state = host.state_mut();
+    ensure_eq!(
+        state.status,
+        SaleStatus::Fixed,
+        CustomContractError::SaleNotFixed.into()
+    );
+    let refundable = state.fail_escrow()?;
+
+    logger.log(&SaleEvent::EscrowFailed(refundable))?;
+
+    Ok(())
+}
+
+/// Propose an emergency admin action(e.g. changing TGE or the project token).
+/// The proposer's approval is counted automatically.
+///
+/// Caller: signers only
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is not a signer
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "propose",
+    parameter = "AdminAction",
+    return_value = "ProposalId",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_propose<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<ProposalId> {
+    let proposer = match ctx.sender() {
+        Address::Account(addr) if host.state().multisig.is_signer(&addr) => addr,
+        _ => bail!(ContractError::Unauthorized),
+    };
+    let action: AdminAction = ctx.parameter_cursor().get()?;
+
+    let id = host.state_mut().multisig.propose(proposer, action.clone());
+    logger.log(&SaleEvent::ProposalCreated(ProposalCreatedEvent {
+        id,
+        proposer,
+        action,
+    }))?;
+
+    Ok(id)
+}
+
+/// Approve a pending proposal.
+///
+/// Caller: signers only
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is not a signer
+/// - The proposal does not exist
+/// - The sender has already approved the proposal
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "approve",
+    parameter = "ProposalId",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
 )]
-fn contract_change_tge<S: HasStateApi>(
+fn contract_approve<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
-    //[#TODO] no need multiple people check to change?
+    let signer = match ctx.sender() {
+        Address::Account(addr) if host.state().multisig.is_signer(&addr) => addr,
+        _ => bail!(ContractError::Unauthorized),
+    };
+    let id: ProposalId = ctx.parameter_cursor().get()?;
+
+    host.state_mut().multisig.approve(id, signer)?;
+    logger.log(&SaleEvent::ProposalApproved(ProposalApprovedEvent { id, signer }))?;
+
+    Ok(())
+}
+
+/// Execute a proposal approved by enough signers.
+/// `ChangeTge` and `ChangePjtoken` are not applied right away but queued
+/// for the timelock delay, see `applyChange`.
+/// Note: should not be called except in case of emergency.
+///
+/// Caller: signers only
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is not a signer
+/// - The proposal does not exist
+/// - Approvals of the current signers are fewer than the threshold
+/// - The new signer set is invalid(for `UpdateSigners`)
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "execute",
+    parameter = "ProposalId",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_execute<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    match ctx.sender() {
+        Address::Account(addr) if host.state().multisig.is_signer(&addr) => {},
+        _ => bail!(ContractError::Unauthorized),
+    };
+    let id: ProposalId = ctx.parameter_cursor().get()?;
+
+    let state = host.state_mut();
+    let action = state.multisig.take_approved(id)?;
+
+    match action.clone() {
+        // These affect users who have already deposited, so they are only queued here.
+        AdminAction::ChangeTge(_) | AdminAction::ChangePjtoken(_) => {
+            let (change_id, effective_at) =
+                state.timelock.queue(ctx.metadata().slot_time(), action.clone())?;
+            logger.log(&SaleEvent::ChangeQueued(ChangeQueuedEvent {
+                id: change_id,
+                action: action.clone(),
+                effective_at,
+            }))?;
+        },
+        AdminAction::SetStatus(status) => {
+            state.status = status.clone();
+            logger.log(&SaleEvent::StatusChanged(status))?;
+        },
+        AdminAction::ChangeFeeRecipient(ChangeFeeRecipientParams { from, to }) => {
+            state.change_fee_recipient(&from, to)?
+        },
+        AdminAction::UpdateSigners(SignersParams { signers, threshold }) => {
+            state.multisig.update_signers(signers, threshold)?
+        },
+    }
+    logger.log(&SaleEvent::ProposalExecuted(ProposalExecutedEvent { id, action }))?;
+
+    Ok(())
+}
+
+/// Cancel a pending proposal.
+///
+/// Caller: the proposer or contract instance owner
+/// Reject if:
+/// - Fails to parse parameter
+/// - The proposal does not exist
+/// - The sender is neither the proposer nor the contract owner
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "cancelProposal",
+    parameter = "ProposalId",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_cancel_proposal<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let id: ProposalId = ctx.parameter_cursor().get()?;
+
+    let state = host.state_mut();
+    let proposer = state
+        .multisig
+        .proposals
+        .get(&id)
+        .map(|proposal| proposal.proposer)
+        .ok_or(ContractError::from(CustomContractError::UnknownProposal))?;
+
+    let sender = ctx.sender();
     ensure!(
-        ctx.sender().matches_account(&ctx.owner()),
+        sender.matches_account(&proposer) || sender.matches_account(&ctx.owner()),
         ContractError::Unauthorized
     );
 
-    let ts: Timestamp = ctx.parameter_cursor().get()?;
-    host.state_mut().schedule.vesting_start = Some(ts);
+    state.multisig.cancel(id);
+    logger.log(&SaleEvent::ProposalCancelled(id))?;
 
     Ok(())
 }
 
-/// Change project token contract
-/// Note: should not be called except in case of emergency.
+/// Apply a queued change whose delay has passed.
+///
+/// Caller: signers
+/// Reject if:
+/// - The sender is not a signer
+/// - Fails to parse parameter
+/// - The change does not exist
+/// - The change is not effective yet
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "applyChange",
+    parameter = "ChangeId",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_apply_change<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    match ctx.sender() {
+        Address::Account(addr) if host.state().multisig.is_signer(&addr) => {},
+        _ => bail!(ContractError::Unauthorized),
+    };
+    let id: ChangeId = ctx.parameter_cursor().get()?;
+
+    let state = host.state_mut();
+    let action = state.timelock.take_effective(id, ctx.metadata().slot_time())?;
+
+    match action.clone() {
+        AdminAction::ChangeTge(ts) => {
+            state.schedule.vesting_start = Some(ts);
+            logger.log(&SaleEvent::TgeSet(ts))?;
+        },
+        AdminAction::ChangePjtoken(addr) => {
+            state.project_token = Some(addr);
+            logger.log(&SaleEvent::ProjectTokenSet(addr))?;
+        },
+        _ => bail!(CustomContractError::Inappropriate.into()),
+    }
+    logger.log(&SaleEvent::ChangeExecuted(ChangeExecutedEvent { id, action }))?;
+
+    Ok(())
+}
+
+/// Cancel a queued change during its delay.
+///
+/// Caller: signers or contract instance owner
+/// Reject if:
+/// - The sender is neither a signer nor the contract owner
+/// - Fails to parse parameter
+/// - The change does not exist
+/// - The change has already become effective
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "cancelChange",
+    parameter = "ChangeId",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_cancel_change<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let is_signer = match ctx.sender() {
+        Address::Account(addr) => host.state().multisig.is_signer(&addr),
+        Address::Contract(_) => false,
+    };
+    ensure!(
+        is_signer || ctx.sender().matches_account(&ctx.owner()),
+        ContractError::Unauthorized
+    );
+    let id: ChangeId = ctx.parameter_cursor().get()?;
+
+    host.state_mut().timelock.cancel(id, ctx.metadata().slot_time())?;
+    logger.log(&SaleEvent::ChangeCancelled(id))?;
+
+    Ok(())
+}
+
+/// Grant a role to an address.
+///
+/// Caller: contract instance owner or Admin role
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is neither the contract owner nor an Admin.
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "grantRole",
+    parameter = "RoleParams",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_grant_role<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure_role(ctx, host.state(), Role::Admin)?;
+
+    let params: RoleParams = ctx.parameter_cursor().get()?;
+    host.state_mut().grant_role(&params.address, params.role);
+    logger.log(&SaleEvent::RoleGranted(RoleEvent {
+        address: params.address,
+        role: params.role,
+    }))?;
+
+    Ok(())
+}
+
+/// Revoke a role from an address.
+///
+/// Caller: contract instance owner or Admin role
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is neither the contract owner nor an Admin.
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "revokeRole",
+    parameter = "RoleParams",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_revoke_role<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure_role(ctx, host.state(), Role::Admin)?;
+
+    let params: RoleParams = ctx.parameter_cursor().get()?;
+    host.state_mut().revoke_role(&params.address, params.role);
+    logger.log(&SaleEvent::RoleRevoked(RoleEvent {
+        address: params.address,
+        role: params.role,
+    }))?;
+
+    Ok(())
+}
+
+/// Parameter type for the contract function `upgrade`.
+#[derive(Debug, Serialize, SchemaType)]
+struct UpgradeParams {
+    /// The new module reference.
+    module: ModuleReference,
+    /// Optional entrypoint(and its parameter) to call in the new module after upgrade,
+    /// typically `migrate`.
+    migrate: Option<(OwnedEntrypointName, OwnedParameter)>,
+}
+
+/// Upgrade this instance to a new module and optionally migrate the state.
+/// Note: This is a low level function, the state is not touched here
+/// so that the migration in the new module can rewrite it.
 ///
 /// Caller: contract instance owner only
 /// Reject if:
 /// - Fails to parse parameter
 /// - The sender is not the contract owner.
+/// - The module does not exist or is not a supported version
+/// - The migration call fails
 #[receive(
     contract = "pub_rido_ccd",
-    name = "changePjtoken",
-    parameter = "ContractAddress",
+    name = "upgrade",
+    parameter = "UpgradeParams",
     error = "ContractError",
-    mutable
+    low_level
 )]
-fn contract_change_pjtoken<S: HasStateApi>(
+fn contract_upgrade<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
-    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    host: &mut impl HasHost<S, StateApiType = S>,
 ) -> ContractResult<()> {
-    //[#TODO] no need multiple people check to change?
     ensure!(
         ctx.sender().matches_account(&ctx.owner()),
         ContractError::Unauthorized
     );
 
-    let addr: ContractAddress = ctx.parameter_cursor().get()?;
-    host.state_mut().project_token = Some(addr);
+    let params: UpgradeParams = ctx.parameter_cursor().get()?;
+    host.upgrade(params.module)?;
+
+    if let Some((func, parameter)) = params.migrate {
+        host.invoke_contract_raw(
+            &ctx.self_address(),
+            parameter.as_parameter(),
+            func.as_entrypoint_name(),
+            Amount::zero(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Migrate the state written by an older module into the current `State` layout.
+/// The parameter is the layout version the stored state was written with,
+/// which has to match the `version` stored at the root of the state(v0 stored none).
+/// Note: This is a low level function since the stored state cannot be read as `State`.
+///
+/// Caller: this contract itself(during `upgrade`) or contract instance owner
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is neither this contract nor the contract owner.
+/// - There is no migration from the given version
+/// - Fails to read the stored state
+/// - The stored version differs from the given one
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "migrate",
+    parameter = "u16",
+    error = "ContractError",
+    low_level
+)]
+fn contract_migrate<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<S, StateApiType = S>,
+) -> ContractResult<()> {
+    let sender = ctx.sender();
+    ensure!(
+        sender.matches_contract(&ctx.self_address()) || sender.matches_account(&ctx.owner()),
+        ContractError::Unauthorized
+    );
+
+    let from_version: u16 = ctx.parameter_cursor().get()?;
+
+    let legacy = match from_version {
+        0 => host.state().read_root::<StateV0<S>>()?,
+        1 => {
+            let legacy: StateV1<S> = host.state().read_root()?;
+            ensure_eq!(
+                legacy.version,
+                from_version,
+                CustomContractError::Inappropriate.into()
+            );
+            legacy.state
+        },
+        _ => bail!(CustomContractError::Inappropriate.into()),
+    };
+    let state = State::migrate_from_v0(legacy, ctx.owner(), host.state_builder())?;
+    host.state_mut().write_root(&state);
 
     Ok(())
 }
@@ -508,79 +1303,74 @@ fn contract_change_pjtoken<S: HasStateApi>(
 
 /// Set project token contract.
 ///
-/// Caller: [#TODO] not decided yet
+/// Caller: Project Admin or TgeManager role
 /// Reject if:
 /// - Fails to parse parameter
 /// - Already set the contract address
-/// - The sender is not the project admin ?
+/// - The sender is neither the project admin nor a TgeManager
 #[receive(
     contract = "pub_rido_ccd",
     name = "setPjtoken",
     parameter = "ContractAddress",
     error = "ContractError",
-    mutable
+    event = "SaleEvent",
+    mutable,
+    enable_logger
 )]
 fn contract_set_pjtoken<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
-    // currently under discussion who should call this func
+    let state = host.state();
     ensure!(
-        ctx.sender().matches_account(&host.state().proj_admin),
+        ctx.sender().matches_account(&state.proj_admin)
+            || sender_has_role(ctx, state, Role::TgeManager),
         ContractError::Unauthorized
     );
-    let addr: ContractAddress = ctx.parameter_cursor().get()?;
-
-    let mut state = host.state_mut();
-
-    ensure!(
-        state.project_token.is_none(),
-        CustomContractError::Inappropriate.into()
-    );
-
-    state.project_token = Some(addr);
+    let addr = rido::set_project_token(ctx, host.state_mut())?;
+    logger.log(&SaleEvent::ProjectTokenSet(addr))?;
 
     Ok(())
 }
 
 /// Set TGE, which means it determines the beginning of the vesting period.
 ///
-/// Caller: Project Admin only
+/// Caller: Project Admin or TgeManager role
 /// Reject if:
 /// - Fails to parse parameter
-/// - The sender is not the project admin
+/// - The sender is neither the project admin nor a TgeManager
 /// - Already set the TGE
 #[receive(
     contract = "pub_rido_ccd",
     name = "setTGE",
     parameter = "Timestamp",
     error = "ContractError",
-    mutable
+    event = "SaleEvent",
+    mutable,
+    enable_logger
 )]
 fn contract_set_tge<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
+    let state = host.state();
     ensure!(
-        ctx.sender().matches_account(&host.state().proj_admin),
+        ctx.sender().matches_account(&state.proj_admin)
+            || sender_has_role(ctx, state, Role::TgeManager),
         ContractError::Unauthorized
     );
-    let ts: Timestamp = ctx.parameter_cursor().get()?;
-
-    let mut state = host.state_mut();
-
-    ensure!(
-        state.schedule.vesting_start.is_none(),
-        CustomContractError::Inappropriate.into()
-    );
-
-    state.schedule.vesting_start = Some(ts);
+    let ts = rido::set_tge(ctx, host.state_mut())?;
+    logger.log(&SaleEvent::TgeSet(ts))?;
 
     Ok(())
 }
 
 /// Project Administrator should this function once project token are generated.
-/// The amount to be deposited must be the same as the amount sold at the sale
+/// The amount to be deposited in total must be the same as the amount sold at the sale,
+/// and it can be split into several deposits(top-ups).
+/// Claims are refused until the pool holds the whole amount.
 /// Note: This contract is supposed to be called from a CIS2 contract
 ///
 /// Caller: Project Token Contract only
@@ -590,92 +1380,107 @@ fn contract_set_tge<S: HasStateApi>(
 /// - Fails to parse parameter
 /// - Status is not Fixed
 /// - The sender is not the project token contract
-/// - The quantity to be deposited differs from the quantity sold in the sale.
+/// - The quantity deposited in total exceeds the quantity sold in the sale.
 #[receive(
     contract = "pub_rido_ccd",
     name = "createPool",
     parameter = "OnReceivingCis2Params<ContractTokenId, ContractTokenAmount>",
-    error = "ContractError"
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
 )]
 fn contract_create_pool<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
-    host: &impl HasHost<State<S>, StateApiType = S>,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
 ) -> ContractResult<()> {
-    let state = host.state();
-    ensure!(!state.paused, CustomContractError::ContractPaused.into());
-    ensure_eq!(
-        state.status,
-        SaleStatus::Fixed,
-        CustomContractError::SaleNotFixed.into()
-    );
-
-    let sender = if let Address::Contract(contract) = ctx.sender() {
-        contract
-    } else {
-        bail!(CustomContractError::ContractOnly.into())
-    };
-
-    ensure!(
-        sender == state.project_token.unwrap_or(ContractAddress::new(0, 0))
-            && ctx.invoker() == state.proj_admin,
-        ContractError::Unauthorized
-    );
+    let sender = rido::ensure_pool_sender(ctx, host.state())?;
 
     let params: OnReceivingCis2Params<ContractTokenId, ContractTokenAmount> =
         ctx.parameter_cursor().get()?;
 
     //[#TODO] Check this func is only called after the sale is over.
     // if not need project_refund func
-    let amount = state.saleinfo.amount_of_pjtoken()?;
-    ensure!(
-        amount == params.amount,
-        CustomContractError::NotMatchAmount.into()
-    );
+    let state = host.state_mut();
+    let funded = state.fund_pool(params.from, params.amount)?;
+    state.receive_tokens(sender, project_token_id(), params.amount)?;
+    let required = state.sold_tokens()?;
+
+    logger.log(&SaleEvent::PoolFunded(PoolFundedEvent {
+        from: params.from,
+        amount: params.amount,
+        funded,
+        required,
+    }))?;
 
     Ok(())
 }
 
 /// Project admin can claim CCD sold at the sale.
-/// Note: No sale fee is charged to the project.
+/// If CCD fees are configured, the CCD is split between the fee recipients
+/// and the project admin, who receives the rest.
+/// Contract recipients are paid through their `receiveCcdFee` entrypoint.
+/// In escrow mode, only the tranches approved and whose milestone has been reached are paid.
 ///
 /// Caller: Anyone on the whitelist
 /// Reject if:
 /// - Contract is paused
 /// - Status is not Fixed
 /// - The sender is not the project admin
-/// - Fails to invoke transfer from this contract to the admin
+/// - A delivery deadline is set and the project has not set TGE and funded the pool yet
+/// - No tranche can be released(escrow mode only)
+/// - Fails to invoke transfer from this contract to any recipient
 #[receive(
     contract = "pub_rido_ccd",
     name = "projectClaim",
-    error = "ContractError"
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
 )]
 fn contract_project_claim<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
-    host: &impl HasHost<State<S>, StateApiType = S>,
-) -> ContractResult<()> {
-    let state = host.state();
-    ensure!(!state.paused, CustomContractError::ContractPaused.into());
-    ensure_eq!(
-        state.status,
-        SaleStatus::Fixed,
-        CustomContractError::SaleNotFixed.into()
-    );
-
-    let proj_admin = host.state().proj_admin;
-
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    rido::ensure_project_claimable(ctx, host.state())?;
+    // With a delivery deadline, the CCD is kept for refunds until the project has delivered.
     ensure!(
-        ctx.sender().matches_account(&proj_admin),
-        ContractError::Unauthorized
+        host.state().delivery_deadline.is_none() || host.state().is_delivered()?,
+        CustomContractError::NotDelivered.into()
     );
-    // Transfer the whole balance to the project admin.
+    // Transfer the whole balance to the project admin and the CCD fee recipients,
+    // except for CCD to be refunded to lottery losers and for excess deposits.
     // [DONE!] charge fee from users
-    let balance = host.self_balance();
-    let transfer_result = host.invoke_transfer(&proj_admin, balance);
+    let balance = if host.state().escrow.is_some() {
+        let now = ctx.metadata().slot_time();
+        let (indexes, amount) = host.state_mut().release_tranches(now)?;
+        for index in indexes {
+            logger.log(&SaleEvent::TrancheReleased(index))?;
+        }
+        amount
+    } else {
+        host.self_balance()
+            .checked_sub(host.state().refundable())
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?
+    };
+    let payouts = host.state().ccd_payouts(balance);
+
+    for (to, amount) in payouts {
+        if amount == Amount::zero() {
+            continue;
+        }
+        let paid = match to {
+            Address::Account(addr) => host.invoke_transfer(&addr, amount).is_ok(),
+            Address::Contract(addr) => host
+                .invoke_contract(&addr, &(), CCD_FEE_ENTRYPOINT, amount)
+                .is_ok(),
+        };
+        ensure!(paid, CustomContractError::TransferError.into());
 
-    ensure!(
-        transfer_result.is_ok(),
-        CustomContractError::TransferError.into()
-    );
+        logger.log(&SaleEvent::CcdPaid(CcdPaidEvent { to, amount }))?;
+    }
 
     Ok(())
 }
@@ -684,6 +1489,16 @@ fn contract_project_claim<S: HasStateApi>(
 // For users
 // ==========================================
 
+/// The optional parameter schema for `userDeposit` function.
+/// Allows users out of the whitelist to deposit.
+#[derive(Debug, Serialize, SchemaType)]
+pub enum Allowance {
+    /// Allocation ticket signed by the backend
+    Ticket(SignedTicket),
+    /// Membership of the whitelist committed to by `setWhitelistRoot`
+    Proof(WhitelistProof),
+}
+
 /// Sale participant call this function to fix the right to purchase tokens
 /// by deposit their CCD to this contract.
 ///
@@ -693,23 +1508,42 @@ fn contract_project_claim<S: HasStateApi>(
 /// - Status is not Ready
 /// - The sender does not have valid priority
 /// - The sender have already deposited
-/// - Hardcap has already been reached
+/// - Hardcap has already been reached(except in lottery mode)
+/// - `MAX_LOTTERY_ENTRIES` users have already entered the draw(lottery mode only)
 /// - Sended CCD not match Sale Amount
+/// - The allocation ticket is given but the sale has no ticket key, or is invalid or expired
+/// - The whitelist proof is given but no whitelist root is set, or is invalid
+///
+/// Users not on the whitelist can pass an allocation ticket signed by the backend
+/// or a proof of membership of the whitelist root (the parameter may be empty otherwise),
+/// which puts them on the whitelist before depositing.
 ///
 /// Note: host.invoke_transfer() can only transfer CCD to the AccountAddress.
 /// If needed, host.invoke_contract() can trasfer CCD to the Contract, but need entrypoint!
 #[receive(
     contract = "pub_rido_ccd",
     name = "userDeposit",
+    parameter = "Option<Allowance>",
     error = "ContractError",
+    event = "SaleEvent",
     mutable,
-    payable
+    payable,
+    enable_logger,
+    crypto_primitives
 )]
 fn contract_user_deposit<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
     amount: Amount,
+    logger: &mut impl HasLogger,
+    crypto_primitives: &impl HasCryptoPrimitives,
 ) -> ContractResult<()> {
+    // no parameter is the same as `None`, as before allowances were introduced
+    let allowance: Option<Allowance> = if ctx.parameter_cursor().size() == 0 {
+        None
+    } else {
+        ctx.parameter_cursor().get()?
+    };
     let state = host.state_mut();
 
     ensure!(!state.paused, CustomContractError::ContractPaused.into());
@@ -730,6 +1564,27 @@ fn contract_user_deposit<S: HasStateApi>(
     );
     let current_priority = current_priority.unwrap();
 
+    match allowance {
+        Some(Allowance::Ticket(signed)) => state.redeem_ticket(
+            crypto_primitives,
+            signed,
+            ctx.sender(),
+            ctx.self_address(),
+            ctx.metadata().slot_time(),
+        )?,
+        Some(Allowance::Proof(proof)) => {
+            state.prove_membership(crypto_primitives, proof, ctx.sender())?
+        },
+        None => {},
+    }
+
+    if state.lottery.is_some() {
+        return register_lottery(ctx, state, amount, current_priority, logger);
+    }
+    if state.overflow.is_some() {
+        return deposit_overflow(ctx, state, amount, current_priority, logger);
+    }
+
     let room = state.saleinfo.check_room_to_apply();
     ensure!(room > 0, CustomContractError::AlreadySaleClosed.into());
 
@@ -770,39 +1625,137 @@ fn contract_user_deposit<S: HasStateApi>(
     );
     let _ = state.deposit(&sender, amount, win_units)?;
 
+    logger.log(&SaleEvent::Deposited(DepositedEvent {
+        user: sender,
+        amount,
+        units: win_units,
+    }))?;
+
+    Ok(())
+}
+
+/// In lottery mode, deposits are accepted regardless of the hardcap
+/// and units are fixed later by `drawLottery`.
+fn register_lottery<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    state: &mut State<S>,
+    amount: Amount,
+    current_priority: Prior,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let sender = ctx.sender();
+    let user = state.get_user_any(&sender)?;
+
+    ensure!(
+        user.deposit_ccd == Amount::zero(),
+        CustomContractError::AlreadyDeposited.into()
+    );
+
+    if user.prior > current_priority {
+        bail!(ContractError::Unauthorized)
+    }
+
+    let calculated_price = state
+        .saleinfo
+        .calc_price_per_unit()?
+        .micro_ccd
+        .checked_mul(user.tgt_units as u64)
+        .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+    ensure!(
+        amount == Amount::from_micro_ccd(calculated_price),
+        CustomContractError::InvalidCcdInput.into()
+    );
+    state.register(&sender, amount)?;
+
+    logger.log(&SaleEvent::Registered(DepositedEvent {
+        user: sender,
+        amount,
+        units: user.tgt_units,
+    }))?;
+
+    Ok(())
+}
+
+/// In overflow mode, any amount from the minimum is accepted regardless of the hardcap
+/// and tokens are allocated when the sale is fixed.
+fn deposit_overflow<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    state: &mut State<S>,
+    amount: Amount,
+    current_priority: Prior,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let sender = ctx.sender();
+    let user = state.get_user_any(&sender)?;
+
+    ensure!(
+        user.deposit_ccd == Amount::zero(),
+        CustomContractError::AlreadyDeposited.into()
+    );
+
+    if user.prior > current_priority {
+        bail!(ContractError::Unauthorized)
+    }
+
+    ensure!(
+        amount >= state.get_overflow()?.min_deposit,
+        CustomContractError::InvalidCcdInput.into()
+    );
+    state.deposit_overflow(&sender, amount)?;
+
+    logger.log(&SaleEvent::Registered(DepositedEvent {
+        user: sender,
+        amount,
+        units: 0,
+    }))?;
+
     Ok(())
 }
 
 /// Sale participants call this function to quit the sale and
 /// to be refunded their ccd.
-/// Note: Not available for now, means no one can quit once deposit their fund.
+/// Note: Not available for now, means no one can quit once deposit their fund,
+/// unless the sale has been suspended or has failed.
 ///
 /// Caller: No one
 /// Reject if:
-/// - Always(currently)
+/// - Always(currently), unless Status is Suspend or Failed
 /// - Contract is paused
 /// - Status is not Ready
 /// - Not on sale
 /// - The sender is not on the whitelist
 /// - The sender has not deposited.
-/// - The sender is ContractAddress.
+/// - The deposit has already been refunded by `refundBatch`.
+/// - The sender is a contract and no refund hook has been set.
 ///
-/// Note: host.invoke_transfer() can only transfer CCD to the AccountAddress.
-/// If needed, host.invoke_contract() can trasfer CCD to the Contract, but need entrypoint!
+/// Note: Contracts are refunded through the entrypoint set by `setRefundHook`.
 #[receive(
     contract = "pub_rido_ccd",
     name = "userQuit",
     error = "ContractError",
-    mutable
+    event = "SaleEvent",
+    mutable,
+    enable_logger
 )]
 fn contract_user_quit<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    user_quit(ctx, host, logger, ctx.sender())
+}
+
+/// Quits the sale on behalf of `sender`, the caller of `userQuit` or the signer of a permit.
+fn user_quit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+    sender: Address,
 ) -> ContractResult<()> {
     let state = host.state_mut();
     ensure!(!state.paused, CustomContractError::ContractPaused.into());
 
-    if state.status != SaleStatus::Suspend {
+    if state.status != SaleStatus::Suspend && state.status != SaleStatus::Failed {
         // currently no one can quit
         ensure!(false, CustomContractError::DisabledForNow.into());
 
@@ -818,27 +1771,246 @@ fn contract_user_quit<S: HasStateApi>(
         );
     }
 
-    let sender = ctx.sender();
     let user = state.get_user(&sender)?;
 
-    ensure!(user.win_units > 0, CustomContractError::NotDeposited.into());
-
-    let user_addr = if let Address::Account(addr) = sender {
-        addr
-    } else {
-        // [#TODO] If need to transfer to Contract, consider invoke_contract.
-        // But in that case, the contract need to implement specific entrypoint.
-        bail!(CustomContractError::AccountOnly.into())
-    };
+    ensure!(!user.refunded, CustomContractError::AlreadyRefunded.into());
+    ensure!(
+        user.deposit_ccd > Amount::zero(),
+        CustomContractError::NotDeposited.into()
+    );
 
+    // the CCD held for a lottery loser or as excess is part of the deposit refunded here
+    state.clear_deposit(&sender)?;
     state.remove_participant(&sender, user.win_units);
 
-    let transfer_result = host.invoke_transfer(&user_addr, user.deposit_ccd);
+    refund_ccd(host, sender, user.deposit_ccd)?;
+
+    logger.log(&SaleEvent::Quit(QuitEvent {
+        user: sender,
+        amount: user.deposit_ccd,
+    }))?;
+
+    Ok(())
+}
+
+/// Refund the deposits of a page of participants when the sale is suspended or has failed.
+/// Accounts are paid by transfer, and contracts through the entrypoint set by `setRefundHook`.
+/// Participants whose refund fails(e.g. contracts without the hook) are left for a later page
+/// or for `userQuit`.
+///
+/// Caller: Anyone
+/// Reject if:
+/// - Fails to parse parameter
+/// - Contract is paused
+/// - Status is neither Suspend nor Failed
+/// - The limit is 0 or the cursor has never been a participant
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "refundBatch",
+    parameter = "RefundBatchParams",
+    return_value = "RefundBatchResponse",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_refund_batch<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<RefundBatchResponse> {
+    let params: RefundBatchParams = ctx.parameter_cursor().get()?;
+
+    let state = host.state();
+    ensure!(!state.paused, CustomContractError::ContractPaused.into());
     ensure!(
-        transfer_result.is_ok(),
-        CustomContractError::TransferError.into()
+        state.status == SaleStatus::Suspend || state.status == SaleStatus::Failed,
+        CustomContractError::Inappropriate.into()
+    );
+
+    let (page, next) = state.refund_page(params.cursor, params.limit)?;
+
+    let mut refunded: u32 = 0;
+    for (user, amount) in page {
+        // marked before paying out so that a hook cannot be refunded twice by reentrance
+        host.state_mut().set_refunded(&user, true)?;
+
+        if refund_ccd(host, user, amount).is_ok() {
+            host.state_mut().clear_deposit(&user)?;
+            refunded += 1;
+            logger.log(&SaleEvent::Refunded(QuitEvent { user, amount }))?;
+        } else {
+            host.state_mut().set_refunded(&user, false)?;
+        }
+    }
+
+    Ok(RefundBatchResponse { refunded, next })
+}
+
+/// In overflow mode, users call this function to be refunded their CCD
+/// which did not buy any token.
+///
+/// Caller: Users who have deposited
+/// Reject if:
+/// - Contract is paused
+/// - The sale is not in overflow mode
+/// - Tokens have not been allocated yet
+/// - The sender has not deposited
+/// - The sender has no excess or has already been refunded(also by `refundBatch`).
+/// - The sender is a contract and no refund hook has been set.
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "userRefundExcess",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_user_refund_excess<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+    ensure!(!state.paused, CustomContractError::ContractPaused.into());
+
+    let sender = ctx.sender();
+    let amount = state.refund_excess(&sender)?;
+
+    refund_ccd(host, sender, amount)?;
+
+    logger.log(&SaleEvent::ExcessRefunded(QuitEvent {
+        user: sender,
+        amount,
+    }))?;
+
+    Ok(())
+}
+
+/// Lottery losers call this function to be refunded their whole deposit.
+/// If the seed has not been revealed within `REVEAL_PERIOD_DAYS` after the end of the sale,
+/// every user who entered the draw can be refunded.
+///
+/// Caller: Lottery losers, or any user who entered the draw once the reveal is overdue
+/// Reject if:
+/// - Contract is paused
+/// - The sale is not in lottery mode
+/// - The lottery has not been drawn yet and the reveal is not overdue
+/// - The sender has won units
+/// - The sender has not deposited or has already been refunded(also by `refundBatch`).
+/// - The sender is a contract and no refund hook has been set.
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "userReclaim",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_user_reclaim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+    ensure!(!state.paused, CustomContractError::ContractPaused.into());
+
+    let sender = ctx.sender();
+    let amount = state.reclaim(&sender, ctx.metadata().slot_time())?;
+
+    refund_ccd(host, sender, amount)?;
+
+    logger.log(&SaleEvent::Reclaimed(QuitEvent {
+        user: sender,
+        amount,
+    }))?;
+
+    Ok(())
+}
+
+/// Participants vote for a tranche of the escrow with the weight of the CCD they paid.
+/// The tranche is approved once the votes reach the quorum.
+///
+/// Caller: Users who have purchased tokens
+/// Reject if:
+/// - Fails to parse parameter
+/// - Contract is paused
+/// - Status is not Fixed
+/// - The sale is not in escrow mode, has no participant vote,
+///   or the project has been declared failed
+/// - The tranche does not exist or has already been approved
+/// - The sender has not purchased any token or has already voted for the tranche
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "voteTranche",
+    parameter = "u8",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_vote_tranche<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let index: u8 = ctx.parameter_cursor().get()?;
+
+    let state = host.state_mut();
+    ensure!(!state.paused, CustomContractError::ContractPaused.into());
+    ensure_eq!(
+        state.status,
+        SaleStatus::Fixed,
+        CustomContractError::SaleNotFixed.into()
     );
 
+    let user = ctx.sender();
+    let (votes, approved) = state.vote_tranche(&user, index)?;
+
+    logger.log(&SaleEvent::TrancheVoted(TrancheVotedEvent { user, index, votes }))?;
+    if approved {
+        logger.log(&SaleEvent::TrancheApproved(index))?;
+    }
+
+    Ok(())
+}
+
+/// After the project has been declared failed, participants call this function
+/// to be refunded their share of the CCD not released from the escrow.
+/// From then on, they cannot claim project tokens anymore.
+///
+/// Caller: Users who have purchased tokens
+/// Reject if:
+/// - Contract is paused
+/// - The sale is not in escrow mode or the project has not been declared failed
+/// - The sender has not purchased any token or has already been refunded.
+/// - The sender is a contract and no refund hook has been set.
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "userEscrowRefund",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_user_escrow_refund<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+    ensure!(!state.paused, CustomContractError::ContractPaused.into());
+
+    let sender = ctx.sender();
+    let amount = state.refund_escrow(&sender)?;
+
+    refund_ccd(host, sender, amount)?;
+
+    logger.log(&SaleEvent::EscrowRefunded(QuitEvent {
+        user: sender,
+        amount,
+    }))?;
+
     Ok(())
 }
 
@@ -852,16 +2024,56 @@ fn contract_user_quit<S: HasStateApi>(
 /// - Status is not Fixed
 /// - Project admin has not yet registered the project token
 /// - Project admin has not yet registered the TGE
+/// - The pool has not been funded with all the tokens sold
+/// - The project has been declared failed(escrow mode)
 /// - The sender is not on the whitelist
 #[receive(
     contract = "pub_rido_ccd",
     name = "userClaim",
     error = "ContractError",
-    mutable
+    event = "SaleEvent",
+    mutable,
+    enable_logger
 )]
 fn contract_user_claim<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
     host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    user_claim(ctx, host, logger, ctx.sender(), ctx.sender())
+}
+
+/// Same as `userClaim`, with the tokens sent to the given address.
+///
+/// Caller: Anyone on the whitelist
+/// Reject if:
+/// - Fails to parse parameter
+/// - Same as `userClaim`
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "userClaimTo",
+    parameter = "Address",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_user_claim_to<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let to: Address = ctx.parameter_cursor().get()?;
+    user_claim(ctx, host, logger, ctx.sender(), to)
+}
+
+/// Claims the tokens vested for `user` and sends them to `to`.
+fn user_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+    user: Address,
+    to: Address,
 ) -> ContractResult<()> {
     let state = host.state_mut();
 
@@ -871,6 +2083,11 @@ fn contract_user_claim<S: HasStateApi>(
         SaleStatus::Fixed,
         CustomContractError::SaleNotFixed.into()
     );
+    // Participants are refunded by `userEscrowRefund` instead once the project has failed.
+    ensure!(
+        !state.escrow.as_ref().map_or(false, |escrow| escrow.failed),
+        CustomContractError::EscrowFailed.into()
+    );
 
     ensure!(
         state.project_token.is_some(),
@@ -880,50 +2097,102 @@ fn contract_user_claim<S: HasStateApi>(
         state.schedule.vesting_start.is_some(),
         CustomContractError::NotSetTge.into()
     );
+    ensure!(
+        state.is_pool_funded()?,
+        CustomContractError::NotDeposited.into()
+    );
     let vesting_start = state.schedule.vesting_start.unwrap();
 
-    let user = ctx.sender();
     let user_state = state.get_user(&user)?;
 
     let now = ctx.metadata().slot_time();
 
-    let (amount, inc): (ContractTokenAmount, u8) = state.calc_vesting_amount(
+    let (amount, inc): (ContractTokenAmount, u8) = state.calc_vesting_amount_purchased(
+        &user_state,
         now,
         vesting_start,
-        user_state.win_units as u64,
-        100 - PUBLIC_RIDO_FEE,
-        user_state.claimed_inc,
+        state.user_share,
     )?;
 
-    if inc > user_state.claimed_inc {
-        state.increment_user_claimed(&user, inc)?;
+    if inc > user_state.claimed_inc || amount.0 > 0 {
+        state.increment_user_claimed(&user, inc, amount)?;
+        state.uncount_claimant(&user_state, amount)?;
     }
 
     if amount.0 > 0 {
-        let to = match user {
-            Address::Account(account_address) => Receiver::from_account(account_address),
-            Address::Contract(contract_address) => Receiver::from_contract(
-                contract_address,
-                OwnedEntrypointName::new_unchecked("callback".to_owned()),
-            ),
-        };
-
-        let transfer = Transfer {
-            from: Address::from(ctx.self_address()),
-            to,
-            token_id: TokenIdUnit(),
-            amount,
-            data: AdditionalData::empty(),
-        };
-
         let project_token = state.project_token.unwrap();
-        let _ = host.invoke_contract(
-            &project_token,
-            &TransferParams::from(vec![transfer]),
-            EntrypointName::new_unchecked("transfer"),
-            Amount::zero(),
-        )?;
+        let hook = state.claim_hook();
+        transfer_token_via(ctx, host, project_token, to, amount, hook)?;
+        host.state_mut().record_claimed(project_token, amount)?;
+
+        logger.log(&SaleEvent::Claimed(ClaimedEvent { user, amount, inc }))?;
     }
 
     Ok(())
 }
+
+/// Invoke `userClaim`, `userClaimTo` or `userQuit` on behalf of a participant
+/// who has signed the message with their account keys(CIS-3 sponsored transaction),
+/// so that a relayer pays the transaction fee.
+///
+/// Caller: Anyone(the relayer)
+/// Reject if:
+/// - Fails to parse parameter
+/// - The message is for another contract or an entrypoint not supported
+/// - The message has expired
+/// - The nonce is not the next nonce of the signer
+/// - The signature is not valid for the signer's account keys
+/// - The invoked entrypoint rejects
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "permit",
+    parameter = "PermitParam",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger,
+    crypto_primitives
+)]
+fn contract_permit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+    crypto_primitives: &impl HasCryptoPrimitives,
+) -> ContractResult<()> {
+    let param: PermitParam = ctx.parameter_cursor().get()?;
+    let message = param.message;
+
+    ensure!(
+        message.contract_address == ctx.self_address(),
+        CustomContractError::InvalidPermit.into()
+    );
+    ensure!(
+        message.timestamp > ctx.metadata().slot_time(),
+        CustomContractError::PermitExpired.into()
+    );
+
+    host.state_mut().use_nonce(&param.signer, message.nonce)?;
+
+    let message_hash = message_hash(crypto_primitives, &param.signer, &message);
+    let valid = host
+        .check_account_signature(param.signer, &param.signature, &message_hash)
+        .unwrap_or(false);
+    ensure!(valid, CustomContractError::WrongSignature.into());
+
+    logger.log(&SaleEvent::Nonce(NonceEvent {
+        account: param.signer,
+        nonce: message.nonce,
+    }))?;
+
+    let signer = Address::Account(param.signer);
+    let entry_point: &str = message.entry_point.as_entrypoint_name().into();
+    match entry_point {
+        "userClaim" => user_claim(ctx, host, logger, signer, signer),
+        "userClaimTo" => {
+            let to: Address = from_bytes(&message.payload)?;
+            user_claim(ctx, host, logger, signer, to)
+        },
+        "userQuit" => user_quit(ctx, host, logger, signer),
+        _ => bail!(CustomContractError::InvalidPermit.into()),
+    }
+}
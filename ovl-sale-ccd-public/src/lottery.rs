@@ -0,0 +1,184 @@
+use crate::state::{State, *};
+use concordium_std::*;
+
+/// Secret revealed at the draw, whose sha256 hash is committed at init.
+pub type LotterySeed = [u8; 32];
+
+/// Days after the end of the sale within which the seed must be revealed.
+/// Past this, the draw is off and every user can reclaim their deposit.
+pub(crate) const REVEAL_PERIOD_DAYS: u64 = 7;
+
+/// Maximum number of users who can enter the draw, so that it fits in one transaction.
+pub(crate) const MAX_LOTTERY_ENTRIES: u32 = 1_000;
+
+/// Allocation by lottery for oversubscribed sales.
+/// Users deposit for their units during the sale regardless of the hardcap,
+/// and winners are drawn after the sale closes from a seed committed beforehand.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct Lottery {
+    /// sha256 hash of the seed, fixed before anyone deposits
+    pub(crate) commitment: HashSha2256,
+    /// The seed revealed at the draw(`None` until drawn)
+    pub(crate) seed: Option<LotterySeed>,
+    /// CCD deposited by losers and not yet reclaimed
+    pub(crate) refundable: Amount,
+    /// Number of users who have entered the draw
+    pub(crate) entries: u32,
+}
+
+impl Lottery {
+    pub(crate) fn new(commitment: HashSha2256) -> Self {
+        Lottery {
+            commitment,
+            seed: None,
+            refundable: Amount::zero(),
+            entries: 0,
+        }
+    }
+
+    pub(crate) fn is_drawn(&self) -> bool {
+        self.seed.is_some()
+    }
+}
+
+impl<S: HasStateApi> State<S> {
+    pub(crate) fn get_lottery(&self) -> ContractResult<&Lottery> {
+        self.lottery
+            .as_ref()
+            .ok_or(ContractError::from(CustomContractError::NotLottery))
+    }
+
+    /// Whether the seed is still not revealed `REVEAL_PERIOD_DAYS` after the end of the sale.
+    pub(crate) fn is_reveal_overdue(&self, now: Timestamp) -> ContractResult<bool> {
+        if self.get_lottery()?.is_drawn() {
+            return Ok(false);
+        }
+        let deadline = self
+            .schedule
+            .close_at
+            .checked_add(Duration::from_days(REVEAL_PERIOD_DAYS))
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+        Ok(now > deadline)
+    }
+
+    /// Records the deposit of a user entering the draw. No units are fixed yet.
+    pub(crate) fn register(&mut self, user: &Address, amount: Amount) -> ContractResult<()> {
+        let lottery = self
+            .lottery
+            .as_mut()
+            .ok_or(ContractError::from(CustomContractError::NotLottery))?;
+        ensure!(
+            lottery.entries < MAX_LOTTERY_ENTRIES,
+            CustomContractError::TooManyEntries.into()
+        );
+        lottery.entries += 1;
+
+        let mut user = self
+            .participants
+            .get_mut(user)
+            .ok_or(ContractError::Unauthorized)?;
+        user.deposit_ccd = amount;
+        Ok(())
+    }
+
+    /// Reveals the seed and fixes the units of the winners.
+    /// The seed can no longer be revealed once the reveal is overdue.
+    ///
+    /// Each registered user is ranked by sha256(seed || entrants || address),
+    /// `entrants` being the sha256 hash of the addresses of every registered user.
+    /// As the seed is committed before anyone deposits, the ranking depends on the final
+    /// entrant set, which is not known to whoever committed the seed.
+    /// Users are picked in that order as long as their units fit under the hardcap.
+    /// Returns the winners with their units.
+    pub(crate) fn draw_lottery(
+        &mut self,
+        now: Timestamp,
+        seed: LotterySeed,
+        crypto_primitives: &impl HasCryptoPrimitives,
+    ) -> ContractResult<Vec<(Address, u8)>> {
+        let lottery = self.get_lottery()?;
+        ensure!(
+            !lottery.is_drawn(),
+            CustomContractError::AlreadyDrawn.into()
+        );
+        ensure!(
+            !self.is_reveal_overdue(now)?,
+            CustomContractError::RevealOverdue.into()
+        );
+        ensure!(
+            crypto_primitives.hash_sha2_256(&seed) == lottery.commitment,
+            CustomContractError::InvalidSeed.into()
+        );
+
+        let mut entrants: Vec<(Address, UserState)> = Vec::new();
+        for (addr, user) in self.participants.iter() {
+            if user.deposit_ccd > Amount::zero() {
+                entrants.push((*addr, user.clone()));
+            }
+        }
+        let mut addresses = Vec::new();
+        for (addr, _) in entrants.iter() {
+            addresses.extend(to_bytes(addr));
+        }
+        let entropy = crypto_primitives.hash_sha2_256(&addresses);
+
+        let mut entries: Vec<([u8; 32], Address, UserState)> = Vec::new();
+        for (addr, user) in entrants {
+            let mut data = seed.to_vec();
+            data.extend(entropy.0);
+            data.extend(to_bytes(&addr));
+            let score = crypto_primitives.hash_sha2_256(&data);
+            entries.push((score.0, addr, user));
+        }
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut winners: Vec<(Address, u8)> = Vec::new();
+        let mut refundable = Amount::zero();
+        for (_, addr, user) in entries {
+            let units = user.tgt_units as UnitsAmount;
+            if units <= self.saleinfo.check_room_to_apply() {
+                self.deposit(&addr, user.deposit_ccd, user.tgt_units)?;
+                winners.push((addr, user.tgt_units));
+            } else {
+                refundable += user.deposit_ccd;
+            }
+        }
+
+        if let Some(lottery) = self.lottery.as_mut() {
+            lottery.seed = Some(seed);
+            lottery.refundable = refundable;
+        }
+        Ok(winners)
+    }
+
+    /// Clears the deposit of a loser and returns the CCD to be refunded.
+    /// If the reveal is overdue, every user who entered the draw is regarded as a loser.
+    pub(crate) fn reclaim(&mut self, user: &Address, now: Timestamp) -> ContractResult<Amount> {
+        let overdue = self.is_reveal_overdue(now)?;
+        ensure!(
+            self.get_lottery()?.is_drawn() || overdue,
+            CustomContractError::NotDrawn.into()
+        );
+
+        let mut user = self
+            .participants
+            .get_mut(user)
+            .ok_or(ContractError::Unauthorized)?;
+        ensure!(user.win_units == 0, ContractError::Unauthorized);
+        ensure!(!user.refunded, CustomContractError::AlreadyRefunded.into());
+        ensure!(
+            user.deposit_ccd > Amount::zero(),
+            CustomContractError::NotDeposited.into()
+        );
+        let amount = user.deposit_ccd;
+        user.deposit_ccd = Amount::zero();
+
+        // nothing is held as refundable without the draw
+        if !overdue {
+            if let Some(lottery) = self.lottery.as_mut() {
+                lottery.refundable -= amount;
+            }
+        }
+        Ok(amount)
+    }
+}
@@ -0,0 +1,43 @@
+use crate::state::{State, *};
+use concordium_std::*;
+use sale_utils::merkle::{MerkleNode, WhitelistLeaf};
+
+/// Proof that the sender is in the whitelist committed to by `setWhitelistRoot`,
+/// as produced by `sale_utils::merkle::MerkleTree::proof`.
+#[derive(Debug, Serialize, SchemaType, Clone)]
+pub struct WhitelistProof {
+    /// Priority for participation in the sale
+    pub prior: Prior,
+    /// Number of units the user can purchase
+    pub units: u8,
+    /// Siblings on the path from the leaf of the user to the root
+    pub proof: Vec<MerkleNode>,
+}
+
+impl<S: HasStateApi> State<S> {
+    /// Verifies the proof of `sender` against the root and puts them on the whitelist.
+    /// Users already on the whitelist keep their entry.
+    pub(crate) fn prove_membership(
+        &mut self,
+        crypto_primitives: &impl HasCryptoPrimitives,
+        proof: WhitelistProof,
+        sender: Address,
+    ) -> ContractResult<()> {
+        let root = self
+            .whitelist_root
+            .as_ref()
+            .ok_or(ContractError::from(CustomContractError::Inappropriate))?;
+        let leaf = WhitelistLeaf {
+            user: sender,
+            prior: proof.prior,
+            units: proof.units,
+        };
+        ensure!(
+            leaf.units > 0 && root.verify(crypto_primitives, &leaf, &proof.proof),
+            CustomContractError::InvalidProof.into()
+        );
+
+        self.allow_user(&sender, leaf.prior, leaf.units);
+        Ok(())
+    }
+}
@@ -0,0 +1,389 @@
+//! Previous layouts of the contract state and the migrations from them.
+//! A new module reads the old layout from the root of the state right after
+//! `upgrade` and writes the current `State` in its place.
+use crate::{
+    fees::FeeState,
+    multisig::Multisig,
+    participants::Participants,
+    state::{State, *},
+    timelock::Timelock,
+};
+use concordium_std::{
+    collections::{BTreeMap, BTreeSet},
+    *,
+};
+use sale_utils::{BPS_DENOMINATOR, PUBLIC_RIDO_FEE, PUBLIC_RIDO_FEE_BBB, PUBLIC_RIDO_FEE_OVL};
+
+/// Timelock delay given to instances migrated from v0, which had no timelock.
+pub const DEFAULT_TIMELOCK_DELAY_DAYS: u64 = 2;
+
+/// The state layout of the first release(v0.3.0), which had no version field.
+#[derive(Debug, DeserialWithState)]
+#[concordium(state_parameter = "S")]
+pub struct StateV0<S: HasStateApi> {
+    pub(crate) proj_admin: AccountAddress,
+    pub(crate) status: SaleStatus,
+    pub(crate) paused: bool,
+    pub(crate) addr_ovl: Address,
+    pub(crate) addr_bbb: Address,
+    pub(crate) ovl_claimed_inc: u8,
+    pub(crate) bbb_claimed_inc: u8,
+    pub(crate) project_token: Option<ContractAddress>,
+    pub(crate) schedule: SaleScheduleV0,
+    pub(crate) saleinfo: SaleInfo,
+    pub(crate) participants: StateMap<Address, UserStateV0, S>,
+}
+
+/// The state layout of the first release with `upgrade`(`STATE_VERSION` 1),
+/// which put the version in front of the v0 layout.
+#[derive(Debug, DeserialWithState)]
+#[concordium(state_parameter = "S")]
+pub struct StateV1<S: HasStateApi> {
+    pub(crate) version: u16,
+    pub(crate) state: StateV0<S>,
+}
+
+/// The schedule layout of the first release(v0.3.0), which only had stepped vesting.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct SaleScheduleV0 {
+    pub(crate) open_at: BTreeMap<Timestamp, Prior>,
+    pub(crate) close_at: Timestamp,
+    pub(crate) vesting_start: Option<Timestamp>,
+    pub(crate) vesting_period: BTreeMap<Duration, u8>,
+}
+
+impl From<SaleScheduleV0> for SaleSchedule {
+    fn from(v0: SaleScheduleV0) -> Self {
+        SaleSchedule {
+            open_at: v0.open_at,
+            close_at: v0.close_at,
+            vesting_start: v0.vesting_start,
+            vesting_period: VestingSchedule::Stepped(
+                v0.vesting_period
+                    .into_iter()
+                    .map(|(duration, per)| (duration, percentage_to_bps(per)))
+                    .collect(),
+            ),
+        }
+    }
+}
+
+/// v0 vesting steps were whole percentages.
+fn percentage_to_bps(percentage: u8) -> BasisPoints {
+    u16::from(percentage) * (BPS_DENOMINATOR / 100)
+}
+
+impl SaleScheduleV0 {
+    /// Tokens paid out by the first `claimed_inc` steps in v0,
+    /// which rounded down the allocation of every step separately.
+    /// v0 shares were whole percentages, so `shared` divides exactly in basis points.
+    fn claimed_amount(
+        &self,
+        token_per_unit: ContractTokenAmount,
+        units: u64,
+        shared: BasisPoints,
+        claimed_inc: u8,
+    ) -> Result<ContractTokenAmount, CustomContractError> {
+        let total_claimable = u128::from(token_per_unit.0)
+            .checked_mul(u128::from(units))
+            .and_then(|total| total.checked_mul(u128::from(shared)))
+            .ok_or(CustomContractError::OverflowError)?
+            / u128::from(BPS_DENOMINATOR);
+
+        let mut amount: u128 = 0;
+        for (_, per) in self.vesting_period.iter().take(usize::from(claimed_inc)) {
+            amount += total_claimable
+                .checked_mul(u128::from(*per))
+                .ok_or(CustomContractError::OverflowError)?
+                / 100;
+        }
+        Ok(ContractTokenAmount::from(u64::try_from(amount)?))
+    }
+}
+
+/// The participant layout of the first release(v0.3.0).
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct UserStateV0 {
+    pub(crate) prior: Prior,
+    pub(crate) deposit_ccd: Amount,
+    pub(crate) tgt_units: u8,
+    pub(crate) win_units: u8,
+    pub(crate) claimed_inc: u8,
+}
+
+impl From<UserStateV0> for UserState {
+    fn from(v0: UserStateV0) -> Self {
+        UserState {
+            prior: v0.prior,
+            deposit_ccd: v0.deposit_ccd,
+            tgt_units: v0.tgt_units,
+            win_units: v0.win_units,
+            claimed_inc: v0.claimed_inc,
+            alloc_tokens: ContractTokenAmount::from(0),
+            refundable_ccd: Amount::zero(),
+            refunded: false,
+            claimed_amount: ContractTokenAmount::from(0),
+            escrow_votes: 0,
+        }
+    }
+}
+
+impl<S: HasStateApi> State<S> {
+    /// Converts the v0 layout(also the v1 layout without its version) into the current one.
+    /// Participants are moved entry by entry and the old entries are deleted,
+    /// so the sale continues with the same deposits and claim progress.
+    /// v0 did not record the order users joined in, so they are walked in address order.
+    /// The contract owner becomes the only signer, as v0 was controlled by the owner alone.
+    /// TGE and project token changes are delayed by `DEFAULT_TIMELOCK_DELAY_DAYS`.
+    /// Every user purchased `TARGET_UNITS` in v0, so that becomes the only tier.
+    /// v0 did not record the pool funding, so the pool is regarded as funded
+    /// once anyone has claimed(claims could only succeed if the tokens had arrived).
+    /// Token balances are not known either, so nothing can be recovered until tokens arrive.
+    /// Claimed amounts are restored from the steps claimed in v0.
+    /// The OVL and BBB addresses make up the fee table with the v0 shares.
+    pub(crate) fn migrate_from_v0(
+        mut legacy: StateV0<S>,
+        owner: AccountAddress,
+        state_builder: &mut StateBuilder<S>,
+    ) -> ContractResult<Self> {
+        let multisig = Multisig::new(state_builder, BTreeSet::from([owner]), 1)?;
+
+        let token_per_unit = legacy.saleinfo.token_per_unit;
+        let sold_units = cmp::min(legacy.saleinfo.max_units, legacy.saleinfo.applied_units);
+        let mut claimed_tokens: u64 = 0;
+        let mut fees: BTreeMap<Address, FeeState> = BTreeMap::new();
+        for (recipient, bps, claimed_inc) in [
+            (legacy.addr_ovl, PUBLIC_RIDO_FEE_OVL, legacy.ovl_claimed_inc),
+            (legacy.addr_bbb, PUBLIC_RIDO_FEE_BBB, legacy.bbb_claimed_inc),
+        ] {
+            let claimed_amount = legacy.schedule.claimed_amount(
+                token_per_unit,
+                sold_units as u64,
+                bps,
+                claimed_inc,
+            )?;
+            claimed_tokens = claimed_tokens
+                .checked_add(claimed_amount.0)
+                .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+
+            // both fees go to the same entry if they were paid to the same address
+            let fee = fees.entry(recipient).or_insert_with(|| FeeState::new(0));
+            fee.bps += bps;
+            fee.claimed_inc = cmp::max(fee.claimed_inc, claimed_inc);
+            fee.claimed_amount += claimed_amount;
+        }
+
+        let mut state = State {
+            version: STATE_VERSION,
+            proj_admin: legacy.proj_admin,
+            status: legacy.status,
+            paused: legacy.paused,
+            user_share: BPS_DENOMINATOR - PUBLIC_RIDO_FEE,
+            fees,
+            project_token: legacy.project_token,
+            schedule: SaleSchedule::from(legacy.schedule.clone()),
+            saleinfo: legacy.saleinfo,
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            participants: Participants::new(state_builder),
+            multisig,
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(
+                state_builder,
+                Duration::from_days(DEFAULT_TIMELOCK_DELAY_DAYS),
+            ),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(claimed_tokens),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+
+        let mut claimed = legacy.ovl_claimed_inc > 0 || legacy.bbb_claimed_inc > 0;
+        for (addr, user) in legacy.participants.iter() {
+            claimed |= user.claimed_inc > 0;
+            let purchased = UserState::from(user.clone());
+            let claimed_amount = legacy.schedule.claimed_amount(
+                token_per_unit,
+                u64::from(user.win_units),
+                state.user_share,
+                user.claimed_inc,
+            )?;
+
+            // replay the purchase and the claims to count the users yet to claim all their tokens
+            state.count_claimant(state.tokens_of(&purchased)?)?;
+            state.uncount_claimant(&purchased, claimed_amount)?;
+            state.claimed_tokens = state
+                .claimed_tokens
+                .0
+                .checked_add(claimed_amount.0)
+                .map(ContractTokenAmount::from)
+                .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+            state.participants.insert(
+                *addr,
+                UserState {
+                    claimed_amount,
+                    ..purchased
+                },
+            );
+        }
+        legacy.participants.clear();
+
+        if claimed {
+            state.pool_funded = state.saleinfo.amount_of_pjtoken()?;
+        }
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_infrastructure::*;
+
+    const USER1_ADDR: Address = Address::Account(AccountAddress([10u8; 32]));
+    const USER2_ADDR: Address = Address::Account(AccountAddress([11u8; 32]));
+    const USER3_ADDR: Address = Address::Account(AccountAddress([12u8; 32]));
+
+    #[test]
+    fn test_migrate_from_v0() {
+        let mut state_builder = TestStateBuilder::new();
+        let schedule = SaleScheduleV0 {
+            open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+            close_at: Timestamp::from_timestamp_millis(30),
+            vesting_start: Some(Timestamp::from_timestamp_millis(50)),
+            vesting_period: BTreeMap::from([(Duration::from_days(1), 100)]),
+        };
+        let saleinfo = SaleInfo {
+            price_per_token: 5_000_000,
+            token_per_unit: 200.into(),
+            max_units: 100,
+            min_units: 50,
+            applied_units: 60,
+        };
+        let user1 = UserStateV0 {
+            prior: Prior::TOP,
+            deposit_ccd: Amount::from_ccd(1000),
+            tgt_units: 1,
+            win_units: 1,
+            claimed_inc: 1,
+        };
+        let user2 = UserStateV0 {
+            prior: Prior::SECOND,
+            deposit_ccd: Amount::zero(),
+            tgt_units: 1,
+            win_units: 0,
+            claimed_inc: 0,
+        };
+        let user3 = UserStateV0 {
+            prior: Prior::TOP,
+            deposit_ccd: Amount::from_ccd(1000),
+            tgt_units: 1,
+            win_units: 1,
+            claimed_inc: 0,
+        };
+        let mut participants = state_builder.new_map();
+        participants.insert(USER1_ADDR, user1.clone());
+        participants.insert(USER2_ADDR, user2.clone());
+        participants.insert(USER3_ADDR, user3.clone());
+        let legacy = StateV0 {
+            proj_admin: AccountAddress([1u8; 32]),
+            status: SaleStatus::Fixed,
+            paused: false,
+            addr_ovl: Address::Account(AccountAddress([2u8; 32])),
+            addr_bbb: Address::Account(AccountAddress([3u8; 32])),
+            ovl_claimed_inc: 1,
+            bbb_claimed_inc: 0,
+            project_token: Some(ContractAddress::new(1000, 0)),
+            schedule: schedule.clone(),
+            saleinfo: saleinfo.clone(),
+            participants,
+        };
+
+        let owner = AccountAddress([0u8; 32]);
+        let state = State::migrate_from_v0(legacy, owner, &mut state_builder).unwrap();
+
+        assert_eq!(state.version, STATE_VERSION);
+        assert_eq!(state.status, SaleStatus::Fixed);
+        assert_eq!(state.schedule, SaleSchedule::from(schedule));
+        assert_eq!(
+            state.schedule.vesting_period,
+            VestingSchedule::Stepped(BTreeMap::from([(Duration::from_days(1), 10_000)])),
+            "v0 percentages should be converted into basis points"
+        );
+        assert_eq!(state.saleinfo, saleinfo);
+        assert_eq!(state.tier_units, BTreeMap::from([(Tier::T0, TARGET_UNITS)]));
+        assert!(
+            state.multisig.is_signer(&owner),
+            "owner should be the signer"
+        );
+        assert_eq!(state.multisig.threshold, 1);
+        assert_eq!(
+            state.timelock.delay,
+            Duration::from_days(DEFAULT_TIMELOCK_DELAY_DAYS)
+        );
+        assert!(
+            state.is_pool_funded().unwrap(),
+            "the pool should be funded as claims have started in v0"
+        );
+        // 60 units of 200 tokens, 5% for OVL and 90% of a unit for user1
+        assert_eq!(state.user_share, 9_000);
+        assert_eq!(
+            state.fees,
+            BTreeMap::from([
+                (
+                    Address::Account(AccountAddress([2u8; 32])),
+                    FeeState {
+                        bps: 500,
+                        ccd_bps: 0,
+                        claimed_inc: 1,
+                        claimed_amount: ContractTokenAmount::from(600),
+                    }
+                ),
+                (
+                    Address::Account(AccountAddress([3u8; 32])),
+                    FeeState::new(500)
+                ),
+            ])
+        );
+        assert_eq!(state.claimed_tokens, ContractTokenAmount::from(600 + 180));
+        assert_eq!(
+            state.unclaimed_users, 1,
+            "only user3 should be yet to claim their tokens"
+        );
+        assert_eq!(state.participants.iter().count(), 3);
+        assert_eq!(
+            state.participants.get(&USER1_ADDR).map(|u| u.clone()),
+            Some(UserState {
+                claimed_amount: ContractTokenAmount::from(180),
+                ..UserState::from(user1)
+            }),
+            "user1 should keep its deposit and claim progress"
+        );
+        assert_eq!(
+            state.participants.get(&USER2_ADDR).map(|u| u.clone()),
+            Some(UserState::from(user2)),
+            "user2 should be migrated as well"
+        );
+        assert_eq!(
+            state.participants.get(&USER3_ADDR).map(|u| u.clone()),
+            Some(UserState::from(user3)),
+            "user3 should keep its deposit"
+        );
+        let (page, next) = state.participants.walk(0, 10).unwrap();
+        assert_eq!(
+            page.iter().map(|(addr, _)| *addr).collect::<Vec<_>>(),
+            vec![USER1_ADDR, USER2_ADDR, USER3_ADDR],
+            "participants should be paged in address order"
+        );
+        assert_eq!(next, None);
+    }
+}
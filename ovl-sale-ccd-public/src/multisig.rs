@@ -0,0 +1,178 @@
+use crate::{fees::ChangeFeeRecipientParams, state::*};
+use concordium_std::{collections::BTreeSet, *};
+
+/// Identifier of a proposal, issued sequentially from 0.
+pub type ProposalId = u64;
+
+/// Emergency admin actions which take effect only after
+/// enough signers have approved them.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub enum AdminAction {
+    /// Change TGE(vesting period)
+    ChangeTge(Timestamp),
+    /// Change project token contract
+    ChangePjtoken(ContractAddress),
+    /// Change the status to something arbitrary
+    SetStatus(SaleStatus),
+    /// Move a share of the sale fee to another recipient
+    ChangeFeeRecipient(ChangeFeeRecipientParams),
+    /// Replace the signer set and the threshold
+    UpdateSigners(SignersParams),
+}
+
+/// Signer set and how many of them need to approve a proposal.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct SignersParams {
+    /// Accounts allowed to propose, approve and execute
+    pub(crate) signers: BTreeSet<AccountAddress>,
+    /// Number of approvals needed to execute a proposal
+    pub(crate) threshold: u8,
+}
+
+/// A pending admin action.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct Proposal {
+    /// Action to be executed
+    pub(crate) action: AdminAction,
+    /// Signer who created this proposal
+    pub(crate) proposer: AccountAddress,
+    /// Signers who approved this proposal(including the proposer)
+    pub(crate) approvals: BTreeSet<AccountAddress>,
+}
+
+/// Multi-signature approval of emergency admin actions.
+#[derive(Debug, Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+pub struct Multisig<S: HasStateApi> {
+    /// Accounts allowed to propose, approve and execute
+    pub(crate) signers: BTreeSet<AccountAddress>,
+    /// Number of approvals needed to execute a proposal
+    pub(crate) threshold: u8,
+    /// Proposals not executed(or cancelled) yet
+    pub(crate) proposals: StateMap<ProposalId, Proposal, S>,
+    /// Id to be given to the next proposal
+    pub(crate) next_proposal_id: ProposalId,
+}
+
+impl<S: HasStateApi> Multisig<S> {
+    pub(crate) fn new(
+        state_builder: &mut StateBuilder<S>,
+        signers: BTreeSet<AccountAddress>,
+        threshold: u8,
+    ) -> Result<Self, CustomContractError> {
+        ensure_valid_signers(&signers, threshold)?;
+
+        Ok(Multisig {
+            signers,
+            threshold,
+            proposals: state_builder.new_map(),
+            next_proposal_id: 0,
+        })
+    }
+
+    pub(crate) fn is_signer(&self, account: &AccountAddress) -> bool {
+        self.signers.contains(account)
+    }
+
+    pub(crate) fn propose(&mut self, proposer: AccountAddress, action: AdminAction) -> ProposalId {
+        let id = self.next_proposal_id;
+        self.proposals.insert(
+            id,
+            Proposal {
+                action,
+                proposer,
+                approvals: BTreeSet::from([proposer]),
+            },
+        );
+        self.next_proposal_id += 1;
+        id
+    }
+
+    pub(crate) fn approve(&mut self, id: ProposalId, signer: AccountAddress) -> ContractResult<()> {
+        let mut proposal = self
+            .proposals
+            .get_mut(&id)
+            .ok_or(ContractError::from(CustomContractError::UnknownProposal))?;
+        ensure!(
+            proposal.approvals.insert(signer),
+            CustomContractError::AlreadyApproved.into()
+        );
+        Ok(())
+    }
+
+    /// Removes the proposal and returns its action
+    /// if enough of the current signers have approved it.
+    pub(crate) fn take_approved(&mut self, id: ProposalId) -> ContractResult<AdminAction> {
+        let proposal = self
+            .proposals
+            .get(&id)
+            .map(|p| p.clone())
+            .ok_or(ContractError::from(CustomContractError::UnknownProposal))?;
+
+        // approvals of accounts removed from the signer set no longer count
+        let approvals = proposal
+            .approvals
+            .iter()
+            .filter(|signer| self.signers.contains(*signer))
+            .count();
+        ensure!(
+            approvals >= self.threshold as usize,
+            CustomContractError::NotEnoughApprovals.into()
+        );
+
+        self.proposals.remove(&id);
+        Ok(proposal.action)
+    }
+
+    pub(crate) fn cancel(&mut self, id: ProposalId) {
+        self.proposals.remove(&id);
+    }
+
+    pub(crate) fn update_signers(
+        &mut self,
+        signers: BTreeSet<AccountAddress>,
+        threshold: u8,
+    ) -> Result<(), CustomContractError> {
+        ensure_valid_signers(&signers, threshold)?;
+        self.signers = signers;
+        self.threshold = threshold;
+        Ok(())
+    }
+}
+
+fn ensure_valid_signers(
+    signers: &BTreeSet<AccountAddress>,
+    threshold: u8,
+) -> Result<(), CustomContractError> {
+    ensure!(
+        threshold > 0 && threshold as usize <= signers.len(),
+        CustomContractError::Inappropriate
+    );
+    Ok(())
+}
+
+#[cfg(any(feature = "wasm-test", test))]
+/// implements PartialEq for `claim_eq` inside test functions.
+impl<S: HasStateApi> PartialEq for Multisig<S> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.signers != other.signers {
+            return false;
+        }
+        if self.threshold != other.threshold {
+            return false;
+        }
+        if self.next_proposal_id != other.next_proposal_id {
+            return false;
+        }
+        if self.proposals.iter().count() != other.proposals.iter().count() {
+            return false;
+        }
+        for (id, proposal) in self.proposals.iter() {
+            match other.proposals.get(&id) {
+                Some(other_proposal) if proposal.clone() == other_proposal.clone() => {},
+                _ => return false,
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,257 @@
+use crate::{
+    participants::Position,
+    state::{State, *},
+};
+use concordium_std::*;
+
+/// The parameter schema for `settleOverflow` function.
+#[derive(Debug, Serialize, SchemaType)]
+pub struct SettleOverflowParams {
+    /// Maximum number of participants to allocate in this page(at least 1)
+    pub limit: u32,
+}
+
+/// Pro-rata allocation for oversubscribed sales.
+/// Users deposit any amount of CCD above a minimum during the sale,
+/// and `max_units * token_per_unit` is shared in proportion to the deposits after it closes.
+/// CCD which did not buy any token becomes refundable.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct Overflow {
+    /// Minimum CCD to be deposited by a user
+    pub(crate) min_deposit: Amount,
+    /// CCD deposited by all users
+    pub(crate) total_deposit: Amount,
+    /// Project tokens allocated to all users(fixed when settled)
+    pub(crate) allocated_tokens: ContractTokenAmount,
+    /// Excess CCD not yet refunded(fixed when settled)
+    pub(crate) refundable: Amount,
+    /// Whether the allocation has been fixed
+    pub(crate) settled: bool,
+    /// Position of the next participant to be allocated by `settleOverflow`
+    pub(crate) next: Position,
+}
+
+impl Overflow {
+    pub(crate) fn new(min_deposit: Amount) -> Self {
+        Overflow {
+            min_deposit,
+            total_deposit: Amount::zero(),
+            allocated_tokens: ContractTokenAmount::from(0),
+            refundable: Amount::zero(),
+            settled: false,
+            next: 0,
+        }
+    }
+
+    /// Tokens and excess CCD of a user who deposited `deposit`.
+    fn allocate(
+        &self,
+        saleinfo: &SaleInfo,
+        deposit: Amount,
+    ) -> Result<(ContractTokenAmount, Amount), CustomContractError> {
+        let price_per_token = u128::from(saleinfo.price_per_token);
+        ensure!(price_per_token > 0, CustomContractError::InvalidCcdInput);
+        let deposit_ccd = u128::from(deposit.micro_ccd);
+        let total_deposit = u128::from(self.total_deposit.micro_ccd);
+        let total_tokens = u128::from(saleinfo.token_per_unit.0)
+            .checked_mul(u128::from(saleinfo.max_units))
+            .ok_or(CustomContractError::OverflowError)?;
+        let hardcap = total_tokens
+            .checked_mul(price_per_token)
+            .ok_or(CustomContractError::OverflowError)?;
+
+        let tokens: u128 = if total_deposit <= hardcap {
+            deposit_ccd / price_per_token
+        } else {
+            deposit_ccd
+                .checked_mul(total_tokens)
+                .ok_or(CustomContractError::OverflowError)?
+                / total_deposit
+        };
+        // tokens * price_per_token never exceeds the deposit
+        let excess = deposit_ccd - tokens * price_per_token;
+
+        Ok((
+            ContractTokenAmount::from(u64::try_from(tokens)?),
+            Amount::from_micro_ccd(u64::try_from(excess)?),
+        ))
+    }
+}
+
+impl<S: HasStateApi> State<S> {
+    pub(crate) fn get_overflow(&self) -> ContractResult<&Overflow> {
+        self.overflow
+            .as_ref()
+            .ok_or(ContractError::from(CustomContractError::NotOverflow))
+    }
+
+    /// Records the deposit of a user. Tokens are allocated when the sale is settled.
+    pub(crate) fn deposit_overflow(
+        &mut self,
+        user: &Address,
+        amount: Amount,
+    ) -> ContractResult<()> {
+        let total_deposit = self
+            .get_overflow()?
+            .total_deposit
+            .micro_ccd
+            .checked_add(amount.micro_ccd)
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+
+        let mut user = self
+            .participants
+            .get_mut(user)
+            .ok_or(ContractError::Unauthorized)?;
+        user.deposit_ccd = amount;
+
+        if let Some(overflow) = self.overflow.as_mut() {
+            overflow.total_deposit = Amount::from_micro_ccd(total_deposit);
+        }
+        Ok(())
+    }
+
+    /// Allocates the tokens to the next `limit` users in proportion to their deposit,
+    /// continuing from where the previous page stopped.
+    /// Once every user has been allocated, the allocation is fixed and `applied_units`
+    /// becomes the number of whole units sold, to be checked against the softcap.
+    /// Returns whether every user has been allocated.
+    pub(crate) fn settle_overflow(&mut self, limit: u32) -> ContractResult<bool> {
+        let overflow = self.get_overflow()?.clone();
+        ensure!(!overflow.settled, CustomContractError::Inappropriate.into());
+
+        let (page, next) = self.participants.walk(overflow.next, limit)?;
+        let mut allocated_tokens = overflow.allocated_tokens.0;
+        let mut refundable = overflow.refundable;
+        for (addr, user) in page {
+            if user.deposit_ccd == Amount::zero() {
+                continue;
+            }
+            let (tokens, excess) = overflow.allocate(&self.saleinfo, user.deposit_ccd)?;
+            if let Some(mut user) = self.participants.get_mut(&addr) {
+                user.alloc_tokens = tokens;
+                user.refundable_ccd = excess;
+            }
+            self.count_claimant(u128::from(tokens.0))?;
+
+            allocated_tokens = allocated_tokens
+                .checked_add(tokens.0)
+                .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+            refundable += excess;
+        }
+
+        let settled = next.is_none();
+        if settled {
+            let sold_units = allocated_tokens / self.saleinfo.token_per_unit.0;
+            self.saleinfo.applied_units = UnitsAmount::try_from(sold_units)
+                .unwrap_or(UnitsAmount::MAX)
+                .min(self.saleinfo.max_units);
+        }
+
+        if let Some(overflow) = self.overflow.as_mut() {
+            overflow.allocated_tokens = ContractTokenAmount::from(allocated_tokens);
+            overflow.refundable = refundable;
+            overflow.settled = settled;
+            if let Some(next) = next {
+                overflow.next = next;
+            }
+        }
+        Ok(settled)
+    }
+
+    /// Clears the excess CCD of a user and returns it.
+    pub(crate) fn refund_excess(&mut self, user: &Address) -> ContractResult<Amount> {
+        ensure!(
+            self.get_overflow()?.settled,
+            CustomContractError::NotSettled.into()
+        );
+
+        let mut user = self
+            .participants
+            .get_mut(user)
+            .ok_or(ContractError::Unauthorized)?;
+        ensure!(!user.refunded, CustomContractError::AlreadyRefunded.into());
+        ensure!(
+            user.deposit_ccd > Amount::zero(),
+            CustomContractError::NotDeposited.into()
+        );
+        ensure!(
+            user.refundable_ccd > Amount::zero(),
+            CustomContractError::AlreadyRefunded.into()
+        );
+        let amount = user.refundable_ccd;
+        user.deposit_ccd -= amount;
+        user.refundable_ccd = Amount::zero();
+
+        if let Some(overflow) = self.overflow.as_mut() {
+            overflow.refundable -= amount;
+        }
+        Ok(amount)
+    }
+
+    /// Vesting amount out of all tokens sold, on which the sale fees are charged.
+    pub(crate) fn calc_vesting_amount_sold(
+        &self,
+        now: Timestamp,
+        vesting_start: Timestamp,
+        shared: BasisPoints,
+        cur_inc: u8,
+        claimed: ContractTokenAmount,
+    ) -> ContractResult<(ContractTokenAmount, u8)> {
+        let total_amount = match &self.overflow {
+            Some(overflow) => u128::from(overflow.allocated_tokens.0),
+            None => {
+                let total_units = cmp::min(self.saleinfo.max_units, self.saleinfo.applied_units);
+                self.tokens_of_units(total_units as u64)?
+            },
+        };
+        self.schedule.calc_claimable_amount(
+            now,
+            vesting_start,
+            total_amount,
+            shared,
+            cur_inc,
+            claimed,
+        )
+    }
+
+    /// Vesting amount out of the tokens purchased by the user.
+    pub(crate) fn calc_vesting_amount_purchased(
+        &self,
+        user: &UserState,
+        now: Timestamp,
+        vesting_start: Timestamp,
+        shared: BasisPoints,
+    ) -> ContractResult<(ContractTokenAmount, u8)> {
+        self.schedule.calc_claimable_amount(
+            now,
+            vesting_start,
+            self.tokens_of(user)?,
+            shared,
+            user.claimed_inc,
+            user.claimed_amount,
+        )
+    }
+
+    /// Project tokens purchased by a user, before the sale fees.
+    pub(crate) fn tokens_of(&self, user: &UserState) -> ContractResult<u128> {
+        if self.overflow.is_some() {
+            Ok(u128::from(user.alloc_tokens.0))
+        } else {
+            self.tokens_of_units(user.win_units as u64)
+        }
+    }
+
+    pub(crate) fn tokens_of_units(&self, units: u64) -> ContractResult<u128> {
+        u128::from(self.saleinfo.token_per_unit.0)
+            .checked_mul(u128::from(units))
+            .ok_or(ContractError::from(CustomContractError::OverflowError))
+    }
+
+    /// Project tokens to be deposited to the pool by the project.
+    pub(crate) fn sold_tokens(&self) -> Result<ContractTokenAmount, CustomContractError> {
+        match &self.overflow {
+            Some(overflow) => Ok(overflow.allocated_tokens),
+            None => self.saleinfo.amount_of_pjtoken(),
+        }
+    }
+}
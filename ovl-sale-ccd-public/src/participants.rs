@@ -0,0 +1,144 @@
+use crate::state::*;
+use concordium_std::*;
+
+/// Position of a participant in the order they joined the sale.
+pub type Position = u32;
+
+/// Sale participants, kept in the order they joined so that they can be walked a page at a time.
+/// A participant keeps their position after quitting,
+/// so that a page can still resume from them.
+#[derive(Debug, Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+pub struct Participants<S: HasStateApi> {
+    /// State of the current participants
+    users: StateMap<Address, UserState, S>,
+    /// Every address which has joined, by position
+    order: StateMap<Position, Address, S>,
+    /// Position of every address which has joined
+    positions: StateMap<Address, Position, S>,
+    /// Number of addresses which have joined
+    len: Position,
+}
+
+impl<S: HasStateApi> Participants<S> {
+    pub(crate) fn new(state_builder: &mut StateBuilder<S>) -> Self {
+        Participants {
+            users: state_builder.new_map(),
+            order: state_builder.new_map(),
+            positions: state_builder.new_map(),
+            len: 0,
+        }
+    }
+
+    pub(crate) fn get(&self, user: &Address) -> Option<StateRef<UserState>> {
+        self.users.get(user)
+    }
+
+    pub(crate) fn get_mut(&mut self, user: &Address) -> Option<StateRefMut<UserState, S>> {
+        self.users.get_mut(user)
+    }
+
+    pub(crate) fn iter(&self) -> StateMapIter<Address, UserState, S> {
+        self.users.iter()
+    }
+
+    pub(crate) fn iter_mut(&mut self) -> StateMapIterMut<Address, UserState, S> {
+        self.users.iter_mut()
+    }
+
+    /// Adds a participant or replaces their state.
+    /// Participants who join again after quitting keep their position.
+    pub(crate) fn insert(&mut self, user: Address, state: UserState) -> Option<UserState> {
+        if self.positions.get(&user).is_none() {
+            self.order.insert(self.len, user);
+            self.positions.insert(user, self.len);
+            self.len += 1;
+        }
+        self.users.insert(user, state)
+    }
+
+    /// State of a participant, who joins with `default` if they have not yet.
+    pub(crate) fn get_or_insert_with(
+        &mut self,
+        user: Address,
+        default: impl FnOnce() -> UserState,
+    ) -> UserState {
+        if let Some(state) = self.users.get(&user) {
+            return state.clone();
+        }
+        let state = default();
+        self.insert(user, state.clone());
+        state
+    }
+
+    pub(crate) fn remove(&mut self, user: &Address) {
+        self.users.remove(user);
+    }
+
+    /// Participants at most `limit` positions from `start`, skipping those who have quit,
+    /// and the position of the next page(`None` if every participant has been walked).
+    pub(crate) fn walk(
+        &self,
+        start: Position,
+        limit: u32,
+    ) -> ContractResult<(Vec<(Address, UserState)>, Option<Position>)> {
+        ensure!(limit > 0, CustomContractError::InvalidPage.into());
+        let end = start.saturating_add(limit).min(self.len);
+
+        let mut page: Vec<(Address, UserState)> = Vec::new();
+        for position in start..end {
+            if let Some(addr) = self.order.get(&position) {
+                if let Some(user) = self.users.get(&addr) {
+                    page.push((*addr, user.clone()));
+                }
+            }
+        }
+        Ok((page, if end < self.len { Some(end) } else { None }))
+    }
+
+    /// Same as `walk`, from the participant at `cursor`(`None` to start from the beginning).
+    /// The cursor of the next page is the participant at its position,
+    /// which stays valid even if they quit in the meantime.
+    pub(crate) fn page(
+        &self,
+        cursor: Option<Address>,
+        limit: u32,
+    ) -> ContractResult<(Vec<(Address, UserState)>, Option<Address>)> {
+        let start = match cursor {
+            Some(cursor) => *self
+                .positions
+                .get(&cursor)
+                .ok_or(ContractError::from(CustomContractError::InvalidPage))?,
+            None => 0,
+        };
+        let (page, next) = self.walk(start, limit)?;
+        let next = next.and_then(|position| self.order.get(&position).map(|addr| *addr));
+        Ok((page, next))
+    }
+}
+
+#[cfg(any(feature = "wasm-test", test))]
+/// implements PartialEq for `claim_eq` inside test functions.
+impl<S: HasStateApi> PartialEq for Participants<S> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.len != other.len {
+            return false;
+        }
+        for position in 0..self.len {
+            let addr = self.order.get(&position).map(|addr| *addr);
+            if addr != other.order.get(&position).map(|addr| *addr) {
+                return false;
+            }
+        }
+        if self.users.iter().count() != other.users.iter().count() {
+            return false;
+        }
+        for (addr, user) in self.users.iter() {
+            match other.users.get(&addr) {
+                Some(other_user) if user.clone() == other_user.clone() => {},
+                _ => return false,
+            }
+        }
+        true
+    }
+}
@@ -0,0 +1,80 @@
+use crate::state::{State, *};
+use concordium_std::*;
+
+/// Entrypoints which can be invoked through `permit`, on behalf of the signer.
+/// `userClaimTo` takes the address receiving the tokens as payload, and the others none.
+pub(crate) const PERMIT_ENTRYPOINTS: [&str; 3] = ["userClaim", "userClaimTo", "userQuit"];
+
+/// Message signed by a participant, to be submitted to `permit` by a relayer
+/// paying the transaction fee(CIS-3).
+#[derive(Debug, Serialize, SchemaType, Clone)]
+pub struct PermitMessage {
+    /// Sale contract the message is signed for
+    pub contract_address: ContractAddress,
+    /// Nonce of the signer, to prevent replays
+    pub nonce: u64,
+    /// The message cannot be used after this time
+    pub timestamp: Timestamp,
+    /// Entrypoint to be invoked on behalf of the signer
+    pub entry_point: OwnedEntrypointName,
+    /// Serialized parameter of the entrypoint
+    #[concordium(size_length = 2)]
+    pub payload: Vec<u8>,
+}
+
+/// The parameter schema for `permit` function.
+#[derive(Debug, Serialize, SchemaType)]
+pub struct PermitParam {
+    /// Signatures of the signer's account keys over the hash of the message
+    pub signature: AccountSignatures,
+    /// Account on whose behalf the entrypoint is invoked
+    pub signer: AccountAddress,
+    pub message: PermitMessage,
+}
+
+/// The parameter schema for `nonceOf` function.
+#[derive(Debug, Serialize, SchemaType)]
+pub struct VecOfAccountAddresses {
+    #[concordium(size_length = 2)]
+    pub queries: Vec<AccountAddress>,
+}
+
+/// The return value of `nonceOf` function, in the order of the queries.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+#[concordium(transparent)]
+pub struct NonceOfQueryResponse(#[concordium(size_length = 2)] pub Vec<u64>);
+
+/// The parameter schema for `supportsPermit` function.
+#[derive(Debug, Serialize, SchemaType)]
+pub struct SupportsPermitQueryParams {
+    #[concordium(size_length = 2)]
+    pub queries: Vec<OwnedEntrypointName>,
+}
+
+/// Hash signed by the wallets: the message prefixed with the signer's address and 8 zero bytes,
+/// so that it cannot be mistaken for a transaction.
+pub(crate) fn message_hash(
+    crypto_primitives: &impl HasCryptoPrimitives,
+    signer: &AccountAddress,
+    message: &PermitMessage,
+) -> [u8; 32] {
+    let mut data = signer.0.to_vec();
+    data.extend_from_slice(&[0u8; 8]);
+    data.extend(to_bytes(message));
+    crypto_primitives.hash_sha2_256(&data).0
+}
+
+impl<S: HasStateApi> State<S> {
+    /// Next nonce to be signed by an account.
+    pub(crate) fn nonce_of(&self, account: &AccountAddress) -> u64 {
+        self.nonces.get(account).map_or(0, |nonce| *nonce)
+    }
+
+    /// Consumes the nonce of a message signed by `signer`.
+    pub(crate) fn use_nonce(&mut self, signer: &AccountAddress, nonce: u64) -> ContractResult<()> {
+        let mut next = self.nonces.entry(*signer).or_insert_with(|| 0);
+        ensure_eq!(*next, nonce, CustomContractError::NonceMismatch.into());
+        *next += 1;
+        Ok(())
+    }
+}
@@ -0,0 +1,199 @@
+use crate::state::{State, *};
+use concordium_cis2::TokenIdVec;
+use concordium_std::*;
+use sale_utils::BPS_DENOMINATOR;
+
+/// The parameter schema for `recoverTokens` function.
+#[derive(Debug, Serialize, SchemaType)]
+pub struct RecoverTokensParams {
+    /// CIS-2 contract of the tokens
+    pub token: ContractAddress,
+    /// Id of the tokens(empty for the project token)
+    pub token_id: TokenIdVec,
+    /// Amount to be withdrawn
+    pub amount: ContractTokenAmount,
+    /// Receiver of the tokens
+    pub to: Address,
+}
+
+/// Id of the project token in the balances.
+pub(crate) fn project_token_id() -> TokenIdVec {
+    TokenIdVec(Vec::new())
+}
+
+impl<S: HasStateApi> State<S> {
+    pub(crate) fn token_balance(&self, token: ContractAddress, token_id: &TokenIdVec) -> u64 {
+        self.token_balances
+            .get(&(token, token_id.clone()))
+            .map_or(0, |balance| balance.0)
+    }
+
+    /// Records CIS-2 tokens received by this contract.
+    pub(crate) fn receive_tokens(
+        &mut self,
+        token: ContractAddress,
+        token_id: TokenIdVec,
+        amount: ContractTokenAmount,
+    ) -> ContractResult<()> {
+        let balance = self
+            .token_balance(token, &token_id)
+            .checked_add(amount.0)
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+        self.token_balances
+            .insert((token, token_id), ContractTokenAmount::from(balance));
+        Ok(())
+    }
+
+    /// Records project tokens paid out of the pool for the vesting claims or as dust.
+    /// Balances are not known for instances migrated from v0, so they never go below zero here.
+    pub(crate) fn record_claimed(
+        &mut self,
+        token: ContractAddress,
+        amount: ContractTokenAmount,
+    ) -> ContractResult<()> {
+        self.claimed_tokens.0 = self
+            .claimed_tokens
+            .0
+            .checked_add(amount.0)
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+
+        if let Some(mut balance) = self.token_balances.get_mut(&(token, project_token_id())) {
+            balance.0 = balance.0.saturating_sub(amount.0);
+        }
+        Ok(())
+    }
+
+    /// Tokens which must stay in the contract for the outstanding user and fee claims.
+    /// Only the project token of a fixed sale is reserved.
+    /// Once the escrow has failed, users are refunded in CCD instead of claiming,
+    /// so only the fees stay reserved and the rest of the pool can be returned to the project.
+    pub(crate) fn reserved_tokens(&self, token: ContractAddress, token_id: &TokenIdVec) -> u64 {
+        if self.status != SaleStatus::Fixed
+            || self.project_token != Some(token)
+            || *token_id != project_token_id()
+        {
+            return 0;
+        }
+        if self.escrow.as_ref().map_or(false, |escrow| escrow.failed) {
+            return self.outstanding_fees().unwrap_or(u64::MAX);
+        }
+        self.sold_tokens().map_or(u64::MAX, |sold| {
+            sold.0.saturating_sub(self.claimed_tokens.0)
+        })
+    }
+
+    /// Fee tokens not yet claimed out of the tokens sold.
+    fn outstanding_fees(&self) -> Result<u64, CustomContractError> {
+        let sold = u128::from(self.sold_tokens()?.0);
+        let mut outstanding: u64 = 0;
+        for fee in self.fees.values() {
+            // bps never exceeds BPS_DENOMINATOR, so the fee fits in u64
+            let total = (sold * u128::from(fee.bps) / u128::from(BPS_DENOMINATOR)) as u64;
+            outstanding = outstanding.saturating_add(total.saturating_sub(fee.claimed_amount.0));
+        }
+        Ok(outstanding)
+    }
+
+    /// Tokens which can be withdrawn by `recoverTokens`.
+    pub(crate) fn recoverable_tokens(&self, token: ContractAddress, token_id: &TokenIdVec) -> u64 {
+        self.token_balance(token, token_id)
+            .saturating_sub(self.reserved_tokens(token, token_id))
+    }
+
+    /// Records the tokens withdrawn by `recoverTokens`.
+    pub(crate) fn recover_tokens(
+        &mut self,
+        token: ContractAddress,
+        token_id: TokenIdVec,
+        amount: ContractTokenAmount,
+    ) -> ContractResult<()> {
+        ensure!(
+            amount.0 <= self.recoverable_tokens(token, &token_id),
+            ContractError::InsufficientFunds
+        );
+        let balance = self.token_balance(token, &token_id) - amount.0;
+        self.token_balances
+            .insert((token, token_id), ContractTokenAmount::from(balance));
+        Ok(())
+    }
+
+    /// Users' part of `tokens` purchased, after the sale fees.
+    fn user_part(&self, tokens: u128) -> ContractResult<u128> {
+        Ok(tokens
+            .checked_mul(u128::from(self.user_share))
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?
+            / u128::from(BPS_DENOMINATOR))
+    }
+
+    /// Counts a user allocated `tokens` in `unclaimed_users`,
+    /// unless nothing is left for them after the sale fees.
+    pub(crate) fn count_claimant(&mut self, tokens: u128) -> ContractResult<()> {
+        if self.user_part(tokens)? > 0 {
+            self.unclaimed_users = self
+                .unclaimed_users
+                .checked_add(1)
+                .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+        }
+        Ok(())
+    }
+
+    /// Uncounts a user from `unclaimed_users` once the `amount` they claim completes their claims.
+    /// `user` is their state before the claim.
+    pub(crate) fn uncount_claimant(
+        &mut self,
+        user: &UserState,
+        amount: ContractTokenAmount,
+    ) -> ContractResult<()> {
+        let claimed = u128::from(user.claimed_amount.0) + u128::from(amount.0);
+        if amount.0 > 0 && claimed >= self.user_part(self.tokens_of(user)?)? {
+            // a miscount must not block the claim, it only keeps `sweepDust` waiting
+            self.unclaimed_users = self.unclaimed_users.saturating_sub(1);
+        }
+        Ok(())
+    }
+
+    /// Whether the user and fee claims have all been paid out in full.
+    /// Users are tracked by `unclaimed_users`, so that the cost does not grow with them.
+    pub(crate) fn is_all_claimed(&self, now: Timestamp) -> ContractResult<bool> {
+        let vesting_start = match self.schedule.vesting_start {
+            Some(v) => v,
+            None => return Ok(false),
+        };
+        match self.schedule.vesting_period.end(vesting_start) {
+            Some(end) if end <= now => {},
+            _ => return Ok(false),
+        }
+        if self.unclaimed_users > 0 {
+            return Ok(false);
+        }
+
+        for fee in self.fees.values() {
+            let (amount, _) =
+                self.calc_vesting_amount_sold(now, vesting_start, fee.bps, 0, fee.claimed_amount)?;
+            if amount.0 > 0 {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Project tokens left in the pool by rounding once every claim has been paid out,
+    /// recorded as paid out so that they are swept only once.
+    pub(crate) fn sweep_dust(&mut self, now: Timestamp) -> ContractResult<ContractTokenAmount> {
+        ensure!(
+            self.status == SaleStatus::Fixed,
+            CustomContractError::SaleNotFixed.into()
+        );
+        let token = self
+            .project_token
+            .ok_or(ContractError::from(CustomContractError::NotSetProjectToken))?;
+        ensure!(
+            self.is_all_claimed(now)?,
+            CustomContractError::ClaimsOutstanding.into()
+        );
+        let dust =
+            ContractTokenAmount::from(self.pool_funded.0.saturating_sub(self.claimed_tokens.0));
+        self.record_claimed(token, dust)?;
+        Ok(dust)
+    }
+}
@@ -0,0 +1,126 @@
+use crate::state::{State, *};
+use concordium_std::*;
+
+/// The parameter schema for `refundBatch` function.
+#[derive(Debug, Serialize, SchemaType)]
+pub struct RefundBatchParams {
+    /// Participant to start the page from(`None` to start from the beginning).
+    /// Pass `next` of the previous page to continue, even if that participant has quit since.
+    pub cursor: Option<Address>,
+    /// Maximum number of participants to walk in this page(at least 1)
+    pub limit: u32,
+}
+
+/// The return value of `refundBatch` function.
+#[derive(Debug, Serialize, SchemaType, PartialEq, Eq)]
+pub struct RefundBatchResponse {
+    /// Number of participants refunded in this page
+    pub refunded: u32,
+    /// Cursor of the next page(`None` if every participant has been walked)
+    pub next: Option<Address>,
+}
+
+/// Pays CCD back to a participant.
+/// Contracts are paid through the entrypoint set by `setRefundHook`, with no parameter.
+pub(crate) fn refund_ccd<S: HasStateApi>(
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    to: Address,
+    amount: Amount,
+) -> ContractResult<()> {
+    let paid = match to {
+        Address::Account(addr) => host.invoke_transfer(&addr, amount).is_ok(),
+        Address::Contract(addr) => {
+            let hook = host
+                .state()
+                .refund_hook
+                .clone()
+                .ok_or(ContractError::from(CustomContractError::MissingRefundHook))?;
+            host.invoke_contract(&addr, &(), hook.as_entrypoint_name(), amount)
+                .is_ok()
+        },
+    };
+    ensure!(paid, CustomContractError::TransferError.into());
+    Ok(())
+}
+
+impl<S: HasStateApi> State<S> {
+    /// Participants still to be refunded in the page starting from `cursor`,
+    /// and the cursor of the next page.
+    pub(crate) fn refund_page(
+        &self,
+        cursor: Option<Address>,
+        limit: u32,
+    ) -> ContractResult<(Vec<(Address, Amount)>, Option<Address>)> {
+        let (page, next) = self.participants.page(cursor, limit)?;
+        let page = page
+            .into_iter()
+            .filter(|(_, user)| !user.refunded && user.deposit_ccd > Amount::zero())
+            .map(|(addr, user)| (addr, user.deposit_ccd))
+            .collect();
+        Ok((page, next))
+    }
+
+    /// Marks the deposit of a user as refunded(or not, if the transfer failed).
+    pub(crate) fn set_refunded(&mut self, user: &Address, refunded: bool) -> ContractResult<()> {
+        let mut user = self
+            .participants
+            .get_mut(user)
+            .ok_or(ContractError::Unauthorized)?;
+        ensure!(
+            !(refunded && user.refunded),
+            CustomContractError::AlreadyRefunded.into()
+        );
+        user.refunded = refunded;
+        Ok(())
+    }
+
+    /// Clears the balances of a user whose whole deposit has been refunded,
+    /// along with the CCD held for them as a lottery loser or as excess,
+    /// so that none of it can be paid out again by `userReclaim` or `userRefundExcess`.
+    pub(crate) fn clear_deposit(&mut self, user: &Address) -> ContractResult<()> {
+        let drawn = self
+            .lottery
+            .as_ref()
+            .map_or(false, |lottery| lottery.is_drawn());
+        let mut user = self
+            .participants
+            .get_mut(user)
+            .ok_or(ContractError::Unauthorized)?;
+        let lost = if drawn && user.win_units == 0 {
+            user.deposit_ccd
+        } else {
+            Amount::zero()
+        };
+        let excess = user.refundable_ccd;
+        user.deposit_ccd = Amount::zero();
+        user.refundable_ccd = Amount::zero();
+        drop(user);
+
+        if let Some(lottery) = self.lottery.as_mut() {
+            lottery.refundable = lottery
+                .refundable
+                .checked_sub(lost)
+                .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+        }
+        if let Some(overflow) = self.overflow.as_mut() {
+            overflow.refundable = overflow
+                .refundable
+                .checked_sub(excess)
+                .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+        }
+        Ok(())
+    }
+
+    /// Number of participants not refunded yet and the CCD they deposited.
+    pub(crate) fn remaining_refunds(&self) -> (u32, Amount) {
+        let mut users: u32 = 0;
+        let mut amount = Amount::zero();
+        for (_, user) in self.participants.iter() {
+            if !user.refunded && user.deposit_ccd > Amount::zero() {
+                users += 1;
+                amount += user.deposit_ccd;
+            }
+        }
+        (users, amount)
+    }
+}
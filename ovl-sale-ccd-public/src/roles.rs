@@ -0,0 +1,82 @@
+use crate::state::{State, *};
+use concordium_std::{collections::BTreeSet, *};
+
+/// Roles which can be granted to addresses to operate the sale.
+/// The contract instance owner implicitly holds every role.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Role {
+    /// Can do everything below and grant/revoke roles
+    Admin,
+    /// Can pause/unpause the contract
+    Pauser,
+    /// Can whitelist users and fix the whitelist
+    WhitelistManager,
+    /// Can claim sale fees for Overlay and BBB
+    FeeClaimer,
+    /// Can set the project token and TGE on behalf of the project admin
+    TgeManager,
+}
+
+/// Parameter type for the contract functions `grantRole` and `revokeRole`.
+#[derive(Debug, Serialize, SchemaType)]
+pub struct RoleParams {
+    /// Address to grant(revoke) the role to(from)
+    pub(crate) address: Address,
+    /// Role to be granted(revoked)
+    pub(crate) role: Role,
+}
+
+impl<S: HasStateApi> State<S> {
+    /// Whether the address holds the role(or Admin, which covers all roles).
+    pub(crate) fn has_role(&self, address: &Address, role: Role) -> bool {
+        match self.roles.get(address) {
+            Some(roles) => roles.contains(&role) || roles.contains(&Role::Admin),
+            None => false,
+        }
+    }
+
+    pub(crate) fn grant_role(&mut self, address: &Address, role: Role) {
+        let mut roles: BTreeSet<Role> = self
+            .roles
+            .get(address)
+            .map(|roles| roles.clone())
+            .unwrap_or_default();
+        roles.insert(role);
+        self.roles.insert(*address, roles);
+    }
+
+    pub(crate) fn revoke_role(&mut self, address: &Address, role: Role) {
+        let roles = self.roles.get(address).map(|roles| roles.clone());
+        if let Some(mut roles) = roles {
+            roles.remove(&role);
+            if roles.is_empty() {
+                self.roles.remove(address);
+            } else {
+                self.roles.insert(*address, roles);
+            }
+        }
+    }
+}
+
+/// Whether the sender is the contract instance owner or holds the role.
+pub(crate) fn sender_has_role<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    state: &State<S>,
+    role: Role,
+) -> bool {
+    let sender = ctx.sender();
+    sender.matches_account(&ctx.owner()) || state.has_role(&sender, role)
+}
+
+/// Reject unless the sender is the contract instance owner or holds the role.
+pub(crate) fn ensure_role<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    state: &State<S>,
+    role: Role,
+) -> ContractResult<()> {
+    ensure!(
+        sender_has_role(ctx, state, role),
+        ContractError::Unauthorized
+    );
+    Ok(())
+}
@@ -2,7 +2,10 @@ use concordium_std::concordium_cfg_test;
 
 #[concordium_cfg_test]
 mod tests {
-    use crate::*;
+    use crate::{participants::Participants, *};
+    use concordium_cis2::{
+        AdditionalData, Receiver, TokenIdUnit, TokenIdVec, Transfer, TransferParams,
+    };
     use concordium_std::test_infrastructure::*;
 
     #[concordium_test]
@@ -22,24 +25,26 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
         let token_per_unit = 200.into();
 
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Prepare,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: None,
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -54,7 +59,25 @@ mod tests {
                 min_units,
                 applied_units: 0,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([invoker]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
 
         // set init context
@@ -65,8 +88,9 @@ mod tests {
         // create params
         let params = InitParams {
             proj_admin,
-            addr_ovl,
-            addr_bbb,
+            user_share: 9_000,
+            fees: BTreeMap::from([(addr_ovl, 500), (addr_bbb, 500)]),
+            ccd_fees: BTreeMap::new(),
             open_at,
             close_at,
             max_units,
@@ -74,6 +98,15 @@ mod tests {
             price_per_token,
             token_per_unit,
             vesting_period,
+            signers: BTreeSet::from([invoker]),
+            threshold: 1,
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            timelock_delay: Duration::from_days(2),
+            lottery_commitment: None,
+            overflow_min_deposit: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
         };
         let params_byte = to_bytes(&params);
         ctx.set_parameter(&params_byte);
@@ -107,11 +140,11 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
@@ -125,8 +158,9 @@ mod tests {
         // create params
         let params = InitParams {
             proj_admin,
-            addr_ovl,
-            addr_bbb,
+            user_share: 9_000,
+            fees: BTreeMap::from([(addr_ovl, 500), (addr_bbb, 500)]),
+            ccd_fees: BTreeMap::new(),
             open_at,
             close_at,
             max_units,
@@ -134,6 +168,15 @@ mod tests {
             price_per_token,
             token_per_unit,
             vesting_period,
+            signers: BTreeSet::from([invoker]),
+            threshold: 1,
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            timelock_delay: Duration::from_days(2),
+            lottery_commitment: None,
+            overflow_min_deposit: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
         };
         let params_byte = to_bytes(&params);
         ctx.set_parameter(&params_byte);
@@ -149,6 +192,61 @@ mod tests {
         );
     }
 
+    #[concordium_test]
+    /// Test that init fails with InvalidTierUnits error.
+    /// Tier T0 is missing, so users out of the whitelist would have no units.
+    fn test_init_fails_with_invalid_tier_units() {
+        let mut state_builder = TestStateBuilder::new();
+        let invoker = AccountAddress([0u8; 32]);
+        let slot_time = Timestamp::from_timestamp_millis(1);
+
+        // set init context
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(invoker);
+        ctx.set_metadata_slot_time(slot_time);
+
+        // create params
+        let params = InitParams {
+            proj_admin: AccountAddress([1u8; 32]),
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), 500),
+                (Address::Account(AccountAddress([3u8; 32])), 500),
+            ]),
+            ccd_fees: BTreeMap::new(),
+            open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+            close_at: Timestamp::from_timestamp_millis(30),
+            max_units: 100,
+            min_units: 50,
+            price_per_token: 5_000_000,
+            token_per_unit: 200.into(),
+            vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                Duration::from_days(1),
+                10_000,
+            )])),
+            signers: BTreeSet::from([invoker]),
+            threshold: 1,
+            tier_units: BTreeMap::from([(Tier::T1, 2), (Tier::T2, 3)]),
+            timelock_delay: Duration::from_days(2),
+            lottery_commitment: None,
+            overflow_min_deposit: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+        };
+        let params_byte = to_bytes(&params);
+        ctx.set_parameter(&params_byte);
+
+        // execute init
+        let result = contract_init(&ctx, &mut state_builder);
+        let err = result.expect_err_report("init should reject");
+        claim_eq!(
+            err,
+            CustomContractError::InvalidTierUnits.into(),
+            "init should reject with InvalidTierUnits"
+        );
+    }
+
     #[concordium_test]
     /// Test that init fails with Inappropriate error.
     /// Total percentage of vesting period != 100.
@@ -167,11 +265,11 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(30), 33),
-            (Duration::from_days(60), 33),
-            (Duration::from_days(90), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(30), 3_300),
+            (Duration::from_days(60), 3_300),
+            (Duration::from_days(90), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
@@ -185,8 +283,9 @@ mod tests {
         // create params
         let params = InitParams {
             proj_admin,
-            addr_ovl,
-            addr_bbb,
+            user_share: 9_000,
+            fees: BTreeMap::from([(addr_ovl, 500), (addr_bbb, 500)]),
+            ccd_fees: BTreeMap::new(),
             open_at,
             close_at,
             max_units,
@@ -194,6 +293,15 @@ mod tests {
             price_per_token,
             token_per_unit,
             vesting_period,
+            signers: BTreeSet::from([invoker]),
+            threshold: 1,
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            timelock_delay: Duration::from_days(2),
+            lottery_commitment: None,
+            overflow_min_deposit: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
         };
         let params_byte = to_bytes(&params);
         ctx.set_parameter(&params_byte);
@@ -227,11 +335,11 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(30), 20),
-            (Duration::from_days(60), 30),
-            (Duration::from_days(90), 50),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(30), 2_000),
+            (Duration::from_days(60), 3_000),
+            (Duration::from_days(90), 5_000),
+        ]));
         let max_units = 0;
         let min_units = 0;
         let price_per_token = 5_000_000;
@@ -245,8 +353,9 @@ mod tests {
         // create params
         let params = InitParams {
             proj_admin,
-            addr_ovl,
-            addr_bbb,
+            user_share: 9_000,
+            fees: BTreeMap::from([(addr_ovl, 500), (addr_bbb, 500)]),
+            ccd_fees: BTreeMap::new(),
             open_at,
             close_at,
             max_units,
@@ -254,6 +363,15 @@ mod tests {
             price_per_token,
             token_per_unit,
             vesting_period,
+            signers: BTreeSet::from([invoker]),
+            threshold: 1,
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            timelock_delay: Duration::from_days(2),
+            lottery_commitment: None,
+            overflow_min_deposit: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
         };
         let params_byte = to_bytes(&params);
         ctx.set_parameter(&params_byte);
@@ -288,11 +406,11 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(30), 20),
-            (Duration::from_days(60), 30),
-            (Duration::from_days(90), 50),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(30), 2_000),
+            (Duration::from_days(60), 3_000),
+            (Duration::from_days(90), 5_000),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 2000_000_000;
@@ -306,8 +424,9 @@ mod tests {
         // create params
         let params = InitParams {
             proj_admin,
-            addr_ovl,
-            addr_bbb,
+            user_share: 9_000,
+            fees: BTreeMap::from([(addr_ovl, 500), (addr_bbb, 500)]),
+            ccd_fees: BTreeMap::new(),
             open_at,
             close_at,
             max_units,
@@ -315,6 +434,15 @@ mod tests {
             price_per_token,
             token_per_unit,
             vesting_period,
+            signers: BTreeSet::from([invoker]),
+            threshold: 1,
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            timelock_delay: Duration::from_days(2),
+            lottery_commitment: None,
+            overflow_min_deposit: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
         };
         let params_byte = to_bytes(&params);
         ctx.set_parameter(&params_byte);
@@ -356,23 +484,25 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
         let token_per_unit = 200.into();
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Prepare,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -387,16 +517,36 @@ mod tests {
                 min_units,
                 applied_units: 0,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let after_paused_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Prepare,
             paused: true,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -411,16 +561,36 @@ mod tests {
                 min_units,
                 applied_units: 0,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let after_unpaused_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Prepare,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -435,7 +605,25 @@ mod tests {
                 min_units,
                 applied_units: 0,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
 
@@ -443,7 +631,8 @@ mod tests {
         let mut ctx = TestReceiveContext::empty();
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(admin));
-        let set_paused_result = contract_set_paused(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let set_paused_result = contract_set_paused(&ctx, &mut host, &mut logger);
         claim!(set_paused_result.is_ok());
         claim_eq!(
             *host.state(),
@@ -456,7 +645,8 @@ mod tests {
         let mut ctx = TestReceiveContext::empty();
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(admin));
-        let result = contract_create_pool(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_create_pool(&ctx, &mut host, &mut logger);
         let err = result.expect_err_report("createPool should reject when paused");
         claim_eq!(
             err,
@@ -469,7 +659,8 @@ mod tests {
         ctx.set_owner(admin);
         ctx.set_invoker(proj_admin);
         ctx.set_sender(Address::Contract(project_token_address));
-        let result = contract_project_claim(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_project_claim(&ctx, &mut host, &mut logger);
         let err = result.expect_err_report("projectClaim should reject when paused");
         claim_eq!(
             err,
@@ -481,7 +672,15 @@ mod tests {
         let mut ctx = TestReceiveContext::empty();
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(some_user));
-        let result = contract_user_deposit(&ctx, &mut host, Amount::from_ccd(100));
+        let crypto_primitives = TestCryptoPrimitives::new();
+        let mut logger = TestLogger::init();
+        let result = contract_user_deposit(
+            &ctx,
+            &mut host,
+            Amount::from_ccd(100),
+            &mut logger,
+            &crypto_primitives,
+        );
         let err = result.expect_err_report("userDeposit should reject when paused");
         claim_eq!(
             err,
@@ -493,7 +692,8 @@ mod tests {
         let mut ctx = TestReceiveContext::empty();
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(some_user));
-        let result = contract_user_quit(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_user_quit(&ctx, &mut host, &mut logger);
         let err = result.expect_err_report("userQuit should reject when paused");
         claim_eq!(
             err,
@@ -505,7 +705,8 @@ mod tests {
         let mut ctx = TestReceiveContext::empty();
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(some_user));
-        let result = contract_user_claim(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_user_claim(&ctx, &mut host, &mut logger);
         let err = result.expect_err_report("userClaim should reject when paused");
         claim_eq!(
             err,
@@ -517,7 +718,8 @@ mod tests {
         let mut ctx = TestReceiveContext::empty();
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(admin));
-        let set_paused_result = contract_set_unpaused(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let set_paused_result = contract_set_unpaused(&ctx, &mut host, &mut logger);
         claim!(set_paused_result.is_ok());
         claim_eq!(
             *host.state(),
@@ -547,23 +749,25 @@ mod tests {
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
         let slot_time = Timestamp::from_timestamp_millis(31);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
         let token_per_unit = 200.into();
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -578,16 +782,36 @@ mod tests {
                 min_units,
                 applied_units: min_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Fixed,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -602,7 +826,25 @@ mod tests {
                 min_units,
                 applied_units: min_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
 
@@ -611,7 +853,8 @@ mod tests {
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(admin));
         ctx.set_metadata_slot_time(slot_time);
-        let result = contract_set_fixed(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_set_fixed(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(
             *host.state(),
@@ -641,23 +884,25 @@ mod tests {
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
         let slot_time = Timestamp::from_timestamp_millis(31);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
         let token_per_unit = 200.into();
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -672,16 +917,36 @@ mod tests {
                 min_units,
                 applied_units: min_units - 1,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Suspend,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -696,7 +961,25 @@ mod tests {
                 min_units,
                 applied_units: min_units - 1,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
 
@@ -705,7 +988,8 @@ mod tests {
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(admin));
         ctx.set_metadata_slot_time(slot_time);
-        let result = contract_set_fixed(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_set_fixed(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(
             *host.state(),
@@ -734,11 +1018,11 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
@@ -748,33 +1032,40 @@ mod tests {
             AllowedUserParams {
                 user: Address::Account(AccountAddress([10u8; 32])),
                 prior: Prior::TOP,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(AccountAddress([11u8; 32])),
                 prior: Prior::TOP,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(AccountAddress([12u8; 32])),
                 prior: Prior::SECOND,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(AccountAddress([13u8; 32])),
                 prior: Prior::SECOND,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(AccountAddress([14u8; 32])),
                 prior: Prior::ANY,
+                tier: Tier::T0,
             },
         ];
 
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Prepare,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -789,9 +1080,27 @@ mod tests {
                 min_units,
                 applied_units: 0,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
-        let mut expected_participants = state_builder.new_map();
+        let mut expected_participants = Participants::new(&mut state_builder);
         for params in &whitelist {
             expected_participants.insert(
                 params.user,
@@ -799,13 +1108,15 @@ mod tests {
             );
         }
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -821,6 +1132,24 @@ mod tests {
                 applied_units: 0,
             },
             participants: expected_participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
 
@@ -837,7 +1166,8 @@ mod tests {
         ctx.set_parameter(&params_byte);
 
         // execute function
-        let result = contract_whitelisting(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_whitelisting(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(
             *host.state(),
@@ -866,24 +1196,26 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
         let token_per_unit = 200.into();
 
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Prepare,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -898,29 +1230,52 @@ mod tests {
                 min_units,
                 applied_units: 0,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
 
         let whitelist1 = vec![
             AllowedUserParams {
                 user: Address::Account(AccountAddress([10u8; 32])),
                 prior: Prior::TOP,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(AccountAddress([11u8; 32])),
                 prior: Prior::TOP,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(AccountAddress([12u8; 32])),
                 prior: Prior::SECOND,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(AccountAddress([13u8; 32])),
                 prior: Prior::SECOND,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(AccountAddress([14u8; 32])),
                 prior: Prior::ANY,
+                tier: Tier::T0,
             },
         ];
 
@@ -928,18 +1283,21 @@ mod tests {
             AllowedUserParams {
                 user: Address::Account(AccountAddress([15u8; 32])),
                 prior: Prior::TOP,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(AccountAddress([16u8; 32])),
                 prior: Prior::TOP,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(AccountAddress([10u8; 32])),
                 prior: Prior::SECOND,
+                tier: Tier::T0,
             },
         ];
 
-        let mut expected_participants_first = state_builder.new_map();
+        let mut expected_participants_first = Participants::new(&mut state_builder);
         for params in &whitelist1 {
             expected_participants_first.insert(
                 params.user,
@@ -947,7 +1305,7 @@ mod tests {
             );
         }
 
-        let mut expected_participants_second = state_builder.new_map();
+        let mut expected_participants_second = Participants::new(&mut state_builder);
         for params in &whitelist1 {
             expected_participants_second.insert(
                 params.user,
@@ -955,7 +1313,7 @@ mod tests {
             );
         }
         for params in &whitelist2 {
-            if expected_participants_second.entry(params.user).is_vacant() {
+            if expected_participants_second.get(&params.user).is_none() {
                 expected_participants_second.insert(
                     params.user,
                     UserState::new(params.prior.clone(), Amount::zero(), TARGET_UNITS),
@@ -964,13 +1322,15 @@ mod tests {
         }
 
         let expected_state_first = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Prepare,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -986,16 +1346,36 @@ mod tests {
                 applied_units: 0,
             },
             participants: expected_participants_first,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
 
         let expected_state_second = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -1011,6 +1391,24 @@ mod tests {
                 applied_units: 0,
             },
             participants: expected_participants_second,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
 
         let mut host = TestHost::new(initial_state, state_builder);
@@ -1029,7 +1427,8 @@ mod tests {
         ctx.set_parameter(&params_byte);
 
         // execute
-        let result = contract_whitelisting(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_whitelisting(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(
             *host.state(),
@@ -1046,7 +1445,8 @@ mod tests {
         ctx.set_parameter(&params_byte);
 
         // execute function
-        let result = contract_whitelisting(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_whitelisting(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(
             *host.state(),
@@ -1061,9 +1461,8 @@ mod tests {
     }
 
     #[concordium_test]
-    /// Test that whitelisting fails with AccountOnly error.
-    /// Currently contract address input is not supported.
-    fn test_whitelisted_fails_with_account_only() {
+    /// Test that whitelisting accepts contract addresses.
+    fn test_whitelisted_with_contract() {
         let mut state_builder = TestStateBuilder::new();
         let admin = AccountAddress([0u8; 32]);
         let proj_admin = AccountAddress([1u8; 32]);
@@ -1081,11 +1480,11 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
@@ -1095,33 +1494,40 @@ mod tests {
             AllowedUserParams {
                 user: Address::Account(AccountAddress([10u8; 32])),
                 prior: Prior::TOP,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(AccountAddress([11u8; 32])),
                 prior: Prior::TOP,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(AccountAddress([12u8; 32])),
                 prior: Prior::SECOND,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Contract(ContractAddress::new(123, 0)),
                 prior: Prior::SECOND,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(AccountAddress([14u8; 32])),
                 prior: Prior::ANY,
+                tier: Tier::T0,
             },
         ];
 
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Prepare,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -1136,7 +1542,25 @@ mod tests {
                 min_units,
                 applied_units: 0,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
 
@@ -1152,12 +1576,23 @@ mod tests {
         ctx.set_parameter(&params_byte);
 
         // execute function
-        let result = contract_whitelisting(&ctx, &mut host);
-        let err = result.expect_err_report("should fail");
+        let mut logger = TestLogger::init();
+        let result = contract_whitelisting(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+
+        let user = Address::Contract(ContractAddress::new(123, 0));
+        let user_state = host.state().participants.get(&user);
+        claim!(user_state.is_some(), "contract should be whitelisted");
+        claim_eq!(user_state.unwrap().prior, Prior::SECOND);
+        claim_eq!(host.state().status, SaleStatus::Ready);
         claim_eq!(
-            err,
-            CustomContractError::AccountOnly.into(),
-            "whitelisting should reject with AccountOnly"
+            logger.logs[3],
+            to_bytes(&SaleEvent::Whitelisted(WhitelistedEvent {
+                user,
+                prior: Prior::SECOND,
+                tier: Tier::T0,
+                tgt_units: TARGET_UNITS,
+            }))
         );
     }
 
@@ -1181,24 +1616,26 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
         let token_per_unit = 200.into();
 
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Prepare,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -1213,7 +1650,25 @@ mod tests {
                 min_units,
                 applied_units: 0,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
 
@@ -1229,6 +1684,7 @@ mod tests {
                     },
                     None => Prior::ANY,
                 },
+                tier: Tier::T0,
             })
             .collect();
 
@@ -1244,12 +1700,13 @@ mod tests {
         ctx.set_parameter(&params_byte);
 
         // execute func
-        let ret = contract_whitelisting(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let ret = contract_whitelisting(&ctx, &mut host, &mut logger);
         ret.is_ok()
     }
 
     #[concordium_test]
-    /// Test that ovlClaim successfully calculate total amount to claim & transfer it.
+    /// Test that feeClaim of OVL successfully calculate total amount to claim & transfer it.
     /// Only calculate for first vesting period
     fn test_ovl_claim() {
         let mut state_builder = TestStateBuilder::new();
@@ -1273,11 +1730,11 @@ mod tests {
         let close_at = Timestamp::from_timestamp_millis(30);
         let vesting_start = Timestamp::from_timestamp_millis(50);
         let slot_time = Timestamp::from_timestamp_millis(60);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_millis(10), 25),
-            (Duration::from_millis(11), 40),
-            (Duration::from_millis(12), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_millis(10), 2_500),
+            (Duration::from_millis(11), 4_000),
+            (Duration::from_millis(12), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let applied_units = 80;
@@ -1288,13 +1745,15 @@ mod tests {
         let token_per_unit = 200.into();
 
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Fixed,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -1309,16 +1768,44 @@ mod tests {
                 min_units,
                 applied_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(200 * 80),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Fixed,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 1,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (
+                    addr_ovl,
+                    FeeState {
+                        bps: 500,
+                        ccd_bps: 0,
+                        claimed_inc: 1,
+                        claimed_amount: ContractTokenAmount::from(200),
+                    },
+                ),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -1333,7 +1820,25 @@ mod tests {
                 min_units,
                 applied_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(200 * 80),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(200),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
         host.setup_mock_entrypoint(
@@ -1356,14 +1861,17 @@ mod tests {
         );
 
         // create params
+        let params_bytes = to_bytes(&addr_ovl);
         let mut ctx = TestReceiveContext::empty();
         ctx.set_self_address(self_address);
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(admin));
         ctx.set_metadata_slot_time(slot_time);
+        ctx.set_parameter(&params_bytes);
 
         // execute function
-        let result = contract_ovl_claim(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_fee_claim(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(
             *host.state(),
@@ -1373,7 +1881,7 @@ mod tests {
     }
 
     #[concordium_test]
-    /// Test that ovlClaim successfully calculate total amount to claim & transfer it.
+    /// Test that feeClaim of OVL successfully calculate total amount to claim & transfer it.
     /// Calculate for 1st & 2nd vesting period
     fn test_ovl_claim2() {
         let mut state_builder = TestStateBuilder::new();
@@ -1397,11 +1905,11 @@ mod tests {
         let close_at = Timestamp::from_timestamp_millis(30);
         let vesting_start = Timestamp::from_timestamp_millis(50);
         let slot_time = Timestamp::from_timestamp_millis(70);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_millis(10), 25),
-            (Duration::from_millis(20), 40),
-            (Duration::from_millis(30), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_millis(10), 2_500),
+            (Duration::from_millis(20), 4_000),
+            (Duration::from_millis(30), 3_500),
+        ]));
         let max_units = 1000;
         let min_units = 500;
         let applied_units = 800;
@@ -1411,13 +1919,15 @@ mod tests {
         let token_per_unit = 200.into();
 
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Fixed,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -1432,16 +1942,44 @@ mod tests {
                 min_units,
                 applied_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(200 * 800),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Fixed,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 2,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (
+                    addr_ovl,
+                    FeeState {
+                        bps: 500,
+                        ccd_bps: 0,
+                        claimed_inc: 2,
+                        claimed_amount: ContractTokenAmount::from(5200),
+                    },
+                ),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -1456,7 +1994,25 @@ mod tests {
                 min_units,
                 applied_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(200 * 800),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(5200),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
         host.setup_mock_entrypoint(
@@ -1479,14 +2035,17 @@ mod tests {
         );
 
         // create params
+        let params_bytes = to_bytes(&addr_ovl);
         let mut ctx = TestReceiveContext::empty();
         ctx.set_self_address(self_address);
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(admin));
         ctx.set_metadata_slot_time(slot_time);
+        ctx.set_parameter(&params_bytes);
 
         // execute function
-        let result = contract_ovl_claim(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_fee_claim(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(
             *host.state(),
@@ -1496,7 +2055,7 @@ mod tests {
     }
 
     #[concordium_test]
-    /// Test that bbbClaim successfully calculate total amount to claim & transfer it.
+    /// Test that feeClaim of BBB successfully calculate total amount to claim & transfer it.
     /// Calculate for 1st/2nd/3rd vesting period
     fn test_bbb_claim3() {
         let mut state_builder = TestStateBuilder::new();
@@ -1520,11 +2079,11 @@ mod tests {
         let close_at = Timestamp::from_timestamp_millis(30);
         let vesting_start = Timestamp::from_timestamp_millis(50);
         let slot_time = Timestamp::from_timestamp_millis(80);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_millis(10), 25),
-            (Duration::from_millis(20), 40),
-            (Duration::from_millis(30), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_millis(10), 2_500),
+            (Duration::from_millis(20), 4_000),
+            (Duration::from_millis(30), 3_500),
+        ]));
         let max_units = 1000;
         let min_units = 500;
         let applied_units = 800;
@@ -1534,13 +2093,15 @@ mod tests {
         let token_per_unit = 200.into();
 
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Fixed,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -1555,16 +2116,44 @@ mod tests {
                 min_units,
                 applied_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(200 * 800),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Fixed,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 3,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (
+                    addr_bbb,
+                    FeeState {
+                        bps: 500,
+                        ccd_bps: 0,
+                        claimed_inc: 3,
+                        claimed_amount: ContractTokenAmount::from(8000),
+                    },
+                ),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -1579,7 +2168,25 @@ mod tests {
                 min_units,
                 applied_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(200 * 800),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(8000),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
         host.setup_mock_entrypoint(
@@ -1605,14 +2212,17 @@ mod tests {
         );
 
         // create params
+        let params_bytes = to_bytes(&addr_bbb);
         let mut ctx = TestReceiveContext::empty();
         ctx.set_self_address(self_address);
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(admin));
         ctx.set_metadata_slot_time(slot_time);
+        ctx.set_parameter(&params_bytes);
 
         // execute function
-        let result = contract_bbb_claim(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_fee_claim(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(
             *host.state(),
@@ -1622,10 +2232,12 @@ mod tests {
     }
 
     #[concordium_test]
-    /// Test that changeTGE successfully update schedule.vesting_start
+    /// Test that a ChangeTge proposal updates schedule.vesting_start
+    /// only after enough signers have approved it and the timelock delay has passed.
     fn test_change_tge() {
         let mut state_builder = TestStateBuilder::new();
         let admin = AccountAddress([0u8; 32]);
+        let second_signer = AccountAddress([3u8; 32]);
         let proj_admin = AccountAddress([1u8; 32]);
         let project_token_address = ContractAddress {
             index: 1000,
@@ -1642,24 +2254,26 @@ mod tests {
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
         let slot_time = Timestamp::from_timestamp_millis(31);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let vesting_start_to_be_set = Timestamp::from_timestamp_millis(100);
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
         let token_per_unit = 200.into();
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -1674,16 +2288,41 @@ mod tests {
                 min_units,
                 applied_units: min_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(
+                &mut state_builder,
+                BTreeSet::from([admin, second_signer]),
+                2,
+            )
+            .unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -1698,30 +2337,154 @@ mod tests {
                 min_units,
                 applied_units: min_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig {
+                signers: BTreeSet::from([admin, second_signer]),
+                threshold: 2,
+                proposals: state_builder.new_map(),
+                next_proposal_id: 1,
+            },
+            roles: state_builder.new_map(),
+            timelock: Timelock {
+                delay: Duration::from_days(2),
+                pending: state_builder.new_map(),
+                next_change_id: 1,
+            },
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
 
-        // create params
+        // propose
+        let action = AdminAction::ChangeTge(vesting_start_to_be_set);
         let mut ctx = TestReceiveContext::empty();
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(admin));
         ctx.set_metadata_slot_time(slot_time);
-        let params_byes = to_bytes(&vesting_start_to_be_set);
+        let params_byes = to_bytes(&action);
         ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_propose(&ctx, &mut host, &mut logger);
+        claim_eq!(result, Ok(0), "the first proposal should have id 0");
 
-        // execute func
-        let result = contract_change_tge(&ctx, &mut host);
+        // execute fails with only one approval
+        let proposal_id: ProposalId = 0;
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(slot_time);
+        let params_byes = to_bytes(&proposal_id);
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_execute(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("execute should reject without enough approvals");
+        claim_eq!(
+            err,
+            CustomContractError::NotEnoughApprovals.into(),
+            "execute should reject without enough approvals"
+        );
+
+        // approve by the second signer
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(second_signer));
+        ctx.set_metadata_slot_time(slot_time);
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_approve(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+
+        // execute queues the change
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(slot_time);
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_execute(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim_eq!(
+            host.state().schedule.vesting_start,
+            None,
+            "TGE should not be changed until the delay has passed"
+        );
+        let effective_at = slot_time.checked_add(Duration::from_days(2)).unwrap();
+        claim_eq!(
+            logger.logs,
+            vec![
+                to_bytes(&SaleEvent::ChangeQueued(ChangeQueuedEvent {
+                    id: 0,
+                    action: action.clone(),
+                    effective_at,
+                })),
+                to_bytes(&SaleEvent::ProposalExecuted(ProposalExecutedEvent {
+                    id: proposal_id,
+                    action: action.clone(),
+                })),
+            ],
+            "ChangeQueued and ProposalExecuted events should be logged."
+        );
+
+        // apply fails during the delay
+        let change_id: ChangeId = 0;
+        let params_byes = to_bytes(&change_id);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(slot_time);
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_apply_change(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("applyChange should reject during the delay");
+        claim_eq!(
+            err,
+            CustomContractError::NotEffectiveYet.into(),
+            "applyChange should reject during the delay"
+        );
+
+        // apply after the delay
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(effective_at);
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_apply_change(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(
             *host.state(),
             expected_state,
             "state has been changed unexpectedly..."
         );
+        claim_eq!(
+            logger.logs,
+            vec![
+                to_bytes(&SaleEvent::TgeSet(vesting_start_to_be_set)),
+                to_bytes(&SaleEvent::ChangeExecuted(ChangeExecutedEvent {
+                    id: change_id,
+                    action,
+                })),
+            ],
+            "TgeSet and ChangeExecuted events should be logged."
+        );
     }
 
     #[concordium_test]
-    /// Test that changePjtoken successfully update project_token.
+    /// Test that a ChangePjtoken proposal updates project_token after the delay
+    /// and that only signers can propose.
     fn test_change_pjtoken() {
         let mut state_builder = TestStateBuilder::new();
         let admin = AccountAddress([0u8; 32]);
@@ -1745,23 +2508,25 @@ mod tests {
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
         let slot_time = Timestamp::from_timestamp_millis(31);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
         let token_per_unit = 200.into();
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -1776,16 +2541,36 @@ mod tests {
                 min_units,
                 applied_units: min_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address_to_be_set),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -1800,20 +2585,86 @@ mod tests {
                 min_units,
                 applied_units: min_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig {
+                signers: BTreeSet::from([admin]),
+                threshold: 1,
+                proposals: state_builder.new_map(),
+                next_proposal_id: 1,
+            },
+            roles: state_builder.new_map(),
+            timelock: Timelock {
+                delay: Duration::from_days(2),
+                pending: state_builder.new_map(),
+                next_change_id: 1,
+            },
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
 
-        // create params
+        // project admin is not a signer
+        let action = AdminAction::ChangePjtoken(project_token_address_to_be_set);
+        let params_byes = to_bytes(&action);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(proj_admin));
+        ctx.set_metadata_slot_time(slot_time);
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_propose(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("propose should reject non-signers");
+        claim_eq!(
+            err,
+            ContractError::Unauthorized,
+            "propose should reject non-signers"
+        );
+
+        // propose
         let mut ctx = TestReceiveContext::empty();
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(admin));
         ctx.set_metadata_slot_time(slot_time);
-        let params_byes = to_bytes(&project_token_address_to_be_set);
         ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_propose(&ctx, &mut host, &mut logger);
+        claim_eq!(result, Ok(0), "the first proposal should have id 0");
 
-        // execute func
-        let result = contract_change_pjtoken(&ctx, &mut host);
+        // execute
+        let proposal_id: ProposalId = 0;
+        let params_byes = to_bytes(&proposal_id);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(slot_time);
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_execute(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+
+        // apply after the delay
+        let change_id: ChangeId = 0;
+        let params_byes = to_bytes(&change_id);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(slot_time.checked_add(Duration::from_days(2)).unwrap());
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_apply_change(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(
             *host.state(),
@@ -1821,4 +2672,886 @@ mod tests {
             "state has been changed unexpectedly..."
         );
     }
+
+    #[concordium_test]
+    /// Test that a queued change can be cancelled only during the delay.
+    fn test_cancel_change() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
+        let addr_bbb = Address::Contract(ContractAddress {
+            index: 100,
+            subindex: 0,
+        });
+        let open_at = BTreeMap::from([
+            (Timestamp::from_timestamp_millis(10), Prior::TOP),
+            (Timestamp::from_timestamp_millis(20), Prior::SECOND),
+        ]);
+        let close_at = Timestamp::from_timestamp_millis(30);
+        let slot_time = Timestamp::from_timestamp_millis(31);
+        let effective_at = slot_time.checked_add(Duration::from_days(2)).unwrap();
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
+        let mut pending = state_builder.new_map();
+        pending.insert(
+            0,
+            PendingChange {
+                action: AdminAction::ChangeTge(Timestamp::from_timestamp_millis(100)),
+                effective_at,
+            },
+        );
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin,
+            status: SaleStatus::Ready,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at,
+                close_at,
+                vesting_start: None,
+                vesting_period,
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 50,
+                applied_units: 50,
+            },
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock {
+                delay: Duration::from_days(2),
+                pending,
+                next_change_id: 1,
+            },
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+
+        let change_id: ChangeId = 0;
+        let params_byes = to_bytes(&change_id);
+
+        // project admin is not a signer
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(proj_admin));
+        ctx.set_metadata_slot_time(slot_time);
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_cancel_change(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("cancelChange should reject non-signers");
+        claim_eq!(
+            err,
+            ContractError::Unauthorized,
+            "cancelChange should reject non-signers"
+        );
+
+        // too late to cancel
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(effective_at);
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_cancel_change(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("cancelChange should reject effective changes");
+        claim_eq!(
+            err,
+            CustomContractError::AlreadyEffective.into(),
+            "cancelChange should reject effective changes"
+        );
+
+        // cancel during the delay
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(slot_time);
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_cancel_change(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim_eq!(
+            host.state().timelock.pending.iter().count(),
+            0,
+            "the change should be removed from the queue"
+        );
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::ChangeCancelled(change_id))],
+            "ChangeCancelled event should be logged."
+        );
+
+        // cancelled change cannot be applied
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(effective_at);
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_apply_change(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("applyChange should reject cancelled changes");
+        claim_eq!(
+            err,
+            CustomContractError::UnknownChange.into(),
+            "applyChange should reject cancelled changes"
+        );
+        claim_eq!(host.state().schedule.vesting_start, None);
+    }
+
+    #[concordium_test]
+    /// Test that a granted role only allows the entrypoints gated on it
+    /// and that it can be revoked.
+    fn test_roles() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let pauser = AccountAddress([3u8; 32]);
+        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
+        let addr_bbb = Address::Contract(ContractAddress {
+            index: 100,
+            subindex: 0,
+        });
+        let open_at = BTreeMap::from([
+            (Timestamp::from_timestamp_millis(10), Prior::TOP),
+            (Timestamp::from_timestamp_millis(20), Prior::SECOND),
+        ]);
+        let close_at = Timestamp::from_timestamp_millis(30);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin,
+            status: SaleStatus::Prepare,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at,
+                close_at,
+                vesting_start: None,
+                vesting_period,
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 50,
+                applied_units: 0,
+            },
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+
+        // grant Pauser
+        let params = RoleParams {
+            address: Address::Account(pauser),
+            role: Role::Pauser,
+        };
+        let params_bytes = to_bytes(&params);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_parameter(&params_bytes);
+        let mut logger = TestLogger::init();
+        let result = contract_grant_role(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim!(host.state().has_role(&Address::Account(pauser), Role::Pauser));
+        claim!(!host.state().has_role(&Address::Account(pauser), Role::WhitelistManager));
+
+        // pauser can pause
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(pauser));
+        let mut logger = TestLogger::init();
+        let result = contract_set_paused(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim!(host.state().paused, "pauser should be able to pause");
+
+        // but cannot whitelist
+        let whitelist_params = WhitelistingParams {
+            wl: vec![],
+            ready: true,
+        };
+        let whitelist_bytes = to_bytes(&whitelist_params);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(pauser));
+        ctx.set_parameter(&whitelist_bytes);
+        let mut logger = TestLogger::init();
+        let result = contract_whitelisting(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("pauser should not be able to whitelist");
+        claim_eq!(
+            err,
+            ContractError::Unauthorized,
+            "pauser should not be able to whitelist"
+        );
+
+        // nor grant roles
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(pauser));
+        ctx.set_parameter(&params_bytes);
+        let mut logger = TestLogger::init();
+        let result = contract_grant_role(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("pauser should not be able to grant roles");
+        claim_eq!(
+            err,
+            ContractError::Unauthorized,
+            "pauser should not be able to grant roles"
+        );
+
+        // revoke Pauser
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_parameter(&params_bytes);
+        let mut logger = TestLogger::init();
+        let result = contract_revoke_role(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim_eq!(host.state().roles.iter().count(), 0, "no roles should be left");
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(pauser));
+        let mut logger = TestLogger::init();
+        let result = contract_set_unpaused(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("revoked pauser should not be able to unpause");
+        claim_eq!(
+            err,
+            ContractError::Unauthorized,
+            "revoked pauser should not be able to unpause"
+        );
+    }
+
+    #[concordium_test]
+    /// Test that drawLottery checks the seed against the commitment
+    /// and fixes units for winners up to the hardcap.
+    fn test_draw_lottery() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let unit_price = Amount::from_micro_ccd(5_000_000 * 200);
+        let users = [
+            Address::Account(AccountAddress([10u8; 32])),
+            Address::Account(AccountAddress([11u8; 32])),
+            Address::Account(AccountAddress([12u8; 32])),
+        ];
+        let seed: LotterySeed = [7u8; 32];
+
+        let crypto_primitives = TestCryptoPrimitives::new();
+        #[cfg(not(feature = "crypto-primitives"))]
+        crypto_primitives.setup_hash_sha2_256_mock(|data| {
+            // stands in for sha256, ranking the users by the last byte of their address
+            HashSha2256([255 - data[data.len() - 1]; 32])
+        });
+        let commitment = crypto_primitives.hash_sha2_256(&seed);
+
+        let mut participants = Participants::new(&mut state_builder);
+        for user in users.iter() {
+            participants.insert(*user, UserState::new(Prior::TOP, unit_price, TARGET_UNITS));
+        }
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin: AccountAddress([1u8; 32]),
+            status: SaleStatus::Ready,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 2,
+                min_units: 1,
+                applied_units: 0,
+            },
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            lottery: Some(Lottery {
+                entries: 3,
+                ..Lottery::new(commitment)
+            }),
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(31));
+
+        // the seed must match the commitment
+        let wrong_seed: LotterySeed = [8u8; 32];
+        let wrong_seed_bytes = to_bytes(&wrong_seed);
+        ctx.set_parameter(&wrong_seed_bytes);
+        let mut logger = TestLogger::init();
+        let result = contract_draw_lottery(&ctx, &mut host, &mut logger, &crypto_primitives);
+        let err = result.expect_err_report("drawLottery should reject");
+        claim_eq!(
+            err,
+            CustomContractError::InvalidSeed.into(),
+            "drawLottery should reject with InvalidSeed"
+        );
+
+        let seed_bytes = to_bytes(&seed);
+        ctx.set_parameter(&seed_bytes);
+        let mut logger = TestLogger::init();
+        let result = contract_draw_lottery(&ctx, &mut host, &mut logger, &crypto_primitives);
+        claim!(result.is_ok(), "Results in rejection");
+
+        let state = host.state();
+        claim_eq!(state.saleinfo.applied_units, 2);
+        claim_eq!(
+            state.lottery,
+            Some(Lottery {
+                commitment,
+                seed: Some(seed),
+                refundable: unit_price,
+                entries: 3,
+            })
+        );
+        let winners = users
+            .iter()
+            .filter(|user| state.participants.get(*user).unwrap().win_units == TARGET_UNITS)
+            .count();
+        claim_eq!(winners, 2, "winners should be drawn up to the hardcap");
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::LotteryDrawn(LotteryDrawnEvent {
+                seed,
+                winners: 2,
+                applied_units: 2,
+            }))],
+            "LotteryDrawn event should be logged."
+        );
+
+        // the draw happens only once
+        let mut logger = TestLogger::init();
+        let result = contract_draw_lottery(&ctx, &mut host, &mut logger, &crypto_primitives);
+        let err = result.expect_err_report("drawLottery should reject");
+        claim_eq!(
+            err,
+            CustomContractError::AlreadyDrawn.into(),
+            "drawLottery should reject with AlreadyDrawn"
+        );
+    }
+
+    #[concordium_test]
+    /// Test that recoverTokens withdraws only the tokens not reserved for the claims.
+    fn test_recover_tokens() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let self_address = ContractAddress {
+            index: 10,
+            subindex: 0,
+        };
+        let project_token_address = ContractAddress {
+            index: 1000,
+            subindex: 0,
+        };
+        let other_token_address = ContractAddress {
+            index: 2000,
+            subindex: 0,
+        };
+        let other_token_id = TokenIdVec(vec![1]);
+
+        // 10000 tokens sold, 2000 claimed and 500 sent in surplus
+        let mut token_balances = state_builder.new_map();
+        token_balances.insert(
+            (project_token_address, TokenIdVec(Vec::new())),
+            ContractTokenAmount::from(8_500),
+        );
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin: AccountAddress([1u8; 32]),
+            status: SaleStatus::Fixed,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: Some(project_token_address),
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: Some(Timestamp::from_timestamp_millis(50)),
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 50,
+                applied_units: 50,
+            },
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(10_000),
+            pool_depositor: None,
+            token_balances,
+            claimed_tokens: ContractTokenAmount::from(2_000),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        for token in [project_token_address, other_token_address] {
+            host.setup_mock_entrypoint(
+                token,
+                OwnedEntrypointName::new_unchecked("transfer".into()),
+                MockFn::returning_ok(()),
+            );
+        }
+
+        // tokens sent by mistake
+        let params = OnReceivingCis2Params {
+            token_id: other_token_id.clone(),
+            amount: ContractTokenAmount::from(30),
+            from: Address::Account(admin),
+            data: AdditionalData::empty(),
+        };
+        let params_byte = to_bytes(&params);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_sender(Address::Contract(other_token_address));
+        ctx.set_parameter(&params_byte);
+        let result = callback(&ctx, &mut host);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(host.state().token_balance(other_token_address, &other_token_id), 30);
+        claim_eq!(host.state().recoverable_tokens(other_token_address, &other_token_id), 30);
+
+        let recover = |host: &mut TestHost<State<TestStateApi>>,
+                       token: ContractAddress,
+                       token_id: TokenIdVec,
+                       amount: u64| {
+            let params = RecoverTokensParams {
+                token,
+                token_id,
+                amount: ContractTokenAmount::from(amount),
+                to: Address::Account(admin),
+            };
+            let params_byte = to_bytes(&params);
+            let mut ctx = TestReceiveContext::empty();
+            ctx.set_self_address(self_address);
+            ctx.set_owner(admin);
+            ctx.set_sender(Address::Account(admin));
+            ctx.set_parameter(&params_byte);
+            let mut logger = TestLogger::init();
+            contract_recover_tokens(&ctx, host, &mut logger)
+        };
+
+        // tokens reserved for the claims cannot be recovered
+        let project_token_id = TokenIdVec(Vec::new());
+        claim_eq!(
+            host.state().recoverable_tokens(project_token_address, &project_token_id),
+            500
+        );
+        let err = recover(&mut host, project_token_address, project_token_id.clone(), 501)
+            .expect_err_report("recoverTokens should reject");
+        claim_eq!(
+            err,
+            ContractError::InsufficientFunds,
+            "recoverTokens should reject beyond the surplus"
+        );
+
+        let result = recover(&mut host, project_token_address, project_token_id.clone(), 500);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(
+            host.state().token_balance(project_token_address, &project_token_id),
+            8_000
+        );
+
+        let result = recover(&mut host, other_token_address, other_token_id.clone(), 30);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(host.state().token_balance(other_token_address, &other_token_id), 0);
+    }
+
+    #[concordium_test]
+    /// Test that only the fees stay reserved once the escrow has failed,
+    /// so that the rest of the pool can be returned to the project.
+    fn test_recover_tokens_escrow_failed() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let depositor = Address::Account(AccountAddress([1u8; 32]));
+        let self_address = ContractAddress {
+            index: 10,
+            subindex: 0,
+        };
+        let project_token_address = ContractAddress {
+            index: 1000,
+            subindex: 0,
+        };
+        let project_token_id = TokenIdVec(Vec::new());
+        let mut escrow = Escrow::new(EscrowParams {
+            tranches: vec![(Milestone::TgeSet, 10_000)],
+            vote_quorum: None,
+        })
+        .unwrap();
+        escrow.raised = Amount::from_ccd(50_000);
+        escrow.failed = true;
+        escrow.refundable = Amount::from_ccd(50_000);
+
+        // 10000 tokens sold, 1900 claimed by the users and 100 by OVL before the failure
+        let mut token_balances = state_builder.new_map();
+        token_balances.insert(
+            (project_token_address, project_token_id.clone()),
+            ContractTokenAmount::from(8_000),
+        );
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin: AccountAddress([1u8; 32]),
+            status: SaleStatus::Fixed,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (
+                    Address::Account(AccountAddress([2u8; 32])),
+                    FeeState {
+                        bps: 500,
+                        ccd_bps: 0,
+                        claimed_inc: 1,
+                        claimed_amount: ContractTokenAmount::from(100),
+                    },
+                ),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: Some(project_token_address),
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: Some(Timestamp::from_timestamp_millis(50)),
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 50,
+                applied_units: 50,
+            },
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: Some(escrow),
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(10_000),
+            pool_depositor: Some(depositor),
+            token_balances,
+            claimed_tokens: ContractTokenAmount::from(2_000),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.setup_mock_entrypoint(
+            project_token_address,
+            OwnedEntrypointName::new_unchecked("transfer".into()),
+            MockFn::returning_ok(()),
+        );
+
+        // 400 tokens left for OVL and 500 for BBB
+        claim_eq!(
+            host.state().recoverable_tokens(project_token_address, &project_token_id),
+            7_100
+        );
+
+        let recover = |host: &mut TestHost<State<TestStateApi>>, amount: u64| {
+            let params = RecoverTokensParams {
+                token: project_token_address,
+                token_id: TokenIdVec(Vec::new()),
+                amount: ContractTokenAmount::from(amount),
+                to: depositor,
+            };
+            let params_byte = to_bytes(&params);
+            let mut ctx = TestReceiveContext::empty();
+            ctx.set_self_address(self_address);
+            ctx.set_owner(admin);
+            ctx.set_sender(Address::Account(admin));
+            ctx.set_parameter(&params_byte);
+            let mut logger = TestLogger::init();
+            contract_recover_tokens(&ctx, host, &mut logger)
+        };
+
+        let err = recover(&mut host, 7_101).expect_err_report("recoverTokens should reject");
+        claim_eq!(
+            err,
+            ContractError::InsufficientFunds,
+            "recoverTokens should keep the fees reserved"
+        );
+
+        let result = recover(&mut host, 7_100);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(
+            host.state().token_balance(project_token_address, &project_token_id),
+            900
+        );
+    }
+
+    #[concordium_test]
+    /// Test that the rounding leftover is swept once every claim is complete.
+    fn test_sweep_dust() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let self_address = ContractAddress {
+            index: 10,
+            subindex: 0,
+        };
+        let project_token_address = ContractAddress {
+            index: 1000,
+            subindex: 0,
+        };
+        let vesting_start = Timestamp::from_timestamp_millis(50);
+        let addr_bbb = Address::Account(AccountAddress([3u8; 32]));
+
+        let user = Address::Account(AccountAddress([10u8; 32]));
+
+        // 303 tokens sold: 15 for OVL, 15 for BBB and 272 for the user, leaving 1 token
+        let mut participants = Participants::new(&mut state_builder);
+        participants.insert(
+            user,
+            UserState {
+                prior: Prior::TOP,
+                deposit_ccd: Amount::from_ccd(1_515),
+                tgt_units: 3,
+                win_units: 3,
+                claimed_inc: 3,
+                alloc_tokens: ContractTokenAmount::from(0),
+                refundable_ccd: Amount::zero(),
+                refunded: false,
+                claimed_amount: ContractTokenAmount::from(271),
+                escrow_votes: 0,
+            },
+        );
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin: AccountAddress([1u8; 32]),
+            status: SaleStatus::Fixed,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (
+                    Address::Account(AccountAddress([2u8; 32])),
+                    FeeState {
+                        bps: 500,
+                        ccd_bps: 0,
+                        claimed_inc: 3,
+                        claimed_amount: ContractTokenAmount::from(15),
+                    },
+                ),
+                (addr_bbb, FeeState::new(500)),
+            ]),
+            project_token: Some(project_token_address),
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: Some(vesting_start),
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([
+                    (Duration::from_days(1), 3_300),
+                    (Duration::from_days(2), 3_300),
+                    (Duration::from_days(3), 3_400),
+                ])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 101.into(),
+                max_units: 100,
+                min_units: 2,
+                applied_units: 3,
+            },
+            participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(303),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(286),
+            unclaimed_users: 1,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.setup_mock_entrypoint(
+            project_token_address,
+            OwnedEntrypointName::new_unchecked("transfer".into()),
+            MockFn::returning_ok(()),
+        );
+
+        let now = vesting_start.checked_add(Duration::from_days(3)).unwrap();
+        let params_byte = to_bytes(&Address::Account(admin));
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_self_address(self_address);
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(now);
+        ctx.set_parameter(&params_byte);
+
+        // BBB and the user have not claimed everything yet
+        let mut logger = TestLogger::init();
+        let err = contract_sweep_dust(&ctx, &mut host, &mut logger)
+            .expect_err_report("sweepDust should reject");
+        claim_eq!(
+            err,
+            ContractError::from(CustomContractError::ClaimsOutstanding),
+            "sweepDust should wait for every claim"
+        );
+
+        let params_bytes = to_bytes(&addr_bbb);
+        let mut claim_ctx = TestReceiveContext::empty();
+        claim_ctx.set_self_address(self_address);
+        claim_ctx.set_owner(admin);
+        claim_ctx.set_sender(Address::Account(admin));
+        claim_ctx.set_metadata_slot_time(now);
+        claim_ctx.set_parameter(&params_bytes);
+        let result = contract_fee_claim(&claim_ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(
+            host.state().get_fee(&addr_bbb).map(|fee| fee.claimed_amount),
+            Ok(ContractTokenAmount::from(15))
+        );
+
+        let mut logger = TestLogger::init();
+        let err = contract_sweep_dust(&ctx, &mut host, &mut logger)
+            .expect_err_report("sweepDust should reject");
+        claim_eq!(
+            err,
+            ContractError::from(CustomContractError::ClaimsOutstanding),
+            "sweepDust should wait for the user to claim"
+        );
+
+        let mut claim_ctx = TestReceiveContext::empty();
+        claim_ctx.set_self_address(self_address);
+        claim_ctx.set_sender(user);
+        claim_ctx.set_metadata_slot_time(now);
+        let result = contract_user_claim(&claim_ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(host.state().unclaimed_users, 0);
+
+        let mut logger = TestLogger::init();
+        let result = contract_sweep_dust(&ctx, &mut host, &mut logger);
+        claim_eq!(result, Ok(ContractTokenAmount::from(1)));
+        claim_eq!(host.state().claimed_tokens, ContractTokenAmount::from(303));
+        claim_eq!(
+            logger.logs[0],
+            to_bytes(&SaleEvent::DustSwept(DustSweptEvent {
+                amount: ContractTokenAmount::from(1),
+                to: Address::Account(admin),
+            })),
+            "Incorrect event emitted"
+        );
+
+        // nothing is left for the second time
+        let mut logger = TestLogger::init();
+        let result = contract_sweep_dust(&ctx, &mut host, &mut logger);
+        claim_eq!(result, Ok(ContractTokenAmount::from(0)));
+        claim!(logger.logs.is_empty(), "No event should be emitted");
+    }
 }
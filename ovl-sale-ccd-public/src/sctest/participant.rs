@@ -2,7 +2,8 @@ use concordium_std::concordium_cfg_test;
 
 #[concordium_cfg_test]
 mod tests {
-    use crate::*;
+    use crate::{participants::Participants, *};
+    use concordium_cis2::{AdditionalData, Receiver, TokenIdUnit, Transfer, TransferParams};
     use concordium_std::test_infrastructure::*;
 
     #[concordium_test]
@@ -28,11 +29,11 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
@@ -42,13 +43,15 @@ mod tests {
             AllowedUserParams {
                 user: Address::Account(first_user),
                 prior: Prior::TOP,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(second_user),
                 prior: Prior::SECOND,
+                tier: Tier::T0,
             },
         ];
-        let mut participants = state_builder.new_map();
+        let mut participants = Participants::new(&mut state_builder);
         for params in &whitelist {
             participants.insert(
                 params.user,
@@ -56,13 +59,15 @@ mod tests {
             );
         }
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -78,8 +83,26 @@ mod tests {
                 applied_units: 0,
             },
             participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
-        let mut expected_participants = state_builder.new_map();
+        let mut expected_participants = Participants::new(&mut state_builder);
         for params in &whitelist {
             if params.user == Address::Account(first_user) {
                 expected_participants.insert(
@@ -90,6 +113,11 @@ mod tests {
                         tgt_units: TARGET_UNITS,
                         win_units: 1,
                         claimed_inc: 0,
+                        alloc_tokens: ContractTokenAmount::from(0),
+                        refundable_ccd: Amount::zero(),
+                        refunded: false,
+                        claimed_amount: ContractTokenAmount::from(0),
+                        escrow_votes: 0,
                     },
                 );
             } else {
@@ -100,13 +128,15 @@ mod tests {
             }
         }
         let expected_state_after_first_call = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -122,8 +152,26 @@ mod tests {
                 applied_units: 1,
             },
             participants: expected_participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
-        let mut expected_participants = state_builder.new_map();
+        let mut expected_participants = Participants::new(&mut state_builder);
         for params in &whitelist {
             expected_participants.insert(
                 params.user,
@@ -133,17 +181,24 @@ mod tests {
                     tgt_units: TARGET_UNITS,
                     win_units: 1,
                     claimed_inc: 0,
+                    alloc_tokens: ContractTokenAmount::from(0),
+                    refundable_ccd: Amount::zero(),
+                    refunded: false,
+                    claimed_amount: ContractTokenAmount::from(0),
+                    escrow_votes: 0,
                 },
             );
         }
         let expected_state_after_second_call = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -159,6 +214,24 @@ mod tests {
                 applied_units: 2,
             },
             participants: expected_participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
 
@@ -170,9 +243,26 @@ mod tests {
         ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(15));
 
         // execute function
-        let result = contract_user_deposit(&ctx, &mut host, deposit_amount);
+        let crypto_primitives = TestCryptoPrimitives::new();
+        let mut logger = TestLogger::init();
+        let result = contract_user_deposit(
+            &ctx,
+            &mut host,
+            deposit_amount,
+            &mut logger,
+            &crypto_primitives,
+        );
         claim!(result.is_ok());
         claim_eq!(*host.state(), expected_state_after_first_call);
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::Deposited(DepositedEvent {
+                user: Address::Account(first_user),
+                amount: deposit_amount,
+                units: 1,
+            }))],
+            "Deposited event should be logged."
+        );
 
         // 2nd user call
         // create params
@@ -182,11 +272,532 @@ mod tests {
         ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(25));
 
         // execute function
-        let result = contract_user_deposit(&ctx, &mut host, deposit_amount);
+        let mut logger = TestLogger::init();
+        let result = contract_user_deposit(
+            &ctx,
+            &mut host,
+            deposit_amount,
+            &mut logger,
+            &crypto_primitives,
+        );
         claim!(result.is_ok());
         claim_eq!(*host.state(), expected_state_after_second_call);
     }
 
+    #[concordium_test]
+    /// Test that userDeposit charges the price of all units of the user's tier.
+    fn test_user_deposit_with_tier() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let first_user = AccountAddress([10u8; 32]);
+        let unit_price = Amount::from_micro_ccd(5_000_000 * 200);
+        let deposit_amount = Amount::from_micro_ccd(5_000_000 * 200 * 3);
+        let tier_units = BTreeMap::from([(Tier::T0, TARGET_UNITS), (Tier::T2, 3)]);
+        let schedule = SaleSchedule {
+            open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+            close_at: Timestamp::from_timestamp_millis(30),
+            vesting_start: None,
+            vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                Duration::from_days(1),
+                10_000,
+            )])),
+        };
+        let saleinfo = SaleInfo {
+            price_per_token: 5_000_000,
+            token_per_unit: 200.into(),
+            max_units: 100,
+            min_units: 50,
+            applied_units: 0,
+        };
+
+        let mut participants = Participants::new(&mut state_builder);
+        participants.insert(
+            Address::Account(first_user),
+            UserState::new(Prior::TOP, Amount::zero(), 3),
+        );
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin: AccountAddress([1u8; 32]),
+            status: SaleStatus::Ready,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: None,
+            schedule: schedule.clone(),
+            saleinfo: saleinfo.clone(),
+            tier_units: tier_units.clone(),
+            participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut expected_participants = Participants::new(&mut state_builder);
+        expected_participants.insert(
+            Address::Account(first_user),
+            UserState {
+                prior: Prior::TOP,
+                deposit_ccd: deposit_amount,
+                tgt_units: 3,
+                win_units: 3,
+                claimed_inc: 0,
+                alloc_tokens: ContractTokenAmount::from(0),
+                refundable_ccd: Amount::zero(),
+                refunded: false,
+                claimed_amount: ContractTokenAmount::from(0),
+                escrow_votes: 0,
+            },
+        );
+        let expected_state = State {
+            version: STATE_VERSION,
+            proj_admin: AccountAddress([1u8; 32]),
+            status: SaleStatus::Ready,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: None,
+            schedule,
+            saleinfo: SaleInfo {
+                applied_units: 3,
+                ..saleinfo
+            },
+            tier_units,
+            participants: expected_participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(first_user));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(15));
+
+        // the price of a single unit is not enough
+        let crypto_primitives = TestCryptoPrimitives::new();
+        let mut logger = TestLogger::init();
+        let result =
+            contract_user_deposit(&ctx, &mut host, unit_price, &mut logger, &crypto_primitives);
+        let err = result.expect_err_report("userDeposit should reject");
+        claim_eq!(
+            err,
+            CustomContractError::InvalidCcdInput.into(),
+            "userDeposit should reject with InvalidCcdInput"
+        );
+
+        let mut logger = TestLogger::init();
+        let result = contract_user_deposit(
+            &ctx,
+            &mut host,
+            deposit_amount,
+            &mut logger,
+            &crypto_primitives,
+        );
+        claim!(result.is_ok());
+        claim_eq!(*host.state(), expected_state);
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::Deposited(DepositedEvent {
+                user: Address::Account(first_user),
+                amount: deposit_amount,
+                units: 3,
+            }))],
+            "Deposited event should be logged."
+        );
+    }
+
+    #[concordium_test]
+    #[cfg(not(feature = "crypto-primitives"))]
+    /// Test that a user out of the whitelist can deposit with an allocation ticket.
+    fn test_user_deposit_with_ticket() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let first_user = AccountAddress([10u8; 32]);
+        let second_user = AccountAddress([11u8; 32]);
+        let sale = ContractAddress::new(2000, 0);
+        let ticket_key = PublicKeyEd25519([7u8; 32]);
+        let signature = SignatureEd25519([8u8; 64]);
+        let deposit_amount = Amount::from_micro_ccd(5_000_000 * 200 * 2);
+
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin: AccountAddress([1u8; 32]),
+            status: SaleStatus::Ready,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 50,
+                applied_units: 0,
+            },
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: Some(ticket_key),
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+
+        let ticket = AllocationTicket {
+            user: Address::Account(first_user),
+            prior: Prior::TOP,
+            units: 2,
+            expiry: Timestamp::from_timestamp_millis(20),
+            contract: sale,
+        };
+        let message = to_bytes(&ticket);
+        let crypto_primitives = TestCryptoPrimitives::new();
+        crypto_primitives.setup_verify_ed25519_signature_mock(move |key, sig, msg| {
+            key == ticket_key && sig == signature && msg == message.as_slice()
+        });
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_self_address(sale);
+        ctx.set_sender(Address::Account(first_user));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(15));
+
+        // the ticket of another user cannot be used
+        let params_byte = to_bytes(&Some(Allowance::Ticket(SignedTicket {
+            ticket: ticket.clone(),
+            signature,
+        })));
+        ctx.set_parameter(&params_byte);
+        ctx.set_sender(Address::Account(second_user));
+        let mut logger = TestLogger::init();
+        let result = contract_user_deposit(
+            &ctx,
+            &mut host,
+            deposit_amount,
+            &mut logger,
+            &crypto_primitives,
+        );
+        let err = result.expect_err_report("userDeposit should reject");
+        claim_eq!(
+            err,
+            CustomContractError::InvalidTicket.into(),
+            "userDeposit should reject with InvalidTicket"
+        );
+
+        // a forged ticket is rejected
+        let params_byte = to_bytes(&Some(Allowance::Ticket(SignedTicket {
+            ticket: AllocationTicket {
+                units: 3,
+                ..ticket.clone()
+            },
+            signature,
+        })));
+        ctx.set_parameter(&params_byte);
+        ctx.set_sender(Address::Account(first_user));
+        let result = contract_user_deposit(
+            &ctx,
+            &mut host,
+            deposit_amount,
+            &mut logger,
+            &crypto_primitives,
+        );
+        let err = result.expect_err_report("userDeposit should reject");
+        claim_eq!(
+            err,
+            CustomContractError::InvalidTicket.into(),
+            "userDeposit should reject with InvalidTicket"
+        );
+
+        let params_byte = to_bytes(&Some(Allowance::Ticket(SignedTicket {
+            ticket: ticket.clone(),
+            signature,
+        })));
+        ctx.set_parameter(&params_byte);
+        let result = contract_user_deposit(
+            &ctx,
+            &mut host,
+            deposit_amount,
+            &mut logger,
+            &crypto_primitives,
+        );
+        claim!(result.is_ok(), "Results in rejection");
+        let user = host
+            .state_mut()
+            .get_user(&Address::Account(first_user))
+            .unwrap();
+        claim_eq!(user.prior, Prior::TOP);
+        claim_eq!(user.tgt_units, 2);
+        claim_eq!(user.win_units, 2);
+        claim_eq!(user.deposit_ccd, deposit_amount);
+
+        // tickets cannot be used after they expire
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(21));
+        ctx.set_sender(Address::Account(second_user));
+        let params_byte = to_bytes(&Some(Allowance::Ticket(SignedTicket {
+            ticket: AllocationTicket {
+                user: Address::Account(second_user),
+                ..ticket
+            },
+            signature,
+        })));
+        ctx.set_parameter(&params_byte);
+        let result = contract_user_deposit(
+            &ctx,
+            &mut host,
+            deposit_amount,
+            &mut logger,
+            &crypto_primitives,
+        );
+        let err = result.expect_err_report("userDeposit should reject");
+        claim_eq!(
+            err,
+            CustomContractError::TicketExpired.into(),
+            "userDeposit should reject with TicketExpired"
+        );
+    }
+
+    #[concordium_test]
+    #[cfg(not(feature = "crypto-primitives"))]
+    /// Test that users in the whitelist committed to by its Merkle root can deposit with a proof.
+    fn test_user_deposit_with_whitelist_proof() {
+        use sale_utils::merkle::{MerkleHash, MerkleTree};
+
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let users = [
+            AccountAddress([10u8; 32]),
+            AccountAddress([11u8; 32]),
+            AccountAddress([12u8; 32]),
+        ];
+        let outsider = AccountAddress([13u8; 32]);
+        let tier_units = BTreeMap::from([(Tier::T0, TARGET_UNITS), (Tier::T2, 3)]);
+        let deposit_amount = Amount::from_micro_ccd(5_000_000 * 200 * 3);
+
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin: AccountAddress([1u8; 32]),
+            status: SaleStatus::Prepare,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 50,
+                applied_units: 0,
+            },
+            tier_units: tier_units.clone(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+
+        // stands in for sha256, which needs the crypto-primitives feature
+        let crypto_primitives = TestCryptoPrimitives::new();
+        crypto_primitives.setup_hash_sha2_256_mock(|data| {
+            let mut hash = [0u8; 32];
+            for (i, byte) in data.iter().enumerate() {
+                hash[i % 32] = hash[i % 32].wrapping_mul(31).wrapping_add(*byte);
+            }
+            HashSha2256(hash)
+        });
+
+        let whitelist: Vec<AllowedUserParams> = users
+            .iter()
+            .map(|user| AllowedUserParams {
+                user: Address::Account(*user),
+                prior: Prior::TOP,
+                tier: Tier::T2,
+            })
+            .collect();
+        let tree = MerkleTree::new(
+            &crypto_primitives,
+            MerkleHash::Sha2_256,
+            &whitelist,
+            &tier_units,
+        )
+        .unwrap();
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        let params_byte = to_bytes(&Some(tree.root()));
+        ctx.set_parameter(&params_byte);
+        let mut logger = TestLogger::init();
+        let result = contract_set_whitelist_root(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(host.state().whitelist_root, Some(tree.root()));
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::WhitelistRootSet(Some(tree.root())))],
+            "WhitelistRootSet event should be logged."
+        );
+        host.state_mut().status = SaleStatus::Ready;
+
+        // the last user has no sibling at the leaves
+        let (leaf, proof) = tree.proof(&Address::Account(users[2])).unwrap();
+        claim_eq!(leaf.units, 3);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(15));
+
+        // the proof of another user cannot be used
+        let params_byte = to_bytes(&Some(Allowance::Proof(WhitelistProof {
+            prior: leaf.prior.clone(),
+            units: leaf.units,
+            proof: proof.clone(),
+        })));
+        ctx.set_parameter(&params_byte);
+        ctx.set_sender(Address::Account(outsider));
+        let result = contract_user_deposit(
+            &ctx,
+            &mut host,
+            deposit_amount,
+            &mut logger,
+            &crypto_primitives,
+        );
+        let err = result.expect_err_report("userDeposit should reject");
+        claim_eq!(
+            err,
+            CustomContractError::InvalidProof.into(),
+            "userDeposit should reject with InvalidProof"
+        );
+
+        // the units cannot be changed
+        let params_byte = to_bytes(&Some(Allowance::Proof(WhitelistProof {
+            prior: leaf.prior.clone(),
+            units: 4,
+            proof: proof.clone(),
+        })));
+        ctx.set_parameter(&params_byte);
+        ctx.set_sender(Address::Account(users[2]));
+        let result = contract_user_deposit(
+            &ctx,
+            &mut host,
+            deposit_amount,
+            &mut logger,
+            &crypto_primitives,
+        );
+        let err = result.expect_err_report("userDeposit should reject");
+        claim_eq!(
+            err,
+            CustomContractError::InvalidProof.into(),
+            "userDeposit should reject with InvalidProof"
+        );
+
+        for user in users {
+            let (leaf, proof) = tree.proof(&Address::Account(user)).unwrap();
+            let params_byte = to_bytes(&Some(Allowance::Proof(WhitelistProof {
+                prior: leaf.prior,
+                units: leaf.units,
+                proof,
+            })));
+            let mut ctx = TestReceiveContext::empty();
+            ctx.set_owner(admin);
+            ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(15));
+            ctx.set_parameter(&params_byte);
+            ctx.set_sender(Address::Account(user));
+            let result = contract_user_deposit(
+                &ctx,
+                &mut host,
+                deposit_amount,
+                &mut logger,
+                &crypto_primitives,
+            );
+            claim!(result.is_ok(), "Results in rejection");
+        }
+        claim_eq!(host.state().saleinfo.applied_units, 9);
+    }
+
     #[concordium_test]
     /// Test that userDeposit fails when the status is not ready.
     fn test_user_deposit_before_ready() {
@@ -211,11 +822,11 @@ mod tests {
         ]);
         let slot_time = Timestamp::from_timestamp_millis(15);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
@@ -225,13 +836,15 @@ mod tests {
             AllowedUserParams {
                 user: Address::Account(first_user),
                 prior: Prior::TOP,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(second_user),
                 prior: Prior::SECOND,
+                tier: Tier::T0,
             },
         ];
-        let mut participants = state_builder.new_map();
+        let mut participants = Participants::new(&mut state_builder);
         for params in &whitelist {
             participants.insert(
                 params.user,
@@ -239,13 +852,15 @@ mod tests {
             );
         }
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Prepare,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -261,6 +876,24 @@ mod tests {
                 applied_units: 0,
             },
             participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
 
@@ -272,7 +905,15 @@ mod tests {
         ctx.set_metadata_slot_time(slot_time);
 
         // execute function
-        let result = contract_user_deposit(&ctx, &mut host, deposit_amount);
+        let crypto_primitives = TestCryptoPrimitives::new();
+        let mut logger = TestLogger::init();
+        let result = contract_user_deposit(
+            &ctx,
+            &mut host,
+            deposit_amount,
+            &mut logger,
+            &crypto_primitives,
+        );
         claim!(result.is_err());
         let err = result.expect_err_report("userDeposit should reject");
         claim_eq!(
@@ -307,11 +948,11 @@ mod tests {
         // before the first open time.
         let slot_time = Timestamp::from_timestamp_millis(9);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
@@ -321,13 +962,15 @@ mod tests {
             AllowedUserParams {
                 user: Address::Account(first_user),
                 prior: Prior::TOP,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(second_user),
                 prior: Prior::SECOND,
+                tier: Tier::T0,
             },
         ];
-        let mut participants = state_builder.new_map();
+        let mut participants = Participants::new(&mut state_builder);
         for params in &whitelist {
             participants.insert(
                 params.user,
@@ -335,13 +978,15 @@ mod tests {
             );
         }
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -357,6 +1002,24 @@ mod tests {
                 applied_units: 0,
             },
             participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
 
@@ -368,7 +1031,15 @@ mod tests {
         ctx.set_metadata_slot_time(slot_time);
 
         // execute function
-        let result = contract_user_deposit(&ctx, &mut host, deposit_amount);
+        let crypto_primitives = TestCryptoPrimitives::new();
+        let mut logger = TestLogger::init();
+        let result = contract_user_deposit(
+            &ctx,
+            &mut host,
+            deposit_amount,
+            &mut logger,
+            &crypto_primitives,
+        );
         claim!(result.is_err());
         let err = result.expect_err_report("userDeposit should reject");
         claim_eq!(
@@ -401,11 +1072,11 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
@@ -415,13 +1086,15 @@ mod tests {
             AllowedUserParams {
                 user: Address::Account(first_user),
                 prior: Prior::TOP,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(second_user),
                 prior: Prior::SECOND,
+                tier: Tier::T0,
             },
         ];
-        let mut participants = state_builder.new_map();
+        let mut participants = Participants::new(&mut state_builder);
         for params in &whitelist {
             if params.user == Address::Account(first_user) {
                 participants.insert(
@@ -432,6 +1105,11 @@ mod tests {
                         tgt_units: TARGET_UNITS,
                         win_units: 1,
                         claimed_inc: 0,
+                        alloc_tokens: ContractTokenAmount::from(0),
+                        refundable_ccd: Amount::zero(),
+                        refunded: false,
+                        claimed_amount: ContractTokenAmount::from(0),
+                        escrow_votes: 0,
                     },
                 );
             } else {
@@ -442,13 +1120,15 @@ mod tests {
             }
         }
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Suspend,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -464,25 +1144,44 @@ mod tests {
                 applied_units: 1,
             },
             participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
-        let mut expected_participants = state_builder.new_map();
+        let mut expected_participants = Participants::new(&mut state_builder);
         for params in &whitelist {
-            if params.user == Address::Account(first_user) {
-                continue;
-            }
             expected_participants.insert(
                 params.user,
                 UserState::new(params.prior.clone(), Amount::zero(), TARGET_UNITS),
             );
         }
+        // the user who quits keeps their position in the join order
+        expected_participants.remove(&Address::Account(first_user));
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Suspend,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -498,6 +1197,24 @@ mod tests {
                 applied_units: 0,
             },
             participants: expected_participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
         host.set_self_balance(deposit_amount);
@@ -509,7 +1226,8 @@ mod tests {
         ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(40));
 
         // execute function
-        let result = contract_user_quit(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_user_quit(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(*host.state(), expected_state);
         claim_eq!(
@@ -520,117 +1238,1093 @@ mod tests {
     }
 
     #[concordium_test]
-    /// Test that userQuit fails due to the function is currently disabled.
-    fn test_user_quit_fails_due_to_currently_disabled() {
+    /// Test that depositors can quit once the project misses the delivery deadline,
+    /// with the excess held for them in overflow mode refunded as part of the deposit.
+    fn test_user_quit_after_delivery_deadline() {
         let mut state_builder = TestStateBuilder::new();
         let admin = AccountAddress([0u8; 32]);
         let proj_admin = AccountAddress([1u8; 32]);
-        let first_user = AccountAddress([10u8; 32]);
-        let second_user = AccountAddress([11u8; 32]);
-        let deposit_amount = Amount::from_micro_ccd(5_000_000 * 200 * 1);
-        let project_token_address = ContractAddress {
-            index: 1000,
-            subindex: 0,
-        };
-        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
-        let addr_bbb = Address::Contract(ContractAddress {
-            index: 100,
-            subindex: 0,
-        });
-        let open_at = BTreeMap::from([
-            (Timestamp::from_timestamp_millis(10), Prior::TOP),
-            (Timestamp::from_timestamp_millis(20), Prior::SECOND),
-        ]);
-        let slot_time = Timestamp::from_timestamp_millis(15);
-        let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
-        let max_units = 100;
-        let min_units = 50;
-        let price_per_token = 5_000_000;
-        let token_per_unit = 200.into();
+        let user = AccountAddress([10u8; 32]);
+        let anyone = AccountAddress([99u8; 32]);
+        let deposit_amount = Amount::from_micro_ccd(5_000_000 * 200);
+        let excess = Amount::from_micro_ccd(5_000_000 * 100);
 
-        let whitelist = vec![
-            AllowedUserParams {
-                user: Address::Account(first_user),
-                prior: Prior::TOP,
-            },
-            AllowedUserParams {
-                user: Address::Account(second_user),
-                prior: Prior::SECOND,
+        let mut participants = Participants::new(&mut state_builder);
+        participants.insert(
+            Address::Account(user),
+            UserState {
+                alloc_tokens: ContractTokenAmount::from(100),
+                refundable_ccd: excess,
+                ..UserState::new(Prior::TOP, deposit_amount, TARGET_UNITS)
             },
-        ];
-        let mut participants = state_builder.new_map();
-        for params in &whitelist {
-            if params.user == Address::Account(first_user) {
-                participants.insert(
-                    params.user,
-                    UserState {
-                        prior: params.prior.clone(),
-                        deposit_ccd: deposit_amount,
-                        tgt_units: TARGET_UNITS,
-                        win_units: 1,
-                        claimed_inc: 0,
-                    },
-                );
-            } else {
-                participants.insert(
-                    params.user,
-                    UserState::new(params.prior.clone(), Amount::zero(), TARGET_UNITS),
-                );
-            }
-        }
+        );
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
-            status: SaleStatus::Ready,
+            status: SaleStatus::Fixed,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
-            project_token: Some(project_token_address),
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: None,
             schedule: SaleSchedule {
-                open_at: open_at.clone(),
-                close_at,
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
                 vesting_start: None,
-                vesting_period: vesting_period.clone(),
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
             },
             saleinfo: SaleInfo {
-                price_per_token,
-                token_per_unit,
-                max_units,
-                min_units,
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 1,
                 applied_units: 1,
             },
             participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: Some(Overflow {
+                min_deposit: Amount::from_ccd(1),
+                total_deposit: deposit_amount,
+                allocated_tokens: ContractTokenAmount::from(100),
+                refundable: excess,
+                settled: true,
+                next: 0,
+            }),
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: Some(Timestamp::from_timestamp_millis(100)),
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
         host.set_self_balance(deposit_amount);
 
-        // create params
+        // the project admin cannot take the CCD before delivery
         let mut ctx = TestReceiveContext::empty();
         ctx.set_owner(admin);
-        ctx.set_sender(Address::Account(first_user));
-        ctx.set_metadata_slot_time(slot_time);
+        ctx.set_sender(Address::Account(proj_admin));
+        let mut logger = TestLogger::init();
+        let result = contract_project_claim(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("projectClaim should reject before delivery");
+        claim_eq!(
+            err,
+            CustomContractError::NotDelivered.into(),
+            "projectClaim should reject before delivery"
+        );
 
-        // execute function
-        let result = contract_user_quit(&ctx, &mut host);
-        claim!(result.is_err());
-        let err = result.expect_err_report("userQuit should reject");
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(anyone));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(100));
+        let result = contract_set_failed(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("setFailed should reject until the deadline passes");
         claim_eq!(
             err,
-            CustomContractError::DisabledForNow.into(),
-            "userQuit should reject with DisabledForNow"
+            CustomContractError::Inappropriate.into(),
+            "setFailed should reject until the deadline passes"
+        );
+
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(101));
+        let result = contract_set_failed(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim_eq!(host.state().status, SaleStatus::Failed);
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::StatusChanged(SaleStatus::Failed))],
+            "StatusChanged event should be logged."
+        );
+
+        // a failed sale cannot be fixed again
+        ctx.set_sender(Address::Account(admin));
+        let result = contract_set_fixed(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("setFixed should reject once the sale has failed");
+        claim_eq!(
+            err,
+            CustomContractError::SaleNotReady.into(),
+            "setFixed should reject once the sale has failed"
+        );
+        claim_eq!(host.state().status, SaleStatus::Failed);
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(user));
+        let mut logger = TestLogger::init();
+        let result = contract_user_quit(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim_eq!(
+            host.get_transfers(),
+            [(user, deposit_amount)],
+            "the deposit should be refunded."
+        );
+        claim_eq!(
+            host.state().refundable(),
+            Amount::zero(),
+            "the excess should be refunded with the deposit"
         );
     }
 
     #[concordium_test]
-    /// Test that userClaim successfully calculate total amount to claim & transfer it.
-    /// This also checks that userClaim can handle multiple user call.
-    fn test_user_claim() {
+    /// Test that a contract participant quits a suspended sale through the refund hook.
+    fn test_user_quit_by_contract() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let contract_user = ContractAddress {
+            index: 3000,
+            subindex: 0,
+        };
+        let deposit_amount = Amount::from_micro_ccd(5_000_000 * 200);
+
+        let mut participants = Participants::new(&mut state_builder);
+        participants.insert(
+            Address::Contract(contract_user),
+            UserState::new(Prior::TOP, deposit_amount, TARGET_UNITS),
+        );
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin: AccountAddress([1u8; 32]),
+            status: SaleStatus::Suspend,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 50,
+                applied_units: 1,
+            },
+            participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: Some(OwnedEntrypointName::new_unchecked("refundHook".into())),
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.set_self_balance(deposit_amount);
+        host.setup_mock_entrypoint(
+            contract_user,
+            OwnedEntrypointName::new_unchecked("refundHook".into()),
+            MockFn::new_v1(move |_parameter, amount, _balance, _state| {
+                claim_eq!(amount, deposit_amount, "the deposit should be attached");
+                Ok((false, ()))
+            }),
+        );
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Contract(contract_user));
+        let mut logger = TestLogger::init();
+        let result = contract_user_quit(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(host.self_balance(), Amount::zero());
+        claim!(host.state().participants.get(&Address::Contract(contract_user)).is_none());
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::Quit(QuitEvent {
+                user: Address::Contract(contract_user),
+                amount: deposit_amount,
+            }))],
+            "Quit event should be logged."
+        );
+    }
+
+    #[concordium_test]
+    /// Test that a lottery loser can reclaim their deposit once.
+    fn test_user_reclaim() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let winner = AccountAddress([10u8; 32]);
+        let loser = AccountAddress([11u8; 32]);
+        let unit_price = Amount::from_micro_ccd(5_000_000 * 200);
+        let seed: LotterySeed = [7u8; 32];
+        let commitment = HashSha2256([1u8; 32]);
+
+        let state_with = |state_builder: &mut TestStateBuilder,
+                          loser_deposit: Amount,
+                          refundable: Amount| {
+            let mut participants = Participants::new(state_builder);
+            participants.insert(
+                Address::Account(winner),
+                UserState {
+                    prior: Prior::TOP,
+                    deposit_ccd: unit_price,
+                    tgt_units: TARGET_UNITS,
+                    win_units: TARGET_UNITS,
+                    claimed_inc: 0,
+                    alloc_tokens: ContractTokenAmount::from(0),
+                    refundable_ccd: Amount::zero(),
+                    refunded: false,
+                    claimed_amount: ContractTokenAmount::from(0),
+                    escrow_votes: 0,
+                },
+            );
+            participants.insert(
+                Address::Account(loser),
+                UserState::new(Prior::TOP, loser_deposit, TARGET_UNITS),
+            );
+            State {
+                version: STATE_VERSION,
+                proj_admin: AccountAddress([1u8; 32]),
+                status: SaleStatus::Fixed,
+                paused: false,
+                user_share: 9_000,
+                fees: BTreeMap::from([
+                    (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                    (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+                ]),
+                project_token: None,
+                schedule: SaleSchedule {
+                    open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                    close_at: Timestamp::from_timestamp_millis(30),
+                    vesting_start: None,
+                    vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                        Duration::from_days(1),
+                        10_000,
+                    )])),
+                },
+                saleinfo: SaleInfo {
+                    price_per_token: 5_000_000,
+                    token_per_unit: 200.into(),
+                    max_units: 1,
+                    min_units: 1,
+                    applied_units: 1,
+                },
+                tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+                participants,
+                multisig: Multisig::new(state_builder, BTreeSet::from([admin]), 1).unwrap(),
+                roles: state_builder.new_map(),
+                timelock: Timelock::new(state_builder, Duration::from_days(2)),
+                lottery: Some(Lottery {
+                    commitment,
+                    seed: Some(seed),
+                    refundable,
+                    entries: 2,
+                }),
+                overflow: None,
+                refund_hook: None,
+                claim_hook: None,
+                escrow: None,
+                delivery_deadline: None,
+                ticket_key: None,
+                whitelist_root: None,
+                pool_funded: ContractTokenAmount::from(0),
+                pool_depositor: None,
+                token_balances: state_builder.new_map(),
+                claimed_tokens: ContractTokenAmount::from(0),
+                unclaimed_users: 0,
+                nonces: state_builder.new_map(),
+            }
+        };
+        let initial_state = state_with(&mut state_builder, unit_price, unit_price);
+        let expected_state = state_with(&mut state_builder, Amount::zero(), Amount::zero());
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.set_self_balance(unit_price * 2);
+
+        // winners have nothing to reclaim
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(winner));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(40));
+        let mut logger = TestLogger::init();
+        let result = contract_user_reclaim(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("userReclaim should reject");
+        claim_eq!(
+            err,
+            ContractError::Unauthorized,
+            "userReclaim should reject winners"
+        );
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(loser));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(40));
+        let mut logger = TestLogger::init();
+        let result = contract_user_reclaim(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(*host.state(), expected_state);
+        claim_eq!(
+            host.get_transfers(),
+            [(loser, unit_price)],
+            "the loser should be refunded in full"
+        );
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::Reclaimed(QuitEvent {
+                user: Address::Account(loser),
+                amount: unit_price,
+            }))],
+            "Reclaimed event should be logged."
+        );
+
+        // refunded only once
+        let mut logger = TestLogger::init();
+        let result = contract_user_reclaim(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("userReclaim should reject");
+        claim_eq!(
+            err,
+            CustomContractError::NotDeposited.into(),
+            "userReclaim should reject with NotDeposited"
+        );
+    }
+
+    #[concordium_test]
+    /// Test that every user can reclaim their deposit
+    /// once the seed has not been revealed in time, and the draw is off.
+    fn test_user_reclaim_reveal_overdue() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let first_user = AccountAddress([10u8; 32]);
+        let second_user = AccountAddress([11u8; 32]);
+        let unit_price = Amount::from_micro_ccd(5_000_000 * 200);
+        let seed: LotterySeed = [7u8; 32];
+        let close_at = Timestamp::from_timestamp_millis(30);
+        let deadline = close_at
+            .checked_add(Duration::from_days(REVEAL_PERIOD_DAYS))
+            .unwrap();
+
+        let mut participants = Participants::new(&mut state_builder);
+        for user in [first_user, second_user] {
+            participants.insert(
+                Address::Account(user),
+                UserState::new(Prior::TOP, unit_price, TARGET_UNITS),
+            );
+        }
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin: AccountAddress([1u8; 32]),
+            status: SaleStatus::Ready,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at,
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 1,
+                min_units: 1,
+                applied_units: 0,
+            },
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            lottery: Some(Lottery {
+                entries: 2,
+                ..Lottery::new(HashSha2256([1u8; 32]))
+            }),
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.set_self_balance(unit_price * 2);
+
+        // nobody can reclaim until the reveal is overdue
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(first_user));
+        ctx.set_metadata_slot_time(deadline);
+        let mut logger = TestLogger::init();
+        let result = contract_user_reclaim(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("userReclaim should reject");
+        claim_eq!(
+            err,
+            CustomContractError::NotDrawn.into(),
+            "userReclaim should reject with NotDrawn"
+        );
+
+        let after_deadline = deadline.checked_add(Duration::from_millis(1)).unwrap();
+
+        // the seed can no longer be revealed
+        let crypto_primitives = TestCryptoPrimitives::new();
+        let seed_bytes = to_bytes(&seed);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(after_deadline);
+        ctx.set_parameter(&seed_bytes);
+        let mut logger = TestLogger::init();
+        let result = contract_draw_lottery(&ctx, &mut host, &mut logger, &crypto_primitives);
+        let err = result.expect_err_report("drawLottery should reject");
+        claim_eq!(
+            err,
+            CustomContractError::RevealOverdue.into(),
+            "drawLottery should reject with RevealOverdue"
+        );
+
+        for user in [first_user, second_user] {
+            let mut ctx = TestReceiveContext::empty();
+            ctx.set_owner(admin);
+            ctx.set_sender(Address::Account(user));
+            ctx.set_metadata_slot_time(after_deadline);
+            let mut logger = TestLogger::init();
+            let result = contract_user_reclaim(&ctx, &mut host, &mut logger);
+            claim!(result.is_ok(), "Results in rejection");
+            claim_eq!(
+                logger.logs,
+                vec![to_bytes(&SaleEvent::Reclaimed(QuitEvent {
+                    user: Address::Account(user),
+                    amount: unit_price,
+                }))],
+                "Reclaimed event should be logged."
+            );
+        }
+        claim_eq!(
+            host.get_transfers(),
+            [(first_user, unit_price), (second_user, unit_price)],
+            "every user should be refunded in full"
+        );
+        claim_eq!(
+            host.state().get_lottery().map(|lottery| lottery.refundable),
+            Ok(Amount::zero()),
+            "nothing should be held as refundable"
+        );
+
+        // refunded only once
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(first_user));
+        ctx.set_metadata_slot_time(after_deadline);
+        let mut logger = TestLogger::init();
+        let result = contract_user_reclaim(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("userReclaim should reject");
+        claim_eq!(
+            err,
+            CustomContractError::NotDeposited.into(),
+            "userReclaim should reject with NotDeposited"
+        );
+    }
+
+    #[concordium_test]
+    /// Test that refundBatch pays back every deposit page by page when the sale is suspended,
+    /// even if the participant at the cursor quits between two pages.
+    fn test_refund_batch() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let anyone = AccountAddress([9u8; 32]);
+        let first_user = AccountAddress([10u8; 32]);
+        let second_user = AccountAddress([11u8; 32]);
+        let contract_user = ContractAddress {
+            index: 3000,
+            subindex: 0,
+        };
+        let deposit_amount = Amount::from_micro_ccd(5_000_000 * 200 * 1);
+
+        let mut participants = Participants::new(&mut state_builder);
+        for user in [
+            Address::Account(first_user),
+            Address::Account(second_user),
+            Address::Contract(contract_user),
+        ] {
+            participants.insert(user, UserState::new(Prior::TOP, deposit_amount, TARGET_UNITS));
+        }
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin: AccountAddress([1u8; 32]),
+            status: SaleStatus::Suspend,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 50,
+                applied_units: 3,
+            },
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            lottery: None,
+            overflow: None,
+            refund_hook: Some(OwnedEntrypointName::new_unchecked("refundHook".into())),
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.set_self_balance(deposit_amount * 3);
+        host.setup_mock_entrypoint(
+            contract_user,
+            OwnedEntrypointName::new_unchecked("refundHook".into()),
+            MockFn::new_v1(move |_parameter, amount, _balance, _state| {
+                claim_eq!(amount, deposit_amount, "the deposit should be attached");
+                Ok((false, ()))
+            }),
+        );
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(anyone));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(40));
+
+        // a page has to walk at least one participant
+        let params_byte = to_bytes(&RefundBatchParams {
+            cursor: None,
+            limit: 0,
+        });
+        ctx.set_parameter(&params_byte);
+        let mut logger = TestLogger::init();
+        let result = contract_refund_batch(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("refundBatch should reject an empty page");
+        claim_eq!(
+            err,
+            CustomContractError::InvalidPage.into(),
+            "refundBatch should reject an empty page"
+        );
+
+        // first page: the first user
+        let params_byte = to_bytes(&RefundBatchParams {
+            cursor: None,
+            limit: 1,
+        });
+        ctx.set_parameter(&params_byte);
+        let result = contract_refund_batch(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            result,
+            Ok(RefundBatchResponse {
+                refunded: 1,
+                next: Some(Address::Account(second_user)),
+            })
+        );
+        claim_eq!(
+            host.get_transfers(),
+            [(first_user, deposit_amount)],
+            "Something wrong with pay back logic."
+        );
+
+        // the participant at the cursor quits between the pages
+        let mut quit_ctx = TestReceiveContext::empty();
+        quit_ctx.set_owner(admin);
+        quit_ctx.set_sender(Address::Account(second_user));
+        let result = contract_user_quit(&quit_ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim_eq!(
+            host.state().remaining_refunds(),
+            (1, deposit_amount),
+            "only the contract should be left"
+        );
+
+        // second page: resumes after the participant who quit, the contract through the hook
+        let params_byte = to_bytes(&RefundBatchParams {
+            cursor: Some(Address::Account(second_user)),
+            limit: 2,
+        });
+        ctx.set_parameter(&params_byte);
+        let mut logger = TestLogger::init();
+        let result = contract_refund_batch(&ctx, &mut host, &mut logger);
+        claim_eq!(
+            result,
+            Ok(RefundBatchResponse {
+                refunded: 1,
+                next: None,
+            })
+        );
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::Refunded(QuitEvent {
+                user: Address::Contract(contract_user),
+                amount: deposit_amount,
+            }))],
+            "Refunded event should be logged."
+        );
+        claim_eq!(host.state().remaining_refunds(), (0, Amount::zero()));
+        claim_eq!(host.self_balance(), Amount::zero());
+
+        // refunded users cannot quit anymore
+        ctx.set_sender(Address::Account(first_user));
+        let mut logger = TestLogger::init();
+        let result = contract_user_quit(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("userQuit should reject");
+        claim_eq!(
+            err,
+            CustomContractError::AlreadyRefunded.into(),
+            "userQuit should reject with AlreadyRefunded"
+        );
+    }
+
+    #[concordium_test]
+    /// Test that lottery losers and overflow depositors refunded by refundBatch
+    /// cannot be paid again by userReclaim or userRefundExcess.
+    fn test_refund_batch_then_reclaim() {
+        let admin = AccountAddress([0u8; 32]);
+        let user = AccountAddress([10u8; 32]);
+        let deposit_amount = Amount::from_ccd(2_000);
+        let excess = Amount::from_ccd(1_000);
+
+        for is_lottery in [true, false] {
+            let mut state_builder = TestStateBuilder::new();
+            let (lottery, overflow, user_state) = if is_lottery {
+                let lottery = Lottery {
+                    commitment: HashSha2256([1u8; 32]),
+                    seed: Some([7u8; 32]),
+                    refundable: deposit_amount,
+                    entries: 1,
+                };
+                let user_state = UserState::new(Prior::TOP, deposit_amount, TARGET_UNITS);
+                (Some(lottery), None, user_state)
+            } else {
+                let overflow = Overflow {
+                    min_deposit: Amount::from_ccd(1),
+                    total_deposit: deposit_amount,
+                    allocated_tokens: ContractTokenAmount::from(200),
+                    refundable: excess,
+                    settled: true,
+                    next: 0,
+                };
+                let user_state = UserState {
+                    alloc_tokens: ContractTokenAmount::from(200),
+                    refundable_ccd: excess,
+                    ..UserState::new(Prior::TOP, deposit_amount, TARGET_UNITS)
+                };
+                (None, Some(overflow), user_state)
+            };
+            let mut participants = Participants::new(&mut state_builder);
+            participants.insert(Address::Account(user), user_state);
+            let initial_state = State {
+                version: STATE_VERSION,
+                proj_admin: AccountAddress([1u8; 32]),
+                status: SaleStatus::Suspend,
+                paused: false,
+                user_share: 9_000,
+                fees: BTreeMap::from([
+                    (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                    (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+                ]),
+                project_token: None,
+                schedule: SaleSchedule {
+                    open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                    close_at: Timestamp::from_timestamp_millis(30),
+                    vesting_start: None,
+                    vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                        Duration::from_days(1),
+                        10_000,
+                    )])),
+                },
+                saleinfo: SaleInfo {
+                    price_per_token: 5_000_000,
+                    token_per_unit: 200.into(),
+                    max_units: 100,
+                    min_units: 50,
+                    applied_units: 0,
+                },
+                tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+                participants,
+                multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+                roles: state_builder.new_map(),
+                timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+                lottery,
+                overflow,
+                refund_hook: None,
+                claim_hook: None,
+                escrow: None,
+                delivery_deadline: None,
+                ticket_key: None,
+                whitelist_root: None,
+                pool_funded: ContractTokenAmount::from(0),
+                pool_depositor: None,
+                token_balances: state_builder.new_map(),
+                claimed_tokens: ContractTokenAmount::from(0),
+                unclaimed_users: 0,
+                nonces: state_builder.new_map(),
+            };
+            let mut host = TestHost::new(initial_state, state_builder);
+            host.set_self_balance(deposit_amount);
+
+            let mut ctx = TestReceiveContext::empty();
+            ctx.set_owner(admin);
+            ctx.set_sender(Address::Account(user));
+            ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(40));
+            let params_byte = to_bytes(&RefundBatchParams {
+                cursor: None,
+                limit: 1,
+            });
+            ctx.set_parameter(&params_byte);
+            let mut logger = TestLogger::init();
+            let result = contract_refund_batch(&ctx, &mut host, &mut logger);
+            claim_eq!(
+                result,
+                Ok(RefundBatchResponse {
+                    refunded: 1,
+                    next: None,
+                })
+            );
+            claim_eq!(
+                host.get_transfers(),
+                [(user, deposit_amount)],
+                "the whole deposit should be refunded"
+            );
+            claim_eq!(
+                host.state().refundable(),
+                Amount::zero(),
+                "nothing should be held for the user anymore"
+            );
+
+            let mut logger = TestLogger::init();
+            let result = if is_lottery {
+                contract_user_reclaim(&ctx, &mut host, &mut logger)
+            } else {
+                contract_user_refund_excess(&ctx, &mut host, &mut logger)
+            };
+            let err = result.expect_err_report("the user should not be refunded twice");
+            claim_eq!(
+                err,
+                CustomContractError::AlreadyRefunded.into(),
+                "the user should not be refunded twice"
+            );
+        }
+    }
+
+    #[concordium_test]
+    /// Test that userRefundExcess refunds CCD which did not buy any token in overflow mode.
+    fn test_user_refund_excess() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let first_user = AccountAddress([10u8; 32]);
+        let deposit_amount = Amount::from_ccd(2_000);
+        let excess = Amount::from_ccd(1_000);
+
+        let state_with = |state_builder: &mut TestStateBuilder,
+                          deposit_ccd: Amount,
+                          refundable_ccd: Amount| {
+            let mut participants = Participants::new(state_builder);
+            participants.insert(
+                Address::Account(first_user),
+                UserState {
+                    prior: Prior::TOP,
+                    deposit_ccd,
+                    tgt_units: TARGET_UNITS,
+                    win_units: 0,
+                    claimed_inc: 0,
+                    alloc_tokens: ContractTokenAmount::from(200),
+                    refundable_ccd,
+                    refunded: false,
+                    claimed_amount: ContractTokenAmount::from(0),
+                    escrow_votes: 0,
+                },
+            );
+            State {
+                version: STATE_VERSION,
+                proj_admin: AccountAddress([1u8; 32]),
+                status: SaleStatus::Fixed,
+                paused: false,
+                user_share: 9_000,
+                fees: BTreeMap::from([
+                    (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                    (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+                ]),
+                project_token: None,
+                schedule: SaleSchedule {
+                    open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                    close_at: Timestamp::from_timestamp_millis(30),
+                    vesting_start: None,
+                    vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                        Duration::from_days(1),
+                        10_000,
+                    )])),
+                },
+                saleinfo: SaleInfo {
+                    price_per_token: 5_000_000,
+                    token_per_unit: 200.into(),
+                    max_units: 1,
+                    min_units: 1,
+                    applied_units: 1,
+                },
+                tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+                participants,
+                multisig: Multisig::new(state_builder, BTreeSet::from([admin]), 1).unwrap(),
+                roles: state_builder.new_map(),
+                timelock: Timelock::new(state_builder, Duration::from_days(2)),
+                lottery: None,
+                overflow: Some(Overflow {
+                    min_deposit: Amount::from_ccd(1),
+                    total_deposit: deposit_amount,
+                    allocated_tokens: ContractTokenAmount::from(200),
+                    refundable: refundable_ccd,
+                    settled: true,
+                    next: 0,
+                }),
+                refund_hook: None,
+                claim_hook: None,
+                escrow: None,
+                delivery_deadline: None,
+                ticket_key: None,
+                whitelist_root: None,
+                pool_funded: ContractTokenAmount::from(0),
+                pool_depositor: None,
+                token_balances: state_builder.new_map(),
+                claimed_tokens: ContractTokenAmount::from(0),
+                unclaimed_users: 0,
+                nonces: state_builder.new_map(),
+            }
+        };
+        let initial_state = state_with(&mut state_builder, deposit_amount, excess);
+        let expected_state =
+            state_with(&mut state_builder, deposit_amount - excess, Amount::zero());
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.set_self_balance(deposit_amount);
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(first_user));
+        let mut logger = TestLogger::init();
+        let result = contract_user_refund_excess(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(*host.state(), expected_state);
+        claim_eq!(
+            host.get_transfers(),
+            [(first_user, excess)],
+            "the excess should be refunded"
+        );
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::ExcessRefunded(QuitEvent {
+                user: Address::Account(first_user),
+                amount: excess,
+            }))],
+            "ExcessRefunded event should be logged."
+        );
+
+        // refunded only once
+        let mut logger = TestLogger::init();
+        let result = contract_user_refund_excess(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("userRefundExcess should reject");
+        claim_eq!(
+            err,
+            CustomContractError::AlreadyRefunded.into(),
+            "userRefundExcess should reject with AlreadyRefunded"
+        );
+    }
+
+    #[concordium_test]
+    /// Test that userQuit fails due to the function is currently disabled.
+    fn test_user_quit_fails_due_to_currently_disabled() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let first_user = AccountAddress([10u8; 32]);
+        let second_user = AccountAddress([11u8; 32]);
+        let deposit_amount = Amount::from_micro_ccd(5_000_000 * 200 * 1);
+        let project_token_address = ContractAddress {
+            index: 1000,
+            subindex: 0,
+        };
+        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
+        let addr_bbb = Address::Contract(ContractAddress {
+            index: 100,
+            subindex: 0,
+        });
+        let open_at = BTreeMap::from([
+            (Timestamp::from_timestamp_millis(10), Prior::TOP),
+            (Timestamp::from_timestamp_millis(20), Prior::SECOND),
+        ]);
+        let slot_time = Timestamp::from_timestamp_millis(15);
+        let close_at = Timestamp::from_timestamp_millis(30);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
+        let max_units = 100;
+        let min_units = 50;
+        let price_per_token = 5_000_000;
+        let token_per_unit = 200.into();
+
+        let whitelist = vec![
+            AllowedUserParams {
+                user: Address::Account(first_user),
+                prior: Prior::TOP,
+                tier: Tier::T0,
+            },
+            AllowedUserParams {
+                user: Address::Account(second_user),
+                prior: Prior::SECOND,
+                tier: Tier::T0,
+            },
+        ];
+        let mut participants = Participants::new(&mut state_builder);
+        for params in &whitelist {
+            if params.user == Address::Account(first_user) {
+                participants.insert(
+                    params.user,
+                    UserState {
+                        prior: params.prior.clone(),
+                        deposit_ccd: deposit_amount,
+                        tgt_units: TARGET_UNITS,
+                        win_units: 1,
+                        claimed_inc: 0,
+                        alloc_tokens: ContractTokenAmount::from(0),
+                        refundable_ccd: Amount::zero(),
+                        refunded: false,
+                        claimed_amount: ContractTokenAmount::from(0),
+                        escrow_votes: 0,
+                    },
+                );
+            } else {
+                participants.insert(
+                    params.user,
+                    UserState::new(params.prior.clone(), Amount::zero(), TARGET_UNITS),
+                );
+            }
+        }
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin,
+            status: SaleStatus::Ready,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
+            project_token: Some(project_token_address),
+            schedule: SaleSchedule {
+                open_at: open_at.clone(),
+                close_at,
+                vesting_start: None,
+                vesting_period: vesting_period.clone(),
+            },
+            saleinfo: SaleInfo {
+                price_per_token,
+                token_per_unit,
+                max_units,
+                min_units,
+                applied_units: 1,
+            },
+            participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.set_self_balance(deposit_amount);
+
+        // create params
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(first_user));
+        ctx.set_metadata_slot_time(slot_time);
+
+        // execute function
+        let mut logger = TestLogger::init();
+        let result = contract_user_quit(&ctx, &mut host, &mut logger);
+        claim!(result.is_err());
+        let err = result.expect_err_report("userQuit should reject");
+        claim_eq!(
+            err,
+            CustomContractError::DisabledForNow.into(),
+            "userQuit should reject with DisabledForNow"
+        );
+    }
+
+    #[concordium_test]
+    /// Test that userClaim successfully calculate total amount to claim & transfer it.
+    /// This also checks that userClaim can handle multiple user call.
+    fn test_user_claim() {
         let mut state_builder = TestStateBuilder::new();
         let self_address = ContractAddress::new(10, 0);
         let admin = AccountAddress([0u8; 32]);
@@ -655,11 +2349,11 @@ mod tests {
         let close_at = Timestamp::from_timestamp_millis(30);
         let vesting_start = Timestamp::from_timestamp_millis(50);
         let slot_time = Timestamp::from_timestamp_millis(80);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_millis(10), 25),
-            (Duration::from_millis(20), 40),
-            (Duration::from_millis(30), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_millis(10), 2_500),
+            (Duration::from_millis(20), 4_000),
+            (Duration::from_millis(30), 3_500),
+        ]));
         let deposit_amount = Amount::from_micro_ccd(5_000_000 * 200 * 1);
         let max_units = 1000;
         let min_units = 500;
@@ -672,17 +2366,20 @@ mod tests {
             AllowedUserParams {
                 user: Address::Account(first_user),
                 prior: Prior::TOP,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(second_user),
                 prior: Prior::SECOND,
+                tier: Tier::T0,
             },
             AllowedUserParams {
                 user: Address::Account(third_user),
                 prior: Prior::ANY,
+                tier: Tier::T0,
             },
         ];
-        let mut participants = state_builder.new_map();
+        let mut participants = Participants::new(&mut state_builder);
         for params in &whitelist {
             participants.insert(
                 params.user,
@@ -692,17 +2389,24 @@ mod tests {
                     tgt_units: TARGET_UNITS,
                     win_units: 1,
                     claimed_inc: 0,
+                    alloc_tokens: ContractTokenAmount::from(0),
+                    refundable_ccd: Amount::zero(),
+                    refunded: false,
+                    claimed_amount: ContractTokenAmount::from(0),
+                    escrow_votes: 0,
                 },
             );
         }
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Fixed,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -718,8 +2422,26 @@ mod tests {
                 applied_units,
             },
             participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(200 * 800),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
-        let mut expected_participants = state_builder.new_map();
+        let mut expected_participants = Participants::new(&mut state_builder);
         for params in &whitelist {
             if params.user == Address::Account(first_user) {
                 expected_participants.insert(
@@ -730,6 +2452,11 @@ mod tests {
                         tgt_units: TARGET_UNITS,
                         win_units: 1,
                         claimed_inc: 3,
+                        alloc_tokens: ContractTokenAmount::from(0),
+                        refundable_ccd: Amount::zero(),
+                        refunded: false,
+                        claimed_amount: ContractTokenAmount::from(180),
+                        escrow_votes: 0,
                     },
                 );
             } else {
@@ -741,18 +2468,25 @@ mod tests {
                         tgt_units: TARGET_UNITS,
                         win_units: 1,
                         claimed_inc: 0,
+                        alloc_tokens: ContractTokenAmount::from(0),
+                        refundable_ccd: Amount::zero(),
+                        refunded: false,
+                        claimed_amount: ContractTokenAmount::from(0),
+                        escrow_votes: 0,
                     },
                 );
             }
         }
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Fixed,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -768,6 +2502,24 @@ mod tests {
                 applied_units,
             },
             participants: expected_participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(200 * 800),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(180),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
         host.setup_mock_entrypoint(
@@ -797,7 +2549,8 @@ mod tests {
         ctx.set_metadata_slot_time(slot_time);
 
         // execute function
-        let result = contract_user_claim(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_user_claim(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(*host.state(), expected_state,);
 
@@ -809,8 +2562,262 @@ mod tests {
         ctx.set_metadata_slot_time(slot_time.checked_add(Duration::from_millis(1)).unwrap());
 
         // execute function
-        let result = contract_user_claim(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_user_claim(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(*host.state(), expected_state,);
     }
+
+    #[concordium_test]
+    /// Test that a user who has claimed part of their tokens can be refunded
+    /// after the escrow fails, but cannot claim the rest afterwards.
+    fn test_user_claim_then_escrow_refund() {
+        let mut state_builder = TestStateBuilder::new();
+        let self_address = ContractAddress::new(10, 0);
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let user = AccountAddress([10u8; 32]);
+        let project_token_address = ContractAddress {
+            index: 200,
+            subindex: 0,
+        };
+        let deposit_amount = Amount::from_micro_ccd(5_000_000 * 200);
+        let mut escrow = Escrow::new(EscrowParams {
+            tranches: vec![(Milestone::TgeSet, 10_000)],
+            vote_quorum: None,
+        })
+        .unwrap();
+        escrow.raised = deposit_amount;
+
+        let mut participants = Participants::new(&mut state_builder);
+        participants.insert(
+            Address::Account(user),
+            UserState {
+                win_units: 1,
+                ..UserState::new(Prior::TOP, deposit_amount, TARGET_UNITS)
+            },
+        );
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin,
+            status: SaleStatus::Fixed,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: Some(project_token_address),
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: Some(Timestamp::from_timestamp_millis(50)),
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([
+                    (Duration::from_millis(10), 2_500),
+                    (Duration::from_millis(20), 4_000),
+                    (Duration::from_millis(30), 3_500),
+                ])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 1,
+                applied_units: 1,
+            },
+            participants,
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: Some(escrow),
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(200),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.set_self_balance(deposit_amount);
+        host.setup_mock_entrypoint(
+            project_token_address,
+            OwnedEntrypointName::new_unchecked("transfer".into()),
+            MockFn::new_v1(|_parameter, _amount, _balance, _state| Ok((false, ()))),
+        );
+
+        // the first 25% of 180 tokens is claimed before the failure
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_self_address(self_address);
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(user));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(65));
+        let mut logger = TestLogger::init();
+        let result = contract_user_claim(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim_eq!(
+            host.state_mut()
+                .get_user(&Address::Account(user))
+                .unwrap()
+                .claimed_amount,
+            ContractTokenAmount::from(45)
+        );
+
+        ctx.set_sender(Address::Account(admin));
+        let result = contract_set_escrow_failed(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+
+        ctx.set_sender(Address::Account(user));
+        let result = contract_user_escrow_refund(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim_eq!(
+            host.get_transfers(),
+            [(user, deposit_amount)],
+            "the CCD not released should be refunded."
+        );
+
+        // the rest of the tokens cannot be claimed on top of the refund
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(80));
+        let mut logger = TestLogger::init();
+        let result = contract_user_claim(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("userClaim should reject once the escrow has failed");
+        claim_eq!(
+            err,
+            CustomContractError::EscrowFailed.into(),
+            "userClaim should reject once the escrow has failed"
+        );
+        claim_eq!(logger.logs.len(), 0);
+    }
+
+    #[concordium_test]
+    /// Test that permit rejects messages for another contract, expired or with a wrong nonce.
+    fn test_permit() {
+        let mut state_builder = TestStateBuilder::new();
+        let self_address = ContractAddress::new(10, 0);
+        let admin = AccountAddress([0u8; 32]);
+        let relayer = AccountAddress([9u8; 32]);
+        let user = AccountAddress([10u8; 32]);
+
+        let mut nonces = state_builder.new_map();
+        nonces.insert(user, 1);
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin: AccountAddress([1u8; 32]),
+            status: SaleStatus::Fixed,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 50,
+                applied_units: 0,
+            },
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces,
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        let crypto_primitives = TestCryptoPrimitives::new();
+
+        let message = PermitMessage {
+            contract_address: self_address,
+            nonce: 1,
+            timestamp: Timestamp::from_timestamp_millis(200),
+            entry_point: OwnedEntrypointName::new_unchecked("userClaim".into()),
+            payload: vec![],
+        };
+        for (message, error) in [
+            (
+                PermitMessage {
+                    contract_address: ContractAddress::new(11, 0),
+                    ..message.clone()
+                },
+                CustomContractError::InvalidPermit,
+            ),
+            (
+                PermitMessage {
+                    timestamp: Timestamp::from_timestamp_millis(100),
+                    ..message.clone()
+                },
+                CustomContractError::PermitExpired,
+            ),
+            (
+                PermitMessage {
+                    nonce: 0,
+                    ..message.clone()
+                },
+                CustomContractError::NonceMismatch,
+            ),
+        ] {
+            let params_byte = to_bytes(&PermitParam {
+                signature: AccountSignatures {
+                    sigs: BTreeMap::new(),
+                },
+                signer: user,
+                message,
+            });
+            let mut ctx = TestReceiveContext::empty();
+            ctx.set_self_address(self_address);
+            ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(100));
+            ctx.set_sender(Address::Account(relayer));
+            ctx.set_parameter(&params_byte);
+            let mut logger = TestLogger::init();
+            let result = contract_permit(&ctx, &mut host, &mut logger, &crypto_primitives);
+            let err = result.expect_err_report("permit should reject");
+            claim_eq!(err, error.into(), "permit should reject");
+        }
+        claim_eq!(
+            host.state().nonce_of(&user),
+            1,
+            "the nonce should not be used"
+        );
+
+        let event = SaleEvent::Nonce(NonceEvent {
+            account: user,
+            nonce: 1,
+        });
+        claim_eq!(
+            to_bytes(&event)[0],
+            250,
+            "CIS-3 requires the nonce event to be tagged 250"
+        );
+    }
 }
@@ -2,7 +2,8 @@ use concordium_std::concordium_cfg_test;
 
 #[concordium_cfg_test]
 mod tests {
-    use crate::*;
+    use crate::{participants::Participants, *};
+    use concordium_cis2::{AdditionalData, TokenIdUnit};
     use concordium_std::test_infrastructure::*;
 
     #[concordium_test]
@@ -25,23 +26,25 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
         let token_per_unit = 200.into();
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: None,
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -56,16 +59,36 @@ mod tests {
                 min_units,
                 applied_units: min_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address_to_be_set),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -80,7 +103,25 @@ mod tests {
                 min_units,
                 applied_units: min_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
 
@@ -92,7 +133,8 @@ mod tests {
         ctx.set_parameter(&params_byes);
 
         // execute func
-        let result = contract_set_pjtoken(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_set_pjtoken(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(
             *host.state(),
@@ -121,24 +163,26 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let vesting_start_to_be_set = Timestamp::from_timestamp_millis(100);
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
         let token_per_unit = 200.into();
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -153,16 +197,36 @@ mod tests {
                 min_units,
                 applied_units: min_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Ready,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -177,7 +241,25 @@ mod tests {
                 min_units,
                 applied_units: min_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
 
@@ -189,13 +271,141 @@ mod tests {
         ctx.set_parameter(&params_byes);
 
         // execute func
-        let result = contract_set_tge(&ctx, &mut host);
+        let mut logger = TestLogger::init();
+        let result = contract_set_tge(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(
             *host.state(),
             expected_state,
             "state has been changed unexpectedly..."
         );
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::TgeSet(vesting_start_to_be_set))],
+            "TgeSet event should be logged."
+        );
+    }
+
+    #[concordium_test]
+    /// Test that createPool records top-ups and claims wait until the pool is funded.
+    fn test_create_pool() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let project_token_address = ContractAddress {
+            index: 1000,
+            subindex: 0,
+        };
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin,
+            status: SaleStatus::Fixed,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: Some(project_token_address),
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: Some(Timestamp::from_timestamp_millis(50)),
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 50,
+                applied_units: 50,
+            },
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        let required = ContractTokenAmount::from(200 * 50);
+
+        let deposit = |host: &mut TestHost<State<TestStateApi>>, amount: u64| {
+            let params = OnReceivingCis2Params {
+                token_id: TokenIdUnit(),
+                amount: ContractTokenAmount::from(amount),
+                from: Address::Account(proj_admin),
+                data: AdditionalData::empty(),
+            };
+            let params_byte = to_bytes(&params);
+            let mut ctx = TestReceiveContext::empty();
+            ctx.set_owner(admin);
+            ctx.set_invoker(proj_admin);
+            ctx.set_sender(Address::Contract(project_token_address));
+            ctx.set_parameter(&params_byte);
+            let mut logger = TestLogger::init();
+            contract_create_pool(&ctx, host, &mut logger).map(|_| logger.logs)
+        };
+
+        // first deposit covers only a part of the tokens sold
+        let logs = deposit(&mut host, 4_000);
+        claim_eq!(
+            logs,
+            Ok(vec![to_bytes(&SaleEvent::PoolFunded(PoolFundedEvent {
+                from: Address::Account(proj_admin),
+                amount: ContractTokenAmount::from(4_000),
+                funded: ContractTokenAmount::from(4_000),
+                required,
+            }))])
+        );
+        claim_eq!(host.state().pool_depositor, Some(Address::Account(proj_admin)));
+        claim_eq!(host.state().is_pool_funded(), Ok(false));
+
+        // claims wait until the pool is funded
+        let params_bytes = to_bytes(&Address::Account(AccountAddress([2u8; 32])));
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(50));
+        ctx.set_parameter(&params_bytes);
+        let mut logger = TestLogger::init();
+        let result = contract_fee_claim(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("feeClaim should reject");
+        claim_eq!(
+            err,
+            CustomContractError::NotDeposited.into(),
+            "feeClaim should reject until the pool is funded"
+        );
+
+        // top-up
+        let logs = deposit(&mut host, 6_000);
+        claim!(logs.is_ok(), "Results in rejection");
+        claim_eq!(host.state().pool_funded, required);
+        claim_eq!(host.state().is_pool_funded(), Ok(true));
+
+        // no more than the tokens sold
+        let err = deposit(&mut host, 1).expect_err_report("createPool should reject");
+        claim_eq!(
+            err,
+            CustomContractError::NotMatchAmount.into(),
+            "createPool should reject tokens beyond the amount sold"
+        );
     }
 
     #[concordium_test]
@@ -219,23 +429,25 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(1), 25),
-            (Duration::from_days(2), 40),
-            (Duration::from_days(3), 35),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]));
         let max_units = 100;
         let min_units = 50;
         let price_per_token = 5_000_000;
         let token_per_unit = 200.into();
         let initial_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Fixed,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -250,16 +462,36 @@ mod tests {
                 min_units,
                 applied_units: min_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let expected_state = State {
+            version: STATE_VERSION,
             proj_admin,
             status: SaleStatus::Fixed,
             paused: false,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (addr_ovl, FeeState::new(500)),
+                (addr_bbb, FeeState::new(500)),
+            ]),
             project_token: Some(project_token_address),
             schedule: SaleSchedule {
                 open_at: open_at.clone(),
@@ -274,7 +506,25 @@ mod tests {
                 min_units,
                 applied_units: min_units,
             },
-            participants: state_builder.new_map(),
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         };
         let mut host = TestHost::new(initial_state, state_builder);
         host.set_self_balance(amount_to_claim);
@@ -283,9 +533,10 @@ mod tests {
         let mut ctx = TestReceiveContext::empty();
         ctx.set_owner(admin);
         ctx.set_sender(Address::Account(proj_admin));
+        let mut logger = TestLogger::init();
 
         // execute func
-        let result = contract_project_claim(&ctx, &mut host);
+        let result = contract_project_claim(&ctx, &mut host, &mut logger);
         claim!(result.is_ok());
         claim_eq!(
             *host.state(),
@@ -297,5 +548,224 @@ mod tests {
             [(proj_admin, amount_to_claim)],
             "Something wrong with project claim."
         );
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::CcdPaid(CcdPaidEvent {
+                to: Address::Account(proj_admin),
+                amount: amount_to_claim,
+            }))],
+            "CcdPaid event should be logged."
+        );
+    }
+
+    #[concordium_test]
+    /// Test that projectClaim splits CCD between the CCD fee recipients and the project admin.
+    fn test_project_claim_with_ccd_fees() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let ovl_account = AccountAddress([2u8; 32]);
+        let addr_ovl = Address::Account(ovl_account);
+        let bbb_contract = ContractAddress {
+            index: 100,
+            subindex: 0,
+        };
+        let addr_bbb = Address::Contract(bbb_contract);
+        let fee = |bps, ccd_bps| FeeState {
+            ccd_bps,
+            ..FeeState::new(bps)
+        };
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin,
+            status: SaleStatus::Fixed,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([(addr_ovl, fee(500, 300)), (addr_bbb, fee(500, 200))]),
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 50,
+                applied_units: 50,
+            },
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.set_self_balance(Amount::from_ccd(100));
+        host.setup_mock_entrypoint(
+            bbb_contract,
+            OwnedEntrypointName::new_unchecked("receiveCcdFee".into()),
+            MockFn::new_v1(move |_parameter, amount, _balance, _state| {
+                claim_eq!(
+                    amount,
+                    Amount::from_ccd(2),
+                    "the CCD fee should be attached"
+                );
+                Ok((false, ()))
+            }),
+        );
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(proj_admin));
+        let mut logger = TestLogger::init();
+
+        let result = contract_project_claim(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim_eq!(
+            host.get_transfers(),
+            [
+                (ovl_account, Amount::from_ccd(3)),
+                (proj_admin, Amount::from_ccd(95))
+            ],
+            "Something wrong with project claim."
+        );
+        claim_eq!(
+            logger.logs,
+            vec![
+                to_bytes(&SaleEvent::CcdPaid(CcdPaidEvent {
+                    to: addr_ovl,
+                    amount: Amount::from_ccd(3),
+                })),
+                to_bytes(&SaleEvent::CcdPaid(CcdPaidEvent {
+                    to: addr_bbb,
+                    amount: Amount::from_ccd(2),
+                })),
+                to_bytes(&SaleEvent::CcdPaid(CcdPaidEvent {
+                    to: Address::Account(proj_admin),
+                    amount: Amount::from_ccd(95),
+                })),
+            ],
+            "CcdPaid event should be logged for each payout."
+        );
+    }
+
+    #[concordium_test]
+    /// Test that projectClaim releases only the approved tranches whose milestone is reached.
+    fn test_project_claim_with_escrow() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let mut escrow = Escrow::new(EscrowParams {
+            tranches: vec![(Milestone::PoolFunded, 3_000), (Milestone::TgeSet, 7_000)],
+            vote_quorum: None,
+        })
+        .unwrap();
+        escrow.raised = Amount::from_ccd(4_000);
+        escrow.tranches[0].approved = true;
+        let initial_state = State {
+            version: STATE_VERSION,
+            proj_admin,
+            status: SaleStatus::Fixed,
+            paused: false,
+            user_share: 9_000,
+            fees: BTreeMap::from([
+                (Address::Account(AccountAddress([2u8; 32])), FeeState::new(500)),
+                (Address::Account(AccountAddress([3u8; 32])), FeeState::new(500)),
+            ]),
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 5_000_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 50,
+                applied_units: 50,
+            },
+            participants: Participants::new(&mut state_builder),
+            multisig: Multisig::new(&mut state_builder, BTreeSet::from([admin]), 1).unwrap(),
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(&mut state_builder, Duration::from_days(2)),
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            lottery: None,
+            overflow: None,
+            refund_hook: None,
+            claim_hook: None,
+            escrow: Some(escrow),
+            delivery_deadline: None,
+            ticket_key: None,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(10_000),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.set_self_balance(Amount::from_ccd(4_000));
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(proj_admin));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(40));
+        let mut logger = TestLogger::init();
+
+        let result = contract_project_claim(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim_eq!(
+            host.get_transfers(),
+            [(proj_admin, Amount::from_ccd(1_200))],
+            "only the first tranche should be released"
+        );
+        claim_eq!(
+            logger.logs,
+            vec![
+                to_bytes(&SaleEvent::TrancheReleased(0)),
+                to_bytes(&SaleEvent::CcdPaid(CcdPaidEvent {
+                    to: Address::Account(proj_admin),
+                    amount: Amount::from_ccd(1_200),
+                })),
+            ],
+            "TrancheReleased and CcdPaid events should be logged."
+        );
+
+        // the second tranche is neither approved nor has TGE been set
+        let mut logger = TestLogger::init();
+        let result = contract_project_claim(&ctx, &mut host, &mut logger);
+        let err =
+            result.expect_err_report("projectClaim should reject without a tranche to release");
+        claim_eq!(
+            err,
+            CustomContractError::MilestoneNotReached.into(),
+            "projectClaim should reject without a tranche to release"
+        );
     }
 }
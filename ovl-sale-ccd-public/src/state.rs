@@ -1,130 +1,182 @@
-use collections::BTreeMap;
+use crate::{
+    escrow::Escrow, fees::FeeState, lottery::Lottery, multisig::Multisig, overflow::Overflow,
+    participants::Participants, roles::Role, timelock::Timelock,
+};
+use collections::{BTreeMap, BTreeSet};
+use concordium_cis2::TokenIdVec;
 use concordium_std::{SchemaType, Serialize, *};
 pub use sale_utils::{
     error::{ContractError, ContractResult, CustomContractError},
+    sale::*,
     types::*,
 };
+use sale_utils::{
+    merkle::MerkleRoot, rido::RidoState, token::DEFAULT_RECEIVE_HOOK, BPS_DENOMINATOR,
+};
 
-/// All participants can purchase only 1 unit.
+/// Units every participant could purchase before tiers were introduced.
+/// Instances migrated from v0 give this to `Tier::T0`.
 pub const TARGET_UNITS: u8 = 1;
 
+/// Version of the current `State` layout.
+/// Bump this whenever the layout changes and add a migration from the previous one.
+pub const STATE_VERSION: u16 = 2;
+
 /// The contract state
 #[derive(Debug, Serial, DeserialWithState, StateClone)]
 #[concordium(state_parameter = "S")]
 pub struct State<S: HasStateApi> {
+    /// Layout version of this state, see `STATE_VERSION`
+    pub(crate) version: u16,
     /// Account of the administrator of the entity running the IDO
     pub(crate) proj_admin: AccountAddress,
     /// Enum for sale status
     pub(crate) status: SaleStatus,
     /// If `true`, some functions will stop working
     pub(crate) paused: bool,
-    /// Address of Overlay for receiving sale fee
-    pub(crate) addr_ovl: Address,
-    /// Address of Overlay for buy back burn
-    pub(crate) addr_bbb: Address,
-    /// Number of how many fee received
-    pub(crate) ovl_claimed_inc: u8,
-    /// Number of how many fee for BBB received
-    pub(crate) bbb_claimed_inc: u8,
+    /// Users' share of the tokens sold, in basis points
+    pub(crate) user_share: BasisPoints,
+    /// Recipients of the sale fee(e.g. Overlay and buy back burn) and their claim progress
+    pub(crate) fees: BTreeMap<Address, FeeState>,
     /// Project token contract address for RIDO
     pub(crate) project_token: Option<ContractAddress>,
     /// Sale schedule
     pub(crate) schedule: SaleSchedule,
     /// Information about sale
     pub(crate) saleinfo: SaleInfo,
+    /// Number of units each tier can purchase
+    pub(crate) tier_units: BTreeMap<Tier, u8>,
     /// Sale participants
-    pub(crate) participants: StateMap<Address, UserState, S>,
+    pub(crate) participants: Participants<S>,
+    /// Signers and pending proposals for emergency admin actions
+    pub(crate) multisig: Multisig<S>,
+    /// Roles granted to addresses besides the contract instance owner
+    pub(crate) roles: StateMap<Address, BTreeSet<Role>, S>,
+    /// Approved changes of TGE and project token waiting for their delay
+    pub(crate) timelock: Timelock<S>,
+    /// If set, units are allocated by lottery instead of first-come-first-served
+    pub(crate) lottery: Option<Lottery>,
+    /// If set, tokens are allocated pro-rata to the deposits instead of first-come-first-served
+    pub(crate) overflow: Option<Overflow>,
+    /// Entrypoint through which contract participants receive their refunds
+    pub(crate) refund_hook: Option<OwnedEntrypointName>,
+    /// Entrypoint through which contract participants receive claimed tokens(`callback` if unset)
+    pub(crate) claim_hook: Option<OwnedEntrypointName>,
+    /// If set, the raised CCD is released to the project in tranches tied to milestones
+    pub(crate) escrow: Option<Escrow>,
+    /// If set, the sale can be declared failed when the project has not delivered by this time
+    pub(crate) delivery_deadline: Option<Timestamp>,
+    /// If set, users can deposit with an allocation ticket signed by this backend key
+    /// instead of being whitelisted on-chain
+    pub(crate) ticket_key: Option<PublicKeyEd25519>,
+    /// If set, users can deposit with a proof of membership of the whitelist committed to
+    /// instead of being whitelisted on-chain
+    pub(crate) whitelist_root: Option<MerkleRoot>,
+    /// Project tokens deposited to the pool by `createPool` so far
+    pub(crate) pool_funded: ContractTokenAmount,
+    /// Address which last deposited project tokens to the pool
+    pub(crate) pool_depositor: Option<Address>,
+    /// CIS-2 tokens held by this contract per (contract, token id)
+    pub(crate) token_balances: StateMap<(ContractAddress, TokenIdVec), ContractTokenAmount, S>,
+    /// Project tokens paid out for the user and fee claims so far
+    pub(crate) claimed_tokens: ContractTokenAmount,
+    /// Number of users who have purchased tokens and not yet claimed all of them
+    pub(crate) unclaimed_users: u32,
+    /// Next nonce of each account signing messages for `permit`
+    pub(crate) nonces: StateMap<AccountAddress, u64, S>,
 }
 
 impl<S: HasStateApi> State<S> {
     pub(crate) fn new(
         state_builder: &mut StateBuilder<S>,
         proj_admin: AccountAddress,
-        addr_ovl: Address,
-        addr_bbb: Address,
+        user_share: BasisPoints,
+        fees: BTreeMap<Address, BasisPoints>,
+        ccd_fees: BTreeMap<Address, BasisPoints>,
         schedule: SaleSchedule,
         saleinfo: SaleInfo,
+        tier_units: BTreeMap<Tier, u8>,
+        multisig: Multisig<S>,
+        timelock_delay: Duration,
+        lottery: Option<Lottery>,
+        overflow: Option<Overflow>,
+        escrow: Option<Escrow>,
+        delivery_deadline: Option<Timestamp>,
+        ticket_key: Option<PublicKeyEd25519>,
     ) -> Self {
+        let mut fees: BTreeMap<Address, FeeState> = fees
+            .into_iter()
+            .map(|(recipient, bps)| (recipient, FeeState::new(bps)))
+            .collect();
+        for (recipient, ccd_bps) in ccd_fees {
+            fees.entry(recipient)
+                .or_insert_with(|| FeeState::new(0))
+                .ccd_bps = ccd_bps;
+        }
+
         State {
+            version: STATE_VERSION,
             proj_admin,
             paused: false,
             status: SaleStatus::Prepare,
-            addr_ovl,
-            addr_bbb,
-            ovl_claimed_inc: 0,
-            bbb_claimed_inc: 0,
+            user_share,
+            fees,
             project_token: None,
             schedule,
             saleinfo,
-            participants: state_builder.new_map(),
+            tier_units,
+            participants: Participants::new(state_builder),
+            multisig,
+            roles: state_builder.new_map(),
+            timelock: Timelock::new(state_builder, timelock_delay),
+            lottery,
+            overflow,
+            refund_hook: None,
+            claim_hook: None,
+            escrow,
+            delivery_deadline,
+            ticket_key,
+            whitelist_root: None,
+            pool_funded: ContractTokenAmount::from(0),
+            pool_depositor: None,
+            token_balances: state_builder.new_map(),
+            claimed_tokens: ContractTokenAmount::from(0),
+            unclaimed_users: 0,
+            nonces: state_builder.new_map(),
         }
     }
 
-    // TODO should we remove &mut (should not be self mutable function)
-    pub(crate) fn calc_vesting_amount(
-        &mut self,
-        now: Timestamp,
-        vesting_start: Timestamp,
-        total_units: u64,
-        shared: u8,
-        cur_inc: u8,
-    ) -> ContractResult<(ContractTokenAmount, u8)> {
-        let mut amount: u128 = 0;
-        let mut inc: u8 = 0;
-
-        for (duration, per) in self.schedule.vesting_period.iter() {
-            let ts = match vesting_start.checked_add(*duration) {
-                Some(v) => v,
-                None => bail!(CustomContractError::InvalidSchedule.into()),
-            };
-
-            if now < ts {
-                break;
-            }
-
-            if cur_inc > inc {
-                inc += 1;
-                continue;
-            }
-
-            let total_amount: u128 = (self.saleinfo.token_per_unit.0 as u128)
-                .checked_mul(u128::from(total_units))
-                .ok_or(ContractError::from(CustomContractError::OverflowError))?;
-
-            let total_claimable: u128 = total_amount
-                .checked_mul(u128::from(shared))
-                .ok_or(ContractError::from(CustomContractError::OverflowError))?
-                / 100;
-
-            let allocation: u128 = total_claimable
-                .checked_mul(u128::from(*per as u128))
-                .ok_or(ContractError::from(CustomContractError::OverflowError))?
-                / 100;
-
-            amount += allocation;
-            inc += 1;
-        }
+    /// Number of units users in the tier can purchase.
+    pub(crate) fn units_of(&self, tier: &Tier) -> Result<u8, CustomContractError> {
+        self.tier_units
+            .get(tier)
+            .copied()
+            .ok_or(CustomContractError::UnknownTier)
+    }
 
-        if amount > u64::MAX as u128 {
-            bail!(ContractError::from(CustomContractError::OverflowError))
-        } else {
-            Ok((ContractTokenAmount::from(amount as u64), inc))
-        }
+    pub(crate) fn whitelisting(
+        &mut self,
+        user: &Address,
+        prior: Prior,
+        tier: &Tier,
+    ) -> Result<u8, CustomContractError> {
+        let tgt_units = self.units_of(tier)?;
+        self.allow_user(user, prior, tgt_units);
+        Ok(tgt_units)
     }
 
-    pub(crate) fn whitelisting(&mut self, user: &Address, prior: Prior) {
+    /// Puts a user on the whitelist, unless they are already on it.
+    pub(crate) fn allow_user(&mut self, user: &Address, prior: Prior, tgt_units: u8) {
         self.participants
-            .entry(*user)
-            .or_insert_with(|| UserState::new(prior, Amount::zero(), TARGET_UNITS));
+            .get_or_insert_with(*user, || UserState::new(prior, Amount::zero(), tgt_units));
     }
 
+    /// Users who are not on the whitelist purchase as `Tier::T0`.
     pub(crate) fn get_user_any(&mut self, user: &Address) -> ContractResult<UserState> {
-        let user = self
-            .participants
-            .entry(*user)
-            .or_insert_with(|| UserState::new(Prior::ANY, Amount::zero(), TARGET_UNITS));
-        let user = user.get_ref();
-        Ok(user.clone())
+        let tgt_units = self.units_of(&Tier::T0)?;
+        Ok(self.participants.get_or_insert_with(*user, || {
+            UserState::new(Prior::ANY, Amount::zero(), tgt_units)
+        }))
     }
 
     // fn modify_whitelist(
@@ -143,7 +195,7 @@ impl<S: HasStateApi> State<S> {
 
     #[allow(dead_code)]
     pub(crate) fn check_listed(&mut self, user: &Address) -> bool {
-        self.participants.entry(*user).is_occupied()
+        self.participants.get(user).is_some()
     }
 
     // TODO should we remove &mut (should not be self mutable function)
@@ -155,6 +207,62 @@ impl<S: HasStateApi> State<S> {
         Ok(user.clone())
     }
 
+    /// Claim progress of a user, `None` if they have no token to claim.
+    fn claim_progress(&self, user: &UserState) -> ContractResult<Option<ClaimProgress>> {
+        let claimable = self
+            .tokens_of(user)?
+            .checked_mul(u128::from(self.user_share))
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?
+            / u128::from(BPS_DENOMINATOR);
+        let claimed = u128::from(user.claimed_amount.0);
+        Ok(if claimable == 0 {
+            None
+        } else if claimed == 0 {
+            Some(ClaimProgress::NotStarted)
+        } else if claimed < claimable {
+            Some(ClaimProgress::InProgress)
+        } else {
+            Some(ClaimProgress::Completed)
+        })
+    }
+
+    fn matches(&self, user: &UserState, filter: &ParticipantFilter) -> ContractResult<bool> {
+        if filter
+            .prior
+            .as_ref()
+            .map_or(false, |prior| user.prior != *prior)
+        {
+            return Ok(false);
+        }
+        let deposited = user.deposit_ccd > Amount::zero();
+        if filter.deposited.map_or(false, |wanted| deposited != wanted) {
+            return Ok(false);
+        }
+        match &filter.claim {
+            Some(claim) => Ok(self.claim_progress(user)?.as_ref() == Some(claim)),
+            None => Ok(true),
+        }
+    }
+
+    /// Participants matching `filter` in the page starting from `cursor`,
+    /// and the cursor of the next page.
+    /// `limit` bounds the participants walked rather than those matched, so a page may be empty.
+    pub(crate) fn participants_page(
+        &self,
+        cursor: Option<Address>,
+        limit: u32,
+        filter: &ParticipantFilter,
+    ) -> ContractResult<(Vec<(Address, UserState)>, Option<Address>)> {
+        let (page, next) = self.participants.page(cursor, limit)?;
+        let mut matched: Vec<(Address, UserState)> = Vec::new();
+        for (addr, user) in page {
+            if self.matches(&user, filter)? {
+                matched.push((addr, user));
+            }
+        }
+        Ok((matched, next))
+    }
+
     pub(crate) fn deposit(
         &mut self,
         user: &Address,
@@ -167,17 +275,25 @@ impl<S: HasStateApi> State<S> {
             .ok_or(ContractError::Unauthorized)?;
         user.deposit_ccd = amount;
         user.win_units = win_units;
+        drop(user);
 
         self.saleinfo.applied_units += win_units as UnitsAmount;
-        Ok(())
+        let tokens = self.tokens_of_units(win_units as u64)?;
+        self.count_claimant(tokens)
     }
 
-    pub(crate) fn increment_user_claimed(&mut self, user: &Address, n: u8) -> ContractResult<()> {
+    pub(crate) fn increment_user_claimed(
+        &mut self,
+        user: &Address,
+        n: u8,
+        amount: ContractTokenAmount,
+    ) -> ContractResult<()> {
         let mut user = self
             .participants
             .get_mut(user)
             .ok_or(ContractError::Unauthorized)?;
         user.claimed_inc = n;
+        user.claimed_amount += amount;
         Ok(())
     }
 
@@ -185,6 +301,105 @@ impl<S: HasStateApi> State<S> {
         self.participants.remove(user);
         self.saleinfo.applied_units -= tgt_units as UnitsAmount;
     }
+
+    /// Records project tokens deposited to the pool and returns the total funded so far.
+    /// The pool can be topped up until it holds all the tokens sold.
+    pub(crate) fn fund_pool(
+        &mut self,
+        from: Address,
+        amount: ContractTokenAmount,
+    ) -> ContractResult<ContractTokenAmount> {
+        let required = self.sold_tokens()?;
+        let funded = self
+            .pool_funded
+            .0
+            .checked_add(amount.0)
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+        ensure!(
+            funded <= required.0,
+            CustomContractError::NotMatchAmount.into()
+        );
+
+        self.pool_funded = ContractTokenAmount::from(funded);
+        self.pool_depositor = Some(from);
+        Ok(self.pool_funded)
+    }
+
+    /// Whether the pool holds all the tokens sold, so that claims can start.
+    pub(crate) fn is_pool_funded(&self) -> Result<bool, CustomContractError> {
+        Ok(self.pool_funded.0 >= self.sold_tokens()?.0)
+    }
+
+    /// Whether the project has set TGE and funded the pool with all the tokens sold.
+    pub(crate) fn is_delivered(&self) -> Result<bool, CustomContractError> {
+        Ok(self.schedule.vesting_start.is_some() && self.is_pool_funded()?)
+    }
+
+    /// Whether the delivery deadline has passed without the project delivering.
+    pub(crate) fn is_delivery_overdue(&self, now: Timestamp) -> Result<bool, CustomContractError> {
+        match self.delivery_deadline {
+            Some(deadline) if now > deadline => Ok(!self.is_delivered()?),
+            _ => Ok(false),
+        }
+    }
+
+    /// Entrypoint through which contracts receive claimed tokens.
+    pub(crate) fn claim_hook(&self) -> OwnedEntrypointName {
+        self.claim_hook
+            .clone()
+            .unwrap_or_else(|| OwnedEntrypointName::new_unchecked(DEFAULT_RECEIVE_HOOK.to_owned()))
+    }
+
+    /// CCD held for refunds, which must not be paid to the project.
+    pub(crate) fn refundable(&self) -> Amount {
+        let lottery = self
+            .lottery
+            .as_ref()
+            .map_or(Amount::zero(), |lottery| lottery.refundable);
+        let overflow = self
+            .overflow
+            .as_ref()
+            .map_or(Amount::zero(), |overflow| overflow.refundable);
+        let escrow = self
+            .escrow
+            .as_ref()
+            .map_or(Amount::zero(), |escrow| escrow.refundable);
+        lottery + overflow + escrow
+    }
+}
+
+impl<S: HasStateApi> RidoState for State<S> {
+    fn proj_admin(&self) -> AccountAddress {
+        self.proj_admin
+    }
+
+    fn status(&self) -> &SaleStatus {
+        &self.status
+    }
+
+    fn paused(&self) -> bool {
+        self.paused
+    }
+
+    fn paused_mut(&mut self) -> &mut bool {
+        &mut self.paused
+    }
+
+    fn project_token(&self) -> Option<ContractAddress> {
+        self.project_token
+    }
+
+    fn project_token_mut(&mut self) -> &mut Option<ContractAddress> {
+        &mut self.project_token
+    }
+
+    fn schedule(&self) -> &SaleSchedule {
+        &self.schedule
+    }
+
+    fn schedule_mut(&mut self) -> &mut SaleSchedule {
+        &mut self.schedule
+    }
 }
 
 #[cfg(any(feature = "wasm-test", test))]
@@ -193,6 +408,9 @@ impl<S: HasStateApi> State<S> {
 /// (e.g. when launched by `cargo concordium test`)
 impl<S: HasStateApi> PartialEq for State<S> {
     fn eq(&self, other: &Self) -> bool {
+        if self.version != other.version {
+            return false;
+        }
         if self.proj_admin != other.proj_admin {
             return false;
         }
@@ -202,215 +420,113 @@ impl<S: HasStateApi> PartialEq for State<S> {
         if self.paused != other.paused {
             return false;
         }
-        if self.addr_ovl != other.addr_ovl {
+        if self.user_share != other.user_share || self.fees != other.fees {
             return false;
         }
-        if self.addr_bbb != other.addr_bbb {
+        if self.project_token != other.project_token {
             return false;
         }
-        if self.ovl_claimed_inc != other.ovl_claimed_inc {
+        if self.schedule != other.schedule {
             return false;
         }
-        if self.bbb_claimed_inc != other.bbb_claimed_inc {
+        if self.saleinfo != other.saleinfo {
             return false;
         }
-        if self.project_token != other.project_token {
+        if self.tier_units != other.tier_units {
             return false;
         }
-        if self.schedule != other.schedule {
+        if self.participants != other.participants {
             return false;
         }
-        if self.saleinfo != other.saleinfo {
+        if self.multisig != other.multisig {
             return false;
         }
-        if self.participants.iter().count() != other.participants.iter().count() {
+        if self.roles.iter().count() != other.roles.iter().count() {
             return false;
         }
-        for (my_user_address, my_user_state) in self.participants.iter() {
-            let other_user_state = other.participants.get(&my_user_address);
-            if other_user_state.is_none() {
-                return false;
-            }
-            let other_user_state = other_user_state.unwrap();
-            if my_user_state.clone() != other_user_state.clone() {
-                return false;
+        for (address, roles) in self.roles.iter() {
+            match other.roles.get(&address) {
+                Some(other_roles) if roles.clone() == other_roles.clone() => {},
+                _ => return false,
             }
         }
-        true
-    }
-
-    fn ne(&self, other: &Self) -> bool {
-        !self.eq(other)
-    }
-}
-
-/// Sale Schedule
-#[derive(Debug, Serialize, SchemaType, Clone)]
-#[cfg_attr(any(feature = "wasm-test", test), derive(PartialEq))]
-pub struct SaleSchedule {
-    /// IDO schedule(The process is split into some phases)
-    pub(crate) open_at: BTreeMap<Timestamp, Prior>,
-    /// Sale End Time
-    pub(crate) close_at: Timestamp,
-    /// Actual vesting_period is calculated based on this start time
-    pub(crate) vesting_start: Option<Timestamp>,
-    /// User(sale particicants) can withdraw assets according to the vesting period
-    pub(crate) vesting_period: BTreeMap<Duration, AllowedPercentage>,
-}
-
-impl SaleSchedule {
-    pub fn new(
-        now: Timestamp,
-        open_at: BTreeMap<Timestamp, Prior>,
-        close_at: Timestamp,
-        vesting_period: BTreeMap<Duration, AllowedPercentage>,
-    ) -> Result<Self, CustomContractError> {
-        ensure!(!open_at.is_empty(), CustomContractError::InvalidSchedule);
-
-        ensure!(
-            now < *open_at.first_key_value().unwrap().0,
-            CustomContractError::InvalidSchedule
-        );
-
-        ensure!(
-            *open_at.last_key_value().unwrap().0 < close_at,
-            CustomContractError::InvalidSchedule
-        );
-
-        // check vesting_period
-        let mut total_per = 0;
-        for (_, per) in vesting_period.iter() {
-            total_per += *per;
+        if self.timelock != other.timelock {
+            return false;
         }
-        ensure!(total_per == 100, CustomContractError::Inappropriate);
-
-        Ok(SaleSchedule {
-            open_at,
-            close_at,
-            vesting_start: None,
-            vesting_period,
-        })
-    }
-
-    #[allow(dead_code)]
-    pub(crate) fn is_sale_opened(&self, now: Timestamp) -> bool {
-        if now < *self.open_at.first_key_value().unwrap().0 {
-            true
-        } else {
-            false
+        if self.lottery != other.lottery {
+            return false;
         }
-    }
-
-    pub(crate) fn is_sale_closed(&self, now: Timestamp) -> bool {
-        if self.close_at < now {
-            true
-        } else {
-            false
+        if self.overflow != other.overflow {
+            return false;
         }
-    }
-
-    pub(crate) fn is_on_sale(&self, now: Timestamp) -> bool {
-        if *self.open_at.first_key_value().unwrap().0 <= now && now < self.close_at {
-            true
-        } else {
-            false
+        if self.refund_hook != other.refund_hook || self.claim_hook != other.claim_hook {
+            return false;
         }
-    }
-
-    pub(crate) fn check_sale_priority(&self, now: Timestamp) -> Option<Prior> {
-        if !self.is_on_sale(now) {
-            return None;
+        if self.escrow != other.escrow {
+            return false;
         }
-
-        let mut current = Prior::TOP;
-        for (ts, priority) in self.open_at.iter() {
-            if priority == &current {
-                continue;
-            }
-            if now < *ts {
-                return Some(current);
-            } else {
-                current = priority.clone();
+        if self.delivery_deadline != other.delivery_deadline {
+            return false;
+        }
+        if self.ticket_key != other.ticket_key || self.whitelist_root != other.whitelist_root {
+            return false;
+        }
+        if self.pool_funded != other.pool_funded || self.pool_depositor != other.pool_depositor {
+            return false;
+        }
+        if self.token_balances.iter().count() != other.token_balances.iter().count() {
+            return false;
+        }
+        for (key, balance) in self.token_balances.iter() {
+            match other.token_balances.get(&key) {
+                Some(other_balance) if *balance == *other_balance => {},
+                _ => return false,
             }
         }
-        return Some(current);
-    }
-}
-
-/// Information about sale
-#[derive(Debug, Serialize, SchemaType, Clone)]
-#[cfg_attr(any(feature = "wasm-test", test), derive(PartialEq))]
-pub struct SaleInfo {
-    /// Price in ccd per a project token
-    pub(crate) price_per_token: MicroCcd,
-    /// Amount of tokens contained in a unit
-    pub(crate) token_per_unit: ContractTokenAmount,
-    /// Maximum quantity to be issued in this sale
-    pub(crate) max_units: UnitsAmount,
-    /// Minimum quantity to be issued for this sale
-    pub(crate) min_units: UnitsAmount,
-    /// Amount of sales completed at that point
-    pub(crate) applied_units: UnitsAmount,
-}
-
-impl SaleInfo {
-    pub(crate) fn new(
-        price_per_token: MicroCcd,
-        token_per_unit: ContractTokenAmount,
-        max_units: UnitsAmount,
-        min_units: UnitsAmount,
-    ) -> Result<Self, CustomContractError> {
-        ensure!(min_units < max_units, CustomContractError::Inappropriate);
-
-        // Price_per_unit must not exceed 18_446_744_073_709_551_615
-        if price_per_token.checked_mul(token_per_unit.0).is_none() {
-            bail!(CustomContractError::OverflowError);
-        }
-
-        Ok(SaleInfo {
-            price_per_token,
-            token_per_unit,
-            max_units,
-            min_units,
-            applied_units: 0,
-        })
-    }
-
-    pub(crate) fn check_room_to_apply(&self) -> UnitsAmount {
-        if self.applied_units < self.max_units {
-            self.max_units - self.applied_units
-        } else {
-            0
+        if self.claimed_tokens != other.claimed_tokens
+            || self.unclaimed_users != other.unclaimed_users
+        {
+            return false;
         }
-    }
-
-    pub(crate) fn is_reached_sc(&self) -> bool {
-        if self.min_units <= self.applied_units {
-            true
-        } else {
-            false
+        if self.nonces.iter().count() != other.nonces.iter().count() {
+            return false;
         }
-    }
-
-    pub(crate) fn amount_of_pjtoken(&self) -> Result<ContractTokenAmount, CustomContractError> {
-        let token_amount = self.token_per_unit.0.checked_mul(self.applied_units as u64);
-        if token_amount.is_none() {
-            bail!(CustomContractError::OverflowError);
+        for (account, nonce) in self.nonces.iter() {
+            match other.nonces.get(&account) {
+                Some(other_nonce) if *nonce == *other_nonce => {},
+                _ => return false,
+            }
         }
-        Ok(ContractTokenAmount::from(token_amount.unwrap()))
+        true
     }
 
-    pub(crate) fn calc_price_per_unit(&self) -> Result<Amount, CustomContractError> {
-        // Price_per_unit must not exceed 18_446_744_073_709_551_615
-        let price = self.price_per_token.checked_mul(self.token_per_unit.0);
-        if price.is_none() {
-            bail!(CustomContractError::OverflowError);
-        }
-        let price = price.unwrap();
-        Ok(Amount::from_micro_ccd(price))
+    fn ne(&self, other: &Self) -> bool {
+        !self.eq(other)
     }
 }
 
+/// Progress of the claims of a participant who has purchased tokens.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub enum ClaimProgress {
+    /// Nothing claimed yet
+    NotStarted,
+    /// Part of the tokens claimed
+    InProgress,
+    /// Every token claimed
+    Completed,
+}
+
+/// Filters of participants, each matching every participant if `None`.
+#[derive(Debug, Serialize, SchemaType, Clone, Default)]
+pub struct ParticipantFilter {
+    /// Priority of the participants
+    pub prior: Option<Prior>,
+    /// Whether the participants have deposited
+    pub deposited: Option<bool>,
+    /// Claim progress of the participants, who must have purchased tokens
+    pub claim: Option<ClaimProgress>,
+}
+
 /// About sale participants
 #[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
 pub struct UserState {
@@ -424,6 +540,16 @@ pub struct UserState {
     pub(crate) win_units: u8,
     /// Number of tokens received during the vesting period(neither Amount or number of claim)
     pub(crate) claimed_inc: u8,
+    /// Project tokens allocated pro-rata(overflow mode only)
+    pub(crate) alloc_tokens: ContractTokenAmount,
+    /// Excess CCD to be refunded(overflow mode only)
+    pub(crate) refundable_ccd: Amount,
+    /// Whether the deposit has been refunded because the sale was suspended
+    pub(crate) refunded: bool,
+    /// Amount of tokens received during the vesting period
+    pub(crate) claimed_amount: ContractTokenAmount,
+    /// Tranches of the escrow the user has voted for, as a bitmap of their indexes
+    pub(crate) escrow_votes: u64,
 }
 
 impl UserState {
@@ -434,6 +560,11 @@ impl UserState {
             tgt_units,
             win_units: 0,
             claimed_inc: 0,
+            alloc_tokens: ContractTokenAmount::from(0),
+            refundable_ccd: Amount::zero(),
+            refunded: false,
+            claimed_amount: ContractTokenAmount::from(0),
+            escrow_votes: 0,
         }
     }
 }
@@ -442,10 +573,11 @@ impl UserState {
 mod tests {
     use super::*;
     use crate::test_infrastructure::*;
-    use crate::InitParams;
+    use crate::{escrow::*, fees::*, multisig::Multisig, InitParams};
     #[allow(unused)]
     use sale_utils::{PUBLIC_RIDO_FEE, PUBLIC_RIDO_FEE_BBB, PUBLIC_RIDO_FEE_OVL};
 
+    const ADMIN_ACC: AccountAddress = AccountAddress([0u8; 32]);
     const PJ_ADMIN_ACC: AccountAddress = AccountAddress([1u8; 32]);
     const ADDR_OVL: Address = Address::Account(AccountAddress([2u8; 32]));
     const ADDR_BBB: Address = Address::Contract(ContractAddress {
@@ -460,10 +592,20 @@ mod tests {
     const USER3_ADDR: Address = Address::Account(USER3_ACC);
 
     fn init_parameter(vesting_period: BTreeMap<Duration, AllowedPercentage>) -> InitParams {
+        let vesting_period = if vesting_period.is_empty() {
+            BTreeMap::from([
+                (Duration::from_days(1), 2_500),
+                (Duration::from_days(2), 4_000),
+                (Duration::from_days(3), 3_500),
+            ])
+        } else {
+            vesting_period
+        };
         InitParams {
             proj_admin: PJ_ADMIN_ACC,
-            addr_ovl: ADDR_OVL,
-            addr_bbb: ADDR_BBB,
+            user_share: 9_000,
+            fees: BTreeMap::from([(ADDR_OVL, 500), (ADDR_BBB, 500)]),
+            ccd_fees: BTreeMap::new(),
             open_at: BTreeMap::from([
                 (Timestamp::from_timestamp_millis(10), Prior::TOP),
                 (Timestamp::from_timestamp_millis(20), Prior::SECOND),
@@ -473,18 +615,152 @@ mod tests {
             min_units: 50,
             price_per_token: 5_000_000,
             token_per_unit: 200.into(),
-            vesting_period: if vesting_period.is_empty() {
-                BTreeMap::from([
-                    (Duration::from_days(1), 25),
-                    (Duration::from_days(2), 40),
-                    (Duration::from_days(3), 35),
-                ])
-            } else {
-                vesting_period
-            },
+            vesting_period: VestingSchedule::Stepped(vesting_period),
+            signers: BTreeSet::from([ADMIN_ACC]),
+            threshold: 1,
+            tier_units: BTreeMap::from([(Tier::T0, TARGET_UNITS)]),
+            timelock_delay: Duration::from_days(2),
+            lottery_commitment: None,
+            overflow_min_deposit: None,
+            escrow: None,
+            delivery_deadline: None,
+            ticket_key: None,
         }
     }
 
+    #[test]
+    fn test_fees() {
+        let params = init_parameter(BTreeMap::new());
+        assert_eq!(
+            validate_fees(params.user_share, &params.fees, &params.ccd_fees),
+            Ok(())
+        );
+        assert_eq!(
+            validate_fees(9_100, &params.fees, &params.ccd_fees),
+            Err(CustomContractError::InvalidFeeTable),
+            "the shares should add up to 100%"
+        );
+        assert_eq!(
+            validate_fees(10_000, &BTreeMap::from([(ADDR_OVL, 0)]), &params.ccd_fees),
+            Err(CustomContractError::InvalidFeeTable),
+            "a recipient should have some share"
+        );
+        assert_eq!(
+            validate_fees(
+                10_000,
+                &BTreeMap::new(),
+                &BTreeMap::from([(ADDR_OVL, 6_000), (ADDR_BBB, 5_000)])
+            ),
+            Err(CustomContractError::InvalidFeeTable),
+            "the CCD fees should not exceed 100%"
+        );
+
+        let mut state_builder = TestStateBuilder::new();
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
+        let mut state = State::new(
+            &mut state_builder,
+            params.proj_admin,
+            params.user_share,
+            params.fees,
+            params.ccd_fees,
+            SaleSchedule::new(
+                Timestamp::from_timestamp_millis(1),
+                params.open_at,
+                params.close_at,
+                params.vesting_period,
+            )
+            .unwrap(),
+            SaleInfo::new(5_000_000, 200.into(), 100, 50).unwrap(),
+            params.tier_units,
+            multisig,
+            params.timelock_delay,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        assert_eq!(
+            state.increment_fee_claimed(&ADDR_OVL, 1, ContractTokenAmount::from(100)),
+            Ok(())
+        );
+
+        // the new recipient takes over the share and the claim progress
+        let new_ovl = Address::Account(AccountAddress([4u8; 32]));
+        assert_eq!(
+            state.change_fee_recipient(&ADDR_OVL, ADDR_BBB),
+            Err(CustomContractError::InvalidFeeTable.into())
+        );
+        assert_eq!(state.change_fee_recipient(&ADDR_OVL, new_ovl), Ok(()));
+        assert_eq!(
+            state.get_fee(&ADDR_OVL),
+            Err(CustomContractError::UnknownFeeRecipient.into())
+        );
+        assert_eq!(
+            state.get_fee(&new_ovl),
+            Ok(FeeState {
+                bps: 500,
+                ccd_bps: 0,
+                claimed_inc: 1,
+                claimed_amount: ContractTokenAmount::from(100),
+            })
+        );
+    }
+
+    #[test]
+    fn test_ccd_payouts() {
+        let params = init_parameter(BTreeMap::new());
+        let addr_ccd_only = Address::Account(AccountAddress([4u8; 32]));
+        let mut state_builder = TestStateBuilder::new();
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
+        let state = State::new(
+            &mut state_builder,
+            params.proj_admin,
+            params.user_share,
+            params.fees,
+            BTreeMap::from([(ADDR_OVL, 333), (addr_ccd_only, 100)]),
+            SaleSchedule::new(
+                Timestamp::from_timestamp_millis(1),
+                params.open_at,
+                params.close_at,
+                params.vesting_period,
+            )
+            .unwrap(),
+            SaleInfo::new(5_000_000, 200.into(), 100, 50).unwrap(),
+            params.tier_units,
+            multisig,
+            params.timelock_delay,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+        assert_eq!(
+            state.get_fee(&addr_ccd_only),
+            Ok(FeeState {
+                ccd_bps: 100,
+                ..FeeState::new(0)
+            }),
+            "a recipient of the CCD fee only has no share of the tokens"
+        );
+
+        // the project admin receives what is left after rounding down
+        assert_eq!(
+            state.ccd_payouts(Amount::from_micro_ccd(1_000_001)),
+            vec![
+                (ADDR_OVL, Amount::from_micro_ccd(33_300)),
+                (addr_ccd_only, Amount::from_micro_ccd(10_000)),
+                (
+                    Address::Account(params.proj_admin),
+                    Amount::from_micro_ccd(956_701)
+                ),
+            ]
+        );
+    }
+
     #[test]
     fn test_invalid_schedule() {
         let open_at = BTreeMap::from([
@@ -492,11 +768,11 @@ mod tests {
             (Timestamp::from_timestamp_millis(20), Prior::SECOND),
         ]);
         let close_at = Timestamp::from_timestamp_millis(30);
-        let vesting_period = BTreeMap::from([
-            (Duration::from_days(30), 30),
-            (Duration::from_days(60), 40),
-            (Duration::from_days(90), 30),
-        ]);
+        let vesting_period = VestingSchedule::Stepped(BTreeMap::from([
+            (Duration::from_days(30), 3_000),
+            (Duration::from_days(60), 4_000),
+            (Duration::from_days(90), 3_000),
+        ]));
         let schedule = SaleSchedule::new(
             Timestamp::from_timestamp_millis(15),
             open_at,
@@ -535,13 +811,24 @@ mod tests {
             params.min_units,
         )
         .unwrap_abort();
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
         let mut state = State::new(
             &mut state_builder,
             params.proj_admin,
-            params.addr_ovl,
-            params.addr_bbb,
+            params.user_share,
+            params.fees,
+            params.ccd_fees,
             schedule,
             saleinfo,
+            params.tier_units,
+            multisig,
+            params.timelock_delay,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
 
         state
@@ -555,7 +842,7 @@ mod tests {
             (&USER2_ADDR, Prior::TOP),
         ];
         for v in users.into_iter() {
-            state.whitelisting(v.0, v.1);
+            state.whitelisting(v.0, v.1, &Tier::T0).unwrap();
         }
 
         assert_eq!(
@@ -565,13 +852,19 @@ mod tests {
                 deposit_ccd: Amount::zero(),
                 tgt_units: 1,
                 win_units: 0,
-                claimed_inc: 0
+                claimed_inc: 0,
+                alloc_tokens: ContractTokenAmount::from(0),
+                refundable_ccd: Amount::zero(),
+                refunded: false,
+                claimed_amount: ContractTokenAmount::from(0),
+                escrow_votes: 0,
             },
             "something wrong with user1 before deposit!"
         );
 
         // deposit = allocation fixed
         let _ = state.deposit(&USER1_ADDR, Amount::from_ccd(100), 1);
+        assert_eq!(state.unclaimed_users, 1, "user1 should have tokens to claim");
 
         // vesting
         assert!(state.check_listed(&USER1_ADDR), "user1 should exist!");
@@ -583,7 +876,12 @@ mod tests {
                 deposit_ccd: Amount::from_micro_ccd(100_000_000),
                 tgt_units: 1,
                 win_units: 1,
-                claimed_inc: 0
+                claimed_inc: 0,
+                alloc_tokens: ContractTokenAmount::from(0),
+                refundable_ccd: Amount::zero(),
+                refunded: false,
+                claimed_amount: ContractTokenAmount::from(0),
+                escrow_votes: 0,
             },
             "something wrong with user1 after deposit!"
         );
@@ -635,13 +933,24 @@ mod tests {
             params.min_units,
         )
         .unwrap_abort();
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
         let mut state = State::new(
             &mut state_builder,
             params.proj_admin,
-            params.addr_ovl,
-            params.addr_bbb,
+            params.user_share,
+            params.fees,
+            params.ccd_fees,
             schedule,
             saleinfo,
+            params.tier_units,
+            multisig,
+            params.timelock_delay,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
 
         state
@@ -651,7 +960,7 @@ mod tests {
         // whitelisted
         let users = vec![(&USER1_ADDR, Prior::TOP), (&USER2_ADDR, Prior::SECOND)];
         for v in users.into_iter() {
-            state.whitelisting(v.0, v.1);
+            state.whitelisting(v.0, v.1, &Tier::T0).unwrap();
         }
 
         assert!(state.check_listed(&USER1_ADDR), "user1 should exist!");
@@ -664,7 +973,12 @@ mod tests {
                 deposit_ccd: Amount::zero(),
                 tgt_units: 1,
                 win_units: 0,
-                claimed_inc: 0
+                claimed_inc: 0,
+                alloc_tokens: ContractTokenAmount::from(0),
+                refundable_ccd: Amount::zero(),
+                refunded: false,
+                claimed_amount: ContractTokenAmount::from(0),
+                escrow_votes: 0,
             }),
             "something wrong with user1 before deposit!"
         );
@@ -682,15 +996,540 @@ mod tests {
                 deposit_ccd: Amount::zero(),
                 tgt_units: 1,
                 win_units: 0,
-                claimed_inc: 0
+                claimed_inc: 0,
+                alloc_tokens: ContractTokenAmount::from(0),
+                refundable_ccd: Amount::zero(),
+                refunded: false,
+                claimed_amount: ContractTokenAmount::from(0),
+                escrow_votes: 0,
             }),
             "something wrong with user1 before deposit!"
         );
     }
 
+    #[test]
+    fn test_participants_page() {
+        // initialize
+        let mut state_builder = TestStateBuilder::new();
+        let params = init_parameter(BTreeMap::new());
+        let schedule = SaleSchedule::new(
+            Timestamp::from_timestamp_millis(1),
+            params.open_at,
+            params.close_at,
+            params.vesting_period,
+        )
+        .unwrap_abort();
+
+        let saleinfo = SaleInfo::new(
+            params.price_per_token,
+            params.token_per_unit,
+            params.max_units,
+            params.min_units,
+        )
+        .unwrap_abort();
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
+        let mut state = State::new(
+            &mut state_builder,
+            params.proj_admin,
+            params.user_share,
+            params.fees,
+            params.ccd_fees,
+            schedule,
+            saleinfo,
+            params.tier_units,
+            multisig,
+            params.timelock_delay,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+
+        let users = vec![
+            (&USER1_ADDR, Prior::TOP),
+            (&USER2_ADDR, Prior::SECOND),
+            (&USER3_ADDR, Prior::TOP),
+        ];
+        for v in users.into_iter() {
+            state.whitelisting(v.0, v.1, &Tier::T0).unwrap();
+        }
+        // user1 and user3 won a unit (180 tokens to claim), user3 claimed them all
+        for (addr, claimed) in [(&USER1_ADDR, 0), (&USER3_ADDR, 180)] {
+            let mut user = state.participants.get_mut(addr).unwrap();
+            user.deposit_ccd = Amount::from_micro_ccd(1_000_000_000);
+            user.win_units = 1;
+            user.claimed_amount = ContractTokenAmount::from(claimed);
+        }
+
+        let addrs = |page: &Vec<(Address, UserState)>| -> Vec<Address> {
+            page.iter().map(|(addr, _)| *addr).collect()
+        };
+        let all = ParticipantFilter::default();
+
+        // walk every participant, 2 at a time
+        let (page, next) = state.participants_page(None, 2, &all).unwrap();
+        assert_eq!(addrs(&page), vec![USER1_ADDR, USER2_ADDR]);
+        assert_eq!(next, Some(USER3_ADDR));
+        let (page, next) = state.participants_page(next, 2, &all).unwrap();
+        assert_eq!(addrs(&page), vec![USER3_ADDR]);
+        assert_eq!(next, None);
+
+        // filters
+        let filter = ParticipantFilter {
+            prior: Some(Prior::TOP),
+            ..Default::default()
+        };
+        let (page, _) = state.participants_page(None, 10, &filter).unwrap();
+        assert_eq!(addrs(&page), vec![USER1_ADDR, USER3_ADDR]);
+
+        let filter = ParticipantFilter {
+            deposited: Some(false),
+            ..Default::default()
+        };
+        let (page, _) = state.participants_page(None, 10, &filter).unwrap();
+        assert_eq!(addrs(&page), vec![USER2_ADDR]);
+
+        let filter = ParticipantFilter {
+            claim: Some(ClaimProgress::NotStarted),
+            ..Default::default()
+        };
+        let (page, _) = state.participants_page(None, 10, &filter).unwrap();
+        assert_eq!(addrs(&page), vec![USER1_ADDR]);
+
+        let filter = ParticipantFilter {
+            claim: Some(ClaimProgress::Completed),
+            ..Default::default()
+        };
+        let (page, next) = state.participants_page(None, 2, &filter).unwrap();
+        assert!(
+            page.is_empty(),
+            "the limit should bound the walked participants"
+        );
+        assert_eq!(next, Some(USER3_ADDR));
+        let (page, next) = state.participants_page(next, 2, &filter).unwrap();
+        assert_eq!(addrs(&page), vec![USER3_ADDR]);
+        assert_eq!(next, None);
+
+        // the participant at the cursor quits between the pages
+        let (page, next) = state.participants_page(None, 1, &all).unwrap();
+        assert_eq!(addrs(&page), vec![USER1_ADDR]);
+        assert_eq!(next, Some(USER2_ADDR));
+        state.remove_participant(&USER2_ADDR, 0);
+        let (page, next) = state.participants_page(next, 2, &all).unwrap();
+        assert_eq!(addrs(&page), vec![USER3_ADDR]);
+        assert_eq!(next, None);
+
+        assert_eq!(
+            state.participants_page(None, 0, &all),
+            Err(CustomContractError::InvalidPage.into()),
+            "a page should walk at least one participant"
+        );
+        assert_eq!(
+            state.participants_page(Some(ADDR_OVL), 2, &all),
+            Err(CustomContractError::InvalidPage.into()),
+            "the cursor should be a participant"
+        );
+    }
+
+    #[test]
+    fn test_whitelisting_with_tiers() {
+        let mut state_builder = TestStateBuilder::new();
+        let params = init_parameter(BTreeMap::new());
+        let schedule = SaleSchedule::new(
+            Timestamp::from_timestamp_millis(1),
+            params.open_at,
+            params.close_at,
+            params.vesting_period,
+        )
+        .unwrap();
+        let saleinfo = SaleInfo::new(
+            params.price_per_token,
+            params.token_per_unit,
+            params.max_units,
+            params.min_units,
+        )
+        .unwrap();
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
+        let mut state = State::new(
+            &mut state_builder,
+            params.proj_admin,
+            params.user_share,
+            params.fees,
+            params.ccd_fees,
+            schedule,
+            saleinfo,
+            BTreeMap::from([(Tier::T0, 1), (Tier::T2, 3)]),
+            multisig,
+            params.timelock_delay,
+            None,
+            None,
+            None,
+            None,
+            None,
+        );
+
+        assert_eq!(state.whitelisting(&USER1_ADDR, Prior::TOP, &Tier::T2), Ok(3));
+        assert_eq!(
+            state.whitelisting(&USER2_ADDR, Prior::TOP, &Tier::T5),
+            Err(CustomContractError::UnknownTier),
+            "tiers without units should be rejected"
+        );
+        assert!(!state.check_listed(&USER2_ADDR), "user2 should not on list");
+
+        assert_eq!(state.get_user(&USER1_ADDR).map(|u| u.tgt_units), Ok(3));
+        assert_eq!(
+            state.get_user_any(&USER3_ADDR).map(|u| u.tgt_units),
+            Ok(1),
+            "users out of the whitelist should purchase as T0"
+        );
+    }
+
+    #[test]
+    fn test_settle_overflow() {
+        let mut state_builder = TestStateBuilder::new();
+        let params = init_parameter(BTreeMap::new());
+        let schedule = SaleSchedule::new(
+            Timestamp::from_timestamp_millis(1),
+            params.open_at,
+            params.close_at,
+            params.vesting_period,
+        )
+        .unwrap();
+        let saleinfo = SaleInfo::new(
+            params.price_per_token,
+            params.token_per_unit,
+            params.max_units,
+            params.min_units,
+        )
+        .unwrap();
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
+        let mut state = State::new(
+            &mut state_builder,
+            params.proj_admin,
+            params.user_share,
+            params.fees,
+            params.ccd_fees,
+            schedule,
+            saleinfo,
+            params.tier_units,
+            multisig,
+            params.timelock_delay,
+            None,
+            Some(Overflow::new(Amount::from_ccd(1))),
+            None,
+            None,
+            None,
+        );
+
+        // hardcap is 100 units * 200 tokens * 5 CCD = 100_000 CCD, deposited twice over
+        let deposits = [
+            (&USER1_ADDR, Amount::from_ccd(100_000)),
+            (&USER2_ADDR, Amount::from_ccd(60_000)),
+            (&USER3_ADDR, Amount::from_ccd(40_000)),
+        ];
+        for (user, amount) in deposits.iter() {
+            state.whitelisting(user, Prior::TOP, &Tier::T0).unwrap();
+            state.deposit_overflow(user, *amount).unwrap();
+        }
+        assert_eq!(
+            state.settle_overflow(0),
+            Err(CustomContractError::InvalidPage.into())
+        );
+        // settled a page at a time, fixing the units sold only at the end
+        assert_eq!(state.settle_overflow(2), Ok(false));
+        assert_eq!(state.saleinfo.applied_units, 0);
+        assert_eq!(state.settle_overflow(2), Ok(true));
+        assert_eq!(
+            state.settle_overflow(2),
+            Err(CustomContractError::Inappropriate.into()),
+            "tokens should be allocated only once"
+        );
+
+        let allocations = [
+            (&USER1_ADDR, 10_000, Amount::from_ccd(50_000)),
+            (&USER2_ADDR, 6_000, Amount::from_ccd(30_000)),
+            (&USER3_ADDR, 4_000, Amount::from_ccd(20_000)),
+        ];
+        for (user, tokens, excess) in allocations.iter() {
+            let user_state = state.get_user(user).unwrap();
+            assert_eq!(user_state.alloc_tokens, ContractTokenAmount::from(*tokens));
+            assert_eq!(user_state.refundable_ccd, *excess);
+        }
+        assert_eq!(state.saleinfo.applied_units, 100);
+        assert_eq!(state.sold_tokens(), Ok(ContractTokenAmount::from(20_000)));
+        assert_eq!(state.refundable(), Amount::from_ccd(100_000));
+
+        assert_eq!(state.refund_excess(&USER1_ADDR), Ok(Amount::from_ccd(50_000)));
+        assert_eq!(
+            state.refund_excess(&USER1_ADDR),
+            Err(CustomContractError::AlreadyRefunded.into()),
+            "excess should be refunded only once"
+        );
+        assert_eq!(
+            state.get_user(&USER1_ADDR).map(|u| u.deposit_ccd),
+            Ok(Amount::from_ccd(50_000))
+        );
+        assert_eq!(state.refundable(), Amount::from_ccd(50_000));
+    }
+
+    #[test]
+    fn test_escrow() {
+        let mut state_builder = TestStateBuilder::new();
+        let params = init_parameter(BTreeMap::new());
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
+        let escrow = Escrow::new(EscrowParams {
+            tranches: vec![
+                (Milestone::PoolFunded, 3_000),
+                (Milestone::At(Timestamp::from_timestamp_millis(100)), 7_000),
+            ],
+            vote_quorum: Some(5_000),
+        });
+        let mut state = State::new(
+            &mut state_builder,
+            params.proj_admin,
+            params.user_share,
+            params.fees,
+            params.ccd_fees,
+            SaleSchedule::new(
+                Timestamp::from_timestamp_millis(1),
+                params.open_at,
+                params.close_at,
+                params.vesting_period,
+            )
+            .unwrap(),
+            SaleInfo::new(5_000_000, 200.into(), 100, 1).unwrap(),
+            BTreeMap::from([(Tier::T0, 1), (Tier::T2, 3)]),
+            multisig,
+            params.timelock_delay,
+            None,
+            None,
+            Some(escrow.unwrap()),
+            None,
+            None,
+        );
+
+        // a unit is 200 tokens * 5 CCD = 1_000 CCD
+        state.whitelisting(&USER1_ADDR, Prior::TOP, &Tier::T2).unwrap();
+        state.whitelisting(&USER2_ADDR, Prior::TOP, &Tier::T0).unwrap();
+        state.deposit(&USER1_ADDR, Amount::from_ccd(3_000), 3).unwrap();
+        state.deposit(&USER2_ADDR, Amount::from_ccd(1_000), 1).unwrap();
+        assert_eq!(state.fix_escrow(Amount::from_ccd(4_000)), Ok(()));
+        assert_eq!(
+            state.fix_escrow(Amount::from_ccd(1_000)),
+            Err(CustomContractError::Inappropriate.into()),
+            "the CCD raised should be fixed only once"
+        );
+
+        let now = Timestamp::from_timestamp_millis(50);
+        assert_eq!(
+            state.release_tranches(now),
+            Err(CustomContractError::MilestoneNotReached.into()),
+            "tranches should be approved before release"
+        );
+        assert_eq!(state.approve_tranche(0), Ok(()));
+        assert_eq!(
+            state.approve_tranche(0),
+            Err(CustomContractError::Inappropriate.into())
+        );
+        assert_eq!(
+            state.release_tranches(now),
+            Err(CustomContractError::MilestoneNotReached.into()),
+            "the pool should be funded first"
+        );
+        state.pool_funded = ContractTokenAmount::from(800);
+        assert_eq!(
+            state.release_tranches(now),
+            Ok((vec![0], Amount::from_ccd(1_200)))
+        );
+
+        // votes are weighted by the CCD paid
+        assert_eq!(
+            state.vote_tranche(&USER2_ADDR, 1),
+            Ok((Amount::from_ccd(1_000), false))
+        );
+        assert_eq!(
+            state.vote_tranche(&USER2_ADDR, 1),
+            Err(CustomContractError::AlreadyVoted.into())
+        );
+        assert_eq!(
+            state.vote_tranche(&USER3_ADDR, 1),
+            Err(ContractError::Unauthorized)
+        );
+        assert_eq!(
+            state.vote_tranche(&USER1_ADDR, 1),
+            Ok((Amount::from_ccd(4_000), true))
+        );
+        assert_eq!(
+            state.release_tranches(now),
+            Err(CustomContractError::MilestoneNotReached.into())
+        );
+
+        // CCD not released is refunded pro rata
+        assert_eq!(state.fail_escrow(), Ok(Amount::from_ccd(2_800)));
+        assert_eq!(
+            state.release_tranches(Timestamp::from_timestamp_millis(100)),
+            Err(CustomContractError::EscrowFailed.into())
+        );
+        assert_eq!(state.refund_escrow(&USER1_ADDR), Ok(Amount::from_ccd(2_100)));
+        assert_eq!(
+            state.refund_escrow(&USER1_ADDR),
+            Err(CustomContractError::AlreadyRefunded.into())
+        );
+        assert_eq!(state.refundable(), Amount::from_ccd(700));
+        assert_eq!(state.refund_escrow(&USER2_ADDR), Ok(Amount::from_ccd(700)));
+        assert_eq!(state.refundable(), Amount::zero());
+    }
+
+    #[test]
+    fn test_linear_vesting() {
+        let linear = |tge_percentage, duration| {
+            VestingSchedule::Linear(LinearVesting {
+                tge_percentage,
+                cliff: Duration::from_days(30),
+                duration,
+            })
+        };
+        claim_eq!(
+            linear(10_001, Duration::from_days(100)).validate(),
+            Err(CustomContractError::Inappropriate)
+        );
+        claim_eq!(
+            linear(1_000, Duration::from_millis(0)).validate(),
+            Err(CustomContractError::InvalidSchedule)
+        );
+        claim_eq!(linear(10_000, Duration::from_millis(0)).validate(), Ok(()));
+
+        let mut schedule = SaleSchedule::new(
+            Timestamp::from_timestamp_millis(1),
+            BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+            Timestamp::from_timestamp_millis(30),
+            linear(1_000, Duration::from_days(100)),
+        )
+        .unwrap();
+        let tge = Timestamp::from_timestamp_millis(1_000);
+        schedule.vesting_start = Some(tge);
+        let at = |days: u64| tge.checked_add(Duration::from_days(days)).unwrap();
+        let vested = |now: Timestamp| schedule.vesting_period.vested_amount(now, tge, 1_000);
+
+        claim_eq!(vested(Timestamp::from_timestamp_millis(999)), Ok(0));
+        // 10% at TGE and nothing more during the cliff
+        claim_eq!(vested(tge), Ok(100));
+        claim_eq!(vested(at(30).checked_sub(Duration::from_millis(1)).unwrap()), Ok(100));
+        // the rest is released linearly
+        claim_eq!(vested(at(30 + 50)), Ok(100 + 900 / 2));
+        claim_eq!(vested(at(30 + 100)), Ok(1_000));
+        claim_eq!(vested(at(1_000)), Ok(1_000));
+
+        // claims are tracked by amount and the step counter is left as is
+        claim_eq!(
+            schedule.calc_claimable_amount(
+                at(30 + 50),
+                tge,
+                1_000,
+                9_000,
+                0,
+                ContractTokenAmount::from(0),
+            ),
+            Ok((ContractTokenAmount::from(90 + 810 / 2), 0))
+        );
+        claim_eq!(
+            schedule.calc_claimable_amount(
+                at(30 + 100),
+                tge,
+                1_000,
+                9_000,
+                0,
+                ContractTokenAmount::from(90 + 810 / 2),
+            ),
+            Ok((ContractTokenAmount::from(810 / 2), 0))
+        );
+    }
+
+    #[test]
+    fn test_vesting_cumulative() {
+        let mut schedule = SaleSchedule::new(
+            Timestamp::from_timestamp_millis(1),
+            BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+            Timestamp::from_timestamp_millis(30),
+            VestingSchedule::Stepped(BTreeMap::from([
+                (Duration::from_days(1), 3_300),
+                (Duration::from_days(2), 3_300),
+                (Duration::from_days(3), 3_400),
+            ])),
+        )
+        .unwrap();
+        let tge = Timestamp::from_timestamp_millis(1_000);
+        schedule.vesting_start = Some(tge);
+        let at = |days: u64| tge.checked_add(Duration::from_days(days)).unwrap();
+
+        // 303 * 90% = 272.7, rounded down once rather than every step
+        let mut claimed = ContractTokenAmount::from(0);
+        let mut claims = Vec::new();
+        for day in 1..=3 {
+            let (amount, inc) = schedule
+                .calc_claimable_amount(at(day), tge, 303, 9_000, 0, claimed)
+                .unwrap();
+            claimed += amount;
+            claims.push((amount.0, inc));
+        }
+        claim_eq!(claims, vec![(89, 1), (90, 2), (93, 3)]);
+        claim_eq!(claimed, ContractTokenAmount::from(272));
+
+        // nothing more after the last unlock
+        claim_eq!(
+            schedule.calc_claimable_amount(at(4), tge, 303, 9_000, 3, claimed),
+            Ok((ContractTokenAmount::from(0), 3))
+        );
+    }
+
+    #[test]
+    fn test_vesting_fractional_percentages() {
+        // 12.5% every month and a 2.5% fee
+        let steps: BTreeMap<Duration, AllowedPercentage> = (1..=8)
+            .map(|month| (Duration::from_days(30 * month), 1_250))
+            .collect();
+        let mut schedule = SaleSchedule::new(
+            Timestamp::from_timestamp_millis(1),
+            BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+            Timestamp::from_timestamp_millis(30),
+            VestingSchedule::Stepped(steps),
+        )
+        .unwrap();
+        let tge = Timestamp::from_timestamp_millis(1_000);
+        schedule.vesting_start = Some(tge);
+        let at = |days: u64| tge.checked_add(Duration::from_days(days)).unwrap();
+
+        claim_eq!(
+            schedule.vesting_period.vested_amount(at(30), tge, 1_000),
+            Ok(125)
+        );
+        claim_eq!(
+            schedule.calc_claimable_amount(
+                at(90),
+                tge,
+                1_000_000,
+                250,
+                0,
+                ContractTokenAmount::from(0)
+            ),
+            Ok((ContractTokenAmount::from(25_000 * 3 / 8), 3))
+        );
+
+        claim_eq!(
+            VestingSchedule::Stepped(BTreeMap::from([(Duration::from_days(1), 9_999)])).validate(),
+            Err(CustomContractError::Inappropriate),
+            "steps should add up to 10,000 basis points"
+        );
+    }
+
     #[test]
     fn test_vesting_first() {
-        let first_per = 25;
+        let first_per = 2_500;
         let max = 5_000_000;
         let applied = 6_000_000;
         let token_per_unit = 200_000_000;
@@ -704,29 +1543,42 @@ mod tests {
                 (Timestamp::from_timestamp_millis(20), Prior::SECOND),
             ]),
             Timestamp::from_timestamp_millis(30),
-            BTreeMap::from([
+            VestingSchedule::Stepped(BTreeMap::from([
                 (Duration::from_millis(10), first_per),
-                (Duration::from_millis(20), 40),
-                (Duration::from_millis(30), 35),
-            ]),
+                (Duration::from_millis(20), 4_000),
+                (Duration::from_millis(30), 3_500),
+            ])),
         )
         .unwrap();
         let saleinfo = SaleInfo::new(15_000_000, token_per_unit.into(), max, 100).unwrap();
 
-        let mut state = State::new(
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
+        let state = State::new(
             &mut state_builder,
             params.proj_admin,
-            params.addr_ovl,
-            params.addr_bbb,
+            params.user_share,
+            params.fees,
+            params.ccd_fees,
             schedule,
             saleinfo,
+            params.tier_units,
+            multisig,
+            params.timelock_delay,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
         let cur_inc = 0;
         let total_units = cmp::min(state.saleinfo.max_units, applied);
         let ret = state
+            .schedule
             .calc_vesting_amount(
                 Timestamp::from_timestamp_millis(61),
                 Timestamp::from_timestamp_millis(50),
+                state.saleinfo.token_per_unit,
                 total_units as u64,
                 PUBLIC_RIDO_FEE_OVL,
                 cur_inc,
@@ -735,9 +1587,9 @@ mod tests {
 
         claim_eq!(
             ret.0 .0,
-            total_units as u64 * token_per_unit * PUBLIC_RIDO_FEE_OVL as u64 / 100
+            total_units as u64 * token_per_unit * PUBLIC_RIDO_FEE_OVL as u64 / 10_000
                 * first_per as u64
-                / 100,
+                / 10_000,
             "Something wrong with vesting calcuration!"
         );
         claim_eq!(ret.1, 1, "Something wrong with claimed_inc!");
@@ -745,7 +1597,7 @@ mod tests {
 
     #[test]
     fn test_vesting_too_early() {
-        let first_per = 25;
+        let first_per = 2_500;
         let max = 5_000_000;
         let applied = 6_000_000;
         let token_per_unit = 200_000_000;
@@ -759,29 +1611,42 @@ mod tests {
                 (Timestamp::from_timestamp_millis(20), Prior::SECOND),
             ]),
             Timestamp::from_timestamp_millis(30),
-            BTreeMap::from([
+            VestingSchedule::Stepped(BTreeMap::from([
                 (Duration::from_millis(10), first_per),
-                (Duration::from_millis(20), 40),
-                (Duration::from_millis(30), 35),
-            ]),
+                (Duration::from_millis(20), 4_000),
+                (Duration::from_millis(30), 3_500),
+            ])),
         )
         .unwrap();
         let saleinfo = SaleInfo::new(15_000_000, token_per_unit.into(), max, 100).unwrap();
 
-        let mut state = State::new(
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
+        let state = State::new(
             &mut state_builder,
             params.proj_admin,
-            params.addr_ovl,
-            params.addr_bbb,
+            params.user_share,
+            params.fees,
+            params.ccd_fees,
             schedule,
             saleinfo,
+            params.tier_units,
+            multisig,
+            params.timelock_delay,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
         let cur_inc = 0;
         let total_units = cmp::min(state.saleinfo.max_units, applied);
         let ret = state
+            .schedule
             .calc_vesting_amount(
                 Timestamp::from_timestamp_millis(40),
                 Timestamp::from_timestamp_millis(50),
+                state.saleinfo.token_per_unit,
                 total_units as u64,
                 PUBLIC_RIDO_FEE_OVL,
                 cur_inc,
@@ -793,8 +1658,8 @@ mod tests {
 
     #[test]
     fn test_vesting_second_all_at_once() {
-        let first_per = 25;
-        let second_per = 40;
+        let first_per = 2_500;
+        let second_per = 4_000;
         let max = 5_000_000;
         let applied = 6_000_000;
         let token_per_unit = 200_000_000;
@@ -808,29 +1673,42 @@ mod tests {
                 (Timestamp::from_timestamp_millis(20), Prior::SECOND),
             ]),
             Timestamp::from_timestamp_millis(30),
-            BTreeMap::from([
+            VestingSchedule::Stepped(BTreeMap::from([
                 (Duration::from_millis(10), first_per),
                 (Duration::from_millis(20), second_per),
-                (Duration::from_millis(30), 35),
-            ]),
+                (Duration::from_millis(30), 3_500),
+            ])),
         )
         .unwrap();
         let saleinfo = SaleInfo::new(15_000_000, token_per_unit.into(), max, 100).unwrap();
 
-        let mut state = State::new(
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
+        let state = State::new(
             &mut state_builder,
             params.proj_admin,
-            params.addr_ovl,
-            params.addr_bbb,
+            params.user_share,
+            params.fees,
+            params.ccd_fees,
             schedule,
             saleinfo,
+            params.tier_units,
+            multisig,
+            params.timelock_delay,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
         let cur_inc = 0;
         let total_units = cmp::min(state.saleinfo.max_units, applied);
         let ret = state
+            .schedule
             .calc_vesting_amount(
                 Timestamp::from_timestamp_millis(70),
                 Timestamp::from_timestamp_millis(50),
+                state.saleinfo.token_per_unit,
                 total_units as u64,
                 PUBLIC_RIDO_FEE_OVL,
                 cur_inc,
@@ -839,9 +1717,9 @@ mod tests {
 
         claim_eq!(
             ret.0 .0,
-            total_units as u64 * token_per_unit * PUBLIC_RIDO_FEE_OVL as u64 / 100
+            total_units as u64 * token_per_unit * PUBLIC_RIDO_FEE_OVL as u64 / 10_000
                 * (first_per + second_per) as u64
-                / 100,
+                / 10_000,
             "Something wrong with vesting calcuration!"
         );
         claim_eq!(ret.1, 2, "Something wrong with claimed_inc!");
@@ -849,8 +1727,8 @@ mod tests {
 
     #[test]
     fn test_vesting_second_separate() {
-        let first_per = 25;
-        let second_per = 40;
+        let first_per = 2_500;
+        let second_per = 4_000;
         let max = 5_000_000;
         let applied = 6_000_000;
         let token_per_unit = 200_000_000;
@@ -864,29 +1742,42 @@ mod tests {
                 (Timestamp::from_timestamp_millis(20), Prior::SECOND),
             ]),
             Timestamp::from_timestamp_millis(30),
-            BTreeMap::from([
+            VestingSchedule::Stepped(BTreeMap::from([
                 (Duration::from_millis(10), first_per),
                 (Duration::from_millis(20), second_per),
-                (Duration::from_millis(30), 35),
-            ]),
+                (Duration::from_millis(30), 3_500),
+            ])),
         )
         .unwrap();
         let saleinfo = SaleInfo::new(15_000_000, token_per_unit.into(), max, 100).unwrap();
 
-        let mut state = State::new(
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
+        let state = State::new(
             &mut state_builder,
             params.proj_admin,
-            params.addr_ovl,
-            params.addr_bbb,
+            params.user_share,
+            params.fees,
+            params.ccd_fees,
             schedule,
             saleinfo,
+            params.tier_units,
+            multisig,
+            params.timelock_delay,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
         let cur_inc = 1;
         let total_units = cmp::min(state.saleinfo.max_units, applied);
         let ret = state
+            .schedule
             .calc_vesting_amount(
                 Timestamp::from_timestamp_millis(70),
                 Timestamp::from_timestamp_millis(50),
+                state.saleinfo.token_per_unit,
                 total_units as u64,
                 PUBLIC_RIDO_FEE_OVL,
                 cur_inc,
@@ -895,9 +1786,9 @@ mod tests {
 
         claim_eq!(
             ret.0 .0,
-            total_units as u64 * token_per_unit * PUBLIC_RIDO_FEE_OVL as u64 / 100
+            total_units as u64 * token_per_unit * PUBLIC_RIDO_FEE_OVL as u64 / 10_000
                 * second_per as u64
-                / 100,
+                / 10_000,
             "Something wrong with vesting calcuration!"
         );
         claim_eq!(ret.1, 2, "Something wrong with claimed_inc!");
@@ -905,7 +1796,7 @@ mod tests {
 
     #[test]
     fn test_vesting_check_u64() {
-        let first_per = 25;
+        let first_per = 2_500;
         let max = 1000;
         let applied = 1000;
         let price_per_token = 100;
@@ -920,29 +1811,42 @@ mod tests {
                 (Timestamp::from_timestamp_millis(20), Prior::SECOND),
             ]),
             Timestamp::from_timestamp_millis(30),
-            BTreeMap::from([
+            VestingSchedule::Stepped(BTreeMap::from([
                 (Duration::from_millis(10), first_per),
-                (Duration::from_millis(20), 40),
-                (Duration::from_millis(30), 35),
-            ]),
+                (Duration::from_millis(20), 4_000),
+                (Duration::from_millis(30), 3_500),
+            ])),
         )
         .unwrap();
         let saleinfo = SaleInfo::new(price_per_token, token_per_unit.into(), max, 10).unwrap();
 
-        let mut state = State::new(
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
+        let state = State::new(
             &mut state_builder,
             params.proj_admin,
-            params.addr_ovl,
-            params.addr_bbb,
+            params.user_share,
+            params.fees,
+            params.ccd_fees,
             schedule,
             saleinfo,
+            params.tier_units,
+            multisig,
+            params.timelock_delay,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
         let cur_inc = 0;
         let total_units = cmp::min(state.saleinfo.max_units, applied);
         let ret = state
+            .schedule
             .calc_vesting_amount(
                 Timestamp::from_timestamp_millis(61),
                 Timestamp::from_timestamp_millis(50),
+                state.saleinfo.token_per_unit,
                 total_units as u64,
                 PUBLIC_RIDO_FEE_OVL,
                 cur_inc,
@@ -950,9 +1854,9 @@ mod tests {
             .unwrap();
 
         // overflow
-        // let ans = total_units as u64 * token_per_unit * PUBLIC_RIDO_FEE_OVL as u64 / 100
+        // let ans = total_units as u64 * token_per_unit * PUBLIC_RIDO_FEE_OVL as u64 / 10_000
         //     * first_per as u64
-        //     / 100;
+        //     / 10_000;
 
         claim_eq!(
             ret.0 .0,
@@ -963,7 +1867,7 @@ mod tests {
 
     #[test]
     fn test_vesting_overflow() {
-        let first_per = 25;
+        let first_per = 2_500;
         let max = 100_000;
         let applied = 100_000;
         let price_per_token = 100;
@@ -978,28 +1882,40 @@ mod tests {
                 (Timestamp::from_timestamp_millis(20), Prior::SECOND),
             ]),
             Timestamp::from_timestamp_millis(30),
-            BTreeMap::from([
+            VestingSchedule::Stepped(BTreeMap::from([
                 (Duration::from_millis(10), first_per),
-                (Duration::from_millis(20), 40),
-                (Duration::from_millis(30), 35),
-            ]),
+                (Duration::from_millis(20), 4_000),
+                (Duration::from_millis(30), 3_500),
+            ])),
         )
         .unwrap();
         let saleinfo = SaleInfo::new(price_per_token, token_per_unit.into(), max, 100).unwrap();
 
-        let mut state = State::new(
+        let multisig =
+            Multisig::new(&mut state_builder, params.signers, params.threshold).unwrap();
+        let state = State::new(
             &mut state_builder,
             params.proj_admin,
-            params.addr_ovl,
-            params.addr_bbb,
+            params.user_share,
+            params.fees,
+            params.ccd_fees,
             schedule,
             saleinfo,
+            params.tier_units,
+            multisig,
+            params.timelock_delay,
+            None,
+            None,
+            None,
+            None,
+            None,
         );
         let cur_inc = 0;
         let total_units = cmp::min(state.saleinfo.max_units, applied);
-        let ret = state.calc_vesting_amount(
+        let ret = state.schedule.calc_vesting_amount(
             Timestamp::from_timestamp_millis(61),
             Timestamp::from_timestamp_millis(50),
+            state.saleinfo.token_per_unit,
             total_units as u64,
             PUBLIC_RIDO_FEE_OVL,
             cur_inc,
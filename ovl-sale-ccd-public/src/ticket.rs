@@ -0,0 +1,59 @@
+use crate::state::{State, *};
+use concordium_std::*;
+
+/// Allocation granted off-chain by the backend in place of a `whitelisting` call.
+/// The backend signs the serialized ticket with the key set at init.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct AllocationTicket {
+    /// User allowed to deposit
+    pub user: Address,
+    /// Priority for participation in the sale
+    pub prior: Prior,
+    /// Number of units the user can purchase
+    pub units: u8,
+    /// The ticket cannot be used after this time
+    pub expiry: Timestamp,
+    /// Sale contract the ticket is issued for, so that it cannot be replayed on another sale
+    pub contract: ContractAddress,
+}
+
+/// Ticket with the signature of the backend, passed to `userDeposit`.
+#[derive(Debug, Serialize, SchemaType, Clone)]
+pub struct SignedTicket {
+    pub ticket: AllocationTicket,
+    /// ed25519 signature of the backend over `to_bytes(&ticket)`
+    pub signature: SignatureEd25519,
+}
+
+impl<S: HasStateApi> State<S> {
+    /// Verifies a ticket presented by `sender` and puts the user on the whitelist.
+    /// Users already on the whitelist keep their entry.
+    pub(crate) fn redeem_ticket(
+        &mut self,
+        crypto_primitives: &impl HasCryptoPrimitives,
+        signed: SignedTicket,
+        sender: Address,
+        self_address: ContractAddress,
+        now: Timestamp,
+    ) -> ContractResult<()> {
+        let key = self
+            .ticket_key
+            .ok_or(ContractError::from(CustomContractError::Inappropriate))?;
+        let ticket = signed.ticket;
+        ensure!(
+            ticket.user == sender && ticket.contract == self_address && ticket.units > 0,
+            CustomContractError::InvalidTicket.into()
+        );
+        ensure!(
+            now <= ticket.expiry,
+            CustomContractError::TicketExpired.into()
+        );
+        ensure!(
+            crypto_primitives.verify_ed25519_signature(key, signed.signature, &to_bytes(&ticket)),
+            CustomContractError::InvalidTicket.into()
+        );
+
+        self.allow_user(&sender, ticket.prior, ticket.units);
+        Ok(())
+    }
+}
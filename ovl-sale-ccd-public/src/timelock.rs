@@ -0,0 +1,115 @@
+use crate::{multisig::AdminAction, state::*};
+use concordium_std::*;
+
+/// Identifier of a queued change, issued sequentially from 0.
+pub type ChangeId = u64;
+
+/// An approved admin action waiting for its effective time.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct PendingChange {
+    /// Action to be applied
+    pub(crate) action: AdminAction,
+    /// The action can be applied at or after this time
+    pub(crate) effective_at: Timestamp,
+}
+
+/// Delay for changes affecting users who have already deposited,
+/// so that they are warned before e.g. their vesting start moves.
+#[derive(Debug, Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+pub struct Timelock<S: HasStateApi> {
+    /// How long a change has to wait after being approved
+    pub(crate) delay: Duration,
+    /// Changes neither applied nor cancelled yet
+    pub(crate) pending: StateMap<ChangeId, PendingChange, S>,
+    /// Id to be given to the next change
+    pub(crate) next_change_id: ChangeId,
+}
+
+impl<S: HasStateApi> Timelock<S> {
+    pub(crate) fn new(state_builder: &mut StateBuilder<S>, delay: Duration) -> Self {
+        Timelock {
+            delay,
+            pending: state_builder.new_map(),
+            next_change_id: 0,
+        }
+    }
+
+    pub(crate) fn queue(
+        &mut self,
+        now: Timestamp,
+        action: AdminAction,
+    ) -> ContractResult<(ChangeId, Timestamp)> {
+        let effective_at = now
+            .checked_add(self.delay)
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+
+        let id = self.next_change_id;
+        self.pending.insert(
+            id,
+            PendingChange {
+                action,
+                effective_at,
+            },
+        );
+        self.next_change_id += 1;
+        Ok((id, effective_at))
+    }
+
+    /// Removes the change and returns its action if it has become effective.
+    pub(crate) fn take_effective(
+        &mut self,
+        id: ChangeId,
+        now: Timestamp,
+    ) -> ContractResult<AdminAction> {
+        let change = self.get(id)?;
+        ensure!(
+            change.effective_at <= now,
+            CustomContractError::NotEffectiveYet.into()
+        );
+        self.pending.remove(&id);
+        Ok(change.action)
+    }
+
+    /// Removes the change if it is still within the delay.
+    pub(crate) fn cancel(&mut self, id: ChangeId, now: Timestamp) -> ContractResult<()> {
+        let change = self.get(id)?;
+        ensure!(
+            now < change.effective_at,
+            CustomContractError::AlreadyEffective.into()
+        );
+        self.pending.remove(&id);
+        Ok(())
+    }
+
+    fn get(&self, id: ChangeId) -> ContractResult<PendingChange> {
+        let change = self
+            .pending
+            .get(&id)
+            .ok_or(ContractError::from(CustomContractError::UnknownChange))?;
+        Ok(change.clone())
+    }
+}
+
+#[cfg(any(feature = "wasm-test", test))]
+/// implements PartialEq for `claim_eq` inside test functions.
+impl<S: HasStateApi> PartialEq for Timelock<S> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.delay != other.delay {
+            return false;
+        }
+        if self.next_change_id != other.next_change_id {
+            return false;
+        }
+        if self.pending.iter().count() != other.pending.iter().count() {
+            return false;
+        }
+        for (id, change) in self.pending.iter() {
+            match other.pending.get(&id) {
+                Some(other_change) if change.clone() == other_change.clone() => {},
+                _ => return false,
+            }
+        }
+        true
+    }
+}
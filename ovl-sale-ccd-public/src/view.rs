@@ -1,18 +1,40 @@
-use crate::state::{State, *};
-use concordium_std::*;
+use crate::{
+    escrow::Escrow,
+    fees::FeeState,
+    lottery::*,
+    multisig::*,
+    permit::*,
+    roles::Role,
+    state::{State, *},
+    timelock::*,
+};
+use concordium_cis2::{SupportResult, SupportsQueryResponse, TokenIdVec};
+use concordium_std::{
+    collections::{BTreeMap, BTreeSet},
+    *,
+};
+use sale_utils::merkle::MerkleRoot;
 
 #[derive(Debug, Serialize, SchemaType)]
 struct ViewResponse {
+    version: u16,
     proj_admin: AccountAddress,
     status: SaleStatus,
     paused: bool,
-    addr_ovl: Address,
-    addr_bbb: Address,
-    ovl_claimed_inc: u8,
-    bbb_claimed_inc: u8,
+    user_share: BasisPoints,
+    fees: BTreeMap<Address, FeeState>,
     project_token: Option<ContractAddress>,
     schedule: SaleSchedule,
     saleinfo: SaleInfo,
+    tier_units: BTreeMap<Tier, u8>,
+    signers: BTreeSet<AccountAddress>,
+    threshold: u8,
+    roles: Vec<(Address, BTreeSet<Role>)>,
+    timelock_delay: Duration,
+    delivery_deadline: Option<Timestamp>,
+    claim_hook: OwnedEntrypointName,
+    ticket_key: Option<PublicKeyEd25519>,
+    whitelist_root: Option<MerkleRoot>,
 }
 
 #[receive(
@@ -26,17 +48,30 @@ fn contract_view<S: HasStateApi>(
 ) -> ReceiveResult<ViewResponse> {
     let state = host.state();
 
+    let mut roles: Vec<(Address, BTreeSet<Role>)> = Vec::new();
+    for (addr, granted) in state.roles.iter() {
+        roles.push((*addr, granted.clone()));
+    }
+
     Ok(ViewResponse {
+        version: state.version,
         proj_admin: state.proj_admin,
         status: state.status.clone(),
         paused: state.paused,
-        addr_ovl: state.addr_ovl,
-        addr_bbb: state.addr_bbb,
-        ovl_claimed_inc: state.ovl_claimed_inc,
-        bbb_claimed_inc: state.bbb_claimed_inc,
+        user_share: state.user_share,
+        fees: state.fees.clone(),
         project_token: state.project_token,
         schedule: state.schedule.clone(),
         saleinfo: state.saleinfo.clone(),
+        tier_units: state.tier_units.clone(),
+        signers: state.multisig.signers.clone(),
+        threshold: state.multisig.threshold,
+        roles,
+        timelock_delay: state.timelock.delay,
+        delivery_deadline: state.delivery_deadline,
+        claim_hook: state.claim_hook(),
+        ticket_key: state.ticket_key,
+        whitelist_root: state.whitelist_root.clone(),
     })
 }
 
@@ -65,6 +100,46 @@ fn contract_view_participants<S: HasStateApi>(
 
 // ------------------------------------------
 
+/// The parameter schema for `viewParticipantsPaged` function.
+#[derive(Debug, Serialize, SchemaType)]
+struct ViewParticipantsPagedParams {
+    /// Participant to start the page from(`None` to start from the beginning).
+    /// Pass `next` of the previous page to continue, even if that participant has quit since.
+    cursor: Option<Address>,
+    /// Maximum number of participants to walk in this page(matching the filter or not, at least 1)
+    limit: u32,
+    filter: ParticipantFilter,
+}
+
+#[derive(Debug, Serialize, SchemaType)]
+struct ViewParticipantsPagedResponse {
+    participants: Vec<(Address, UserState)>,
+    /// Cursor of the next page(`None` if every participant has been walked)
+    next: Option<Address>,
+}
+
+/// Same as `viewParticipants`, a page at a time so that large sales stay within the limits
+/// of return values and energy.
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "viewParticipantsPaged",
+    parameter = "ViewParticipantsPagedParams",
+    return_value = "ViewParticipantsPagedResponse"
+)]
+fn contract_view_participants_paged<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewParticipantsPagedResponse> {
+    let params: ViewParticipantsPagedParams = ctx.parameter_cursor().get()?;
+    let (participants, next) =
+        host.state()
+            .participants_page(params.cursor, params.limit, &params.filter)?;
+
+    Ok(ViewParticipantsPagedResponse { participants, next })
+}
+
+// ------------------------------------------
+
 #[receive(contract = "pub_rido_ccd", name = "viewWinUnits", return_value = "u8")]
 fn contract_win_units<S: HasStateApi>(
     ctx: &impl HasReceiveContext,
@@ -80,3 +155,262 @@ fn contract_win_units<S: HasStateApi>(
 
     Ok(user_state.win_units)
 }
+
+// ------------------------------------------
+
+type ViewProposalsResponse = Vec<(ProposalId, Proposal)>;
+
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "viewProposals",
+    return_value = "ViewProposalsResponse"
+)]
+fn contract_view_proposals<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewProposalsResponse> {
+    let state = host.state();
+
+    let mut ret: Vec<(ProposalId, Proposal)> = Vec::new();
+    for (id, proposal) in state.multisig.proposals.iter() {
+        ret.push((*id, proposal.clone()));
+    }
+
+    Ok(ret)
+}
+
+// ------------------------------------------
+
+type ViewPendingChangesResponse = Vec<(ChangeId, PendingChange)>;
+
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "viewPendingChanges",
+    return_value = "ViewPendingChangesResponse"
+)]
+fn contract_view_pending_changes<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewPendingChangesResponse> {
+    let state = host.state();
+
+    let mut ret: Vec<(ChangeId, PendingChange)> = Vec::new();
+    for (id, change) in state.timelock.pending.iter() {
+        ret.push((*id, change.clone()));
+    }
+
+    Ok(ret)
+}
+
+// ------------------------------------------
+
+#[derive(Debug, Serialize, SchemaType)]
+struct ViewLotteryResponse {
+    commitment: HashSha2256,
+    seed: Option<LotterySeed>,
+    winners: Vec<(Address, u8)>,
+    losers: Vec<Address>,
+    refundable: Amount,
+    entries: u32,
+}
+
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "viewLottery",
+    return_value = "ViewLotteryResponse"
+)]
+fn contract_view_lottery<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewLotteryResponse> {
+    let state = host.state();
+    let lottery = state.get_lottery()?;
+
+    let mut winners: Vec<(Address, u8)> = Vec::new();
+    let mut losers: Vec<Address> = Vec::new();
+    if lottery.is_drawn() {
+        for (addr, user_state) in state.participants.iter() {
+            if user_state.win_units > 0 {
+                winners.push((*addr, user_state.win_units));
+            } else if user_state.deposit_ccd > Amount::zero() {
+                losers.push(*addr);
+            }
+        }
+    }
+
+    Ok(ViewLotteryResponse {
+        commitment: lottery.commitment,
+        seed: lottery.seed,
+        winners,
+        losers,
+        refundable: lottery.refundable,
+        entries: lottery.entries,
+    })
+}
+
+// ------------------------------------------
+
+type ViewEscrowResponse = Escrow;
+
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "viewEscrow",
+    return_value = "ViewEscrowResponse"
+)]
+fn contract_view_escrow<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewEscrowResponse> {
+    Ok(host.state().get_escrow()?.clone())
+}
+
+// ------------------------------------------
+
+#[derive(Debug, Serialize, SchemaType)]
+struct ViewRefundsResponse {
+    status: SaleStatus,
+    refund_hook: Option<OwnedEntrypointName>,
+    remaining_users: u32,
+    remaining_amount: Amount,
+}
+
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "viewRefunds",
+    return_value = "ViewRefundsResponse"
+)]
+fn contract_view_refunds<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewRefundsResponse> {
+    let state = host.state();
+    let (remaining_users, remaining_amount) = state.remaining_refunds();
+
+    Ok(ViewRefundsResponse {
+        status: state.status.clone(),
+        refund_hook: state.refund_hook.clone(),
+        remaining_users,
+        remaining_amount,
+    })
+}
+
+// ------------------------------------------
+
+#[derive(Debug, Serialize, SchemaType)]
+struct ViewPoolResponse {
+    project_token: Option<ContractAddress>,
+    depositor: Option<Address>,
+    funded: ContractTokenAmount,
+    required: ContractTokenAmount,
+    is_funded: bool,
+}
+
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "viewPool",
+    return_value = "ViewPoolResponse"
+)]
+fn contract_view_pool<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewPoolResponse> {
+    let state = host.state();
+
+    Ok(ViewPoolResponse {
+        project_token: state.project_token,
+        depositor: state.pool_depositor,
+        funded: state.pool_funded,
+        required: state.sold_tokens()?,
+        is_funded: state.is_pool_funded()?,
+    })
+}
+
+// ------------------------------------------
+
+type ViewTokenBalancesResponse = Vec<ViewTokenBalance>;
+
+#[derive(Debug, Serialize, SchemaType)]
+struct ViewTokenBalance {
+    token: ContractAddress,
+    token_id: TokenIdVec,
+    balance: ContractTokenAmount,
+    recoverable: ContractTokenAmount,
+}
+
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "viewTokenBalances",
+    return_value = "ViewTokenBalancesResponse"
+)]
+fn contract_view_token_balances<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<ViewTokenBalancesResponse> {
+    let state = host.state();
+
+    let mut ret: Vec<ViewTokenBalance> = Vec::new();
+    for (key, balance) in state.token_balances.iter() {
+        let (token, token_id) = key.clone();
+        ret.push(ViewTokenBalance {
+            recoverable: ContractTokenAmount::from(state.recoverable_tokens(token, &token_id)),
+            token,
+            token_id,
+            balance: *balance,
+        });
+    }
+
+    Ok(ret)
+}
+
+// ------------------------------------------
+
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "nonceOf",
+    parameter = "VecOfAccountAddresses",
+    return_value = "NonceOfQueryResponse"
+)]
+fn contract_nonce_of<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<NonceOfQueryResponse> {
+    let params: VecOfAccountAddresses = ctx.parameter_cursor().get()?;
+    let state = host.state();
+
+    Ok(NonceOfQueryResponse(
+        params
+            .queries
+            .iter()
+            .map(|account| state.nonce_of(account))
+            .collect(),
+    ))
+}
+
+// ------------------------------------------
+
+#[receive(
+    contract = "pub_rido_ccd",
+    name = "supportsPermit",
+    parameter = "SupportsPermitQueryParams",
+    return_value = "SupportsQueryResponse"
+)]
+fn contract_supports_permit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    _host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<SupportsQueryResponse> {
+    let params: SupportsPermitQueryParams = ctx.parameter_cursor().get()?;
+
+    let results = params
+        .queries
+        .iter()
+        .map(|entry_point| {
+            let entry_point: &str = entry_point.as_entrypoint_name().into();
+            if PERMIT_ENTRYPOINTS.contains(&entry_point) {
+                SupportResult::Support
+            } else {
+                SupportResult::NoSupport
+            }
+        })
+        .collect();
+    Ok(SupportsQueryResponse { results })
+}
@@ -0,0 +1,74 @@
+use crate::state::*;
+use concordium_std::*;
+
+/// Events logged by the sale contract so that indexers can follow
+/// every state transition without diffing the views.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub enum SaleEvent {
+    /// A user has been added to the whitelist.
+    Whitelisted(WhitelistedEvent),
+    /// A participant has deposited USDC to fix their allocation.
+    Deposited(DepositedEvent),
+    /// A participant has been refunded their USDC.
+    Refunded(RefundedEvent),
+    /// A participant has claimed vested project tokens.
+    Claimed(ClaimedEvent),
+    /// A sale fee has been paid out in project tokens.
+    FeeClaimed(FeeClaimedEvent),
+    /// The raised USDC has been paid out to the project admin.
+    ProjectClaimed(UsdcAmount),
+    /// The sale status has changed.
+    StatusChanged(SaleStatus),
+    /// The contract has been paused(`true`) or unpaused(`false`).
+    Paused(bool),
+    /// TGE(the beginning of the vesting period) has been set.
+    TgeSet(Timestamp),
+    /// Project token contract has been set.
+    ProjectTokenSet(ContractAddress),
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct WhitelistedEvent {
+    /// Whitelisted user
+    pub(crate) user: Address,
+    /// Priority for participation in the sale
+    pub(crate) prior: Prior,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct DepositedEvent {
+    /// Participant who deposited
+    pub(crate) user: Address,
+    /// Deposited USDC
+    pub(crate) amount: UsdcAmount,
+    /// Number of units fixed by the deposit
+    pub(crate) units: u8,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct RefundedEvent {
+    /// Participant who has been refunded
+    pub(crate) user: Address,
+    /// Refunded USDC
+    pub(crate) amount: UsdcAmount,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct ClaimedEvent {
+    /// Participant who claimed
+    pub(crate) user: Address,
+    /// Amount of project tokens transferred
+    pub(crate) amount: ContractTokenAmount,
+    /// Number of vesting steps received so far
+    pub(crate) inc: u8,
+}
+
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct FeeClaimedEvent {
+    /// Receiver of the fee
+    pub(crate) to: Address,
+    /// Amount of project tokens transferred
+    pub(crate) amount: ContractTokenAmount,
+    /// Number of vesting steps received so far
+    pub(crate) inc: u8,
+}
@@ -0,0 +1,661 @@
+//! This Contract is used for public sale with USDC on the Overlay IDO platform.
+//! Participants pay with a CIS-2 stablecoin instead of CCD,
+//! so deposits arrive through the `OnReceivingCis2Params` hook `userDeposit`.
+#[cfg(any(feature = "wasm-test", test))]
+mod sctest;
+mod events;
+mod state;
+mod view;
+
+use concordium_cis2::OnReceivingCis2Params;
+use concordium_std::{collections::BTreeMap, *};
+use events::*;
+use sale_utils::{
+    rido::{self, FeeReceiver},
+    token::transfer_token,
+    BPS_DENOMINATOR, PUBLIC_RIDO_FEE,
+};
+use state::{State, *};
+
+/// The parameter schema for `init` function.
+#[derive(Debug, Serialize, SchemaType)]
+pub struct InitParams {
+    /// Account of the administrator of the entity running the IDO
+    pub proj_admin: AccountAddress,
+    /// Address of Overlay for receiving sale fee
+    pub addr_ovl: Address,
+    /// Address of Overlay for buy back burn
+    pub addr_bbb: Address,
+    /// CIS-2 stablecoin contract participants pay with
+    pub usdc_contract: ContractAddress,
+    /// IDO schedule(The process is split into some phases)
+    pub open_at: BTreeMap<Timestamp, Prior>,
+    /// Sale End Time
+    pub close_at: Timestamp,
+    /// User(sale particicants) can withdraw assets according to the vesting period
+    /// (unlocked shares in basis points, adding up to 10,000)
+    pub vesting_period: BTreeMap<Duration, AllowedPercentage>,
+    /// Swap price of the project token
+    pub price_per_token: MicroUsdc,
+    /// Amount of project tokens contained in a unit
+    pub token_per_unit: ContractTokenAmount,
+    /// Hardcap
+    pub max_units: UnitsAmount,
+    /// Softcap
+    pub min_units: UnitsAmount,
+}
+
+/// # Init Function
+/// everyone can init this module, but need to be initialized by ovl_team
+/// since contract_id is needed to record into project contract.
+#[init(contract = "pub_rido_usdc", parameter = "InitParams")]
+fn contract_init<S: HasStateApi>(
+    ctx: &impl HasInitContext,
+    state_builder: &mut StateBuilder<S>,
+) -> InitResult<State<S>> {
+    let params: InitParams = ctx.parameter_cursor().get()?;
+
+    let schedule = SaleSchedule::new(
+        ctx.metadata().slot_time(),
+        params.open_at,
+        params.close_at,
+        VestingSchedule::Stepped(params.vesting_period),
+    )?;
+
+    let saleinfo = SaleInfo::new(
+        params.price_per_token,
+        params.token_per_unit,
+        params.max_units,
+        params.min_units,
+    )?;
+
+    Ok(State::new(
+        state_builder,
+        params.proj_admin,
+        params.addr_ovl,
+        params.addr_bbb,
+        params.usdc_contract,
+        schedule,
+        saleinfo,
+    ))
+}
+
+// ==============================================
+// For ovl team
+// ==========================================
+
+/// Some transferable functions (createPool, projectClaim, deposit, quit, userClaim)
+/// cannot be executed when the contract is paused.
+///
+/// Caller: contract instance owner only
+/// Reject if:
+/// - The sender is not the contract owner.
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "setPaused",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_paused<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    rido::set_paused(ctx, host.state_mut(), true)?;
+    logger.log(&SaleEvent::Paused(true))?;
+    Ok(())
+}
+
+/// The contract is unpaused.
+///
+/// Caller: contract instance owner only
+/// Reject if:
+/// - The sender is not the contract owner.
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "setUnpaused",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_unpaused<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    rido::set_paused(ctx, host.state_mut(), false)?;
+    logger.log(&SaleEvent::Paused(false))?;
+    Ok(())
+}
+
+/// Set status to fix for next stage(claim).
+/// Note: if not reached softcap, the sale will be cancelled.
+///
+/// Caller: contract instance owner only
+/// Reject if:
+/// - The sender is not the contract owner.
+/// - Status is not Ready
+/// - Called before the end of the sale
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "setFixed",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_fixed<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(
+        ctx.sender().matches_account(&ctx.owner()),
+        ContractError::Unauthorized
+    );
+
+    let state = host.state_mut();
+    rido::ensure_sale_closed(ctx, state)?;
+
+    if state.saleinfo.is_reached_sc() {
+        state.status = SaleStatus::Fixed;
+    } else {
+        state.status = SaleStatus::Suspend;
+    }
+    logger.log(&SaleEvent::StatusChanged(state.status.clone()))?;
+
+    Ok(())
+}
+
+/// Parameter type for the contract function `whitelisting`.
+#[derive(Debug, Serialize, SchemaType)]
+struct WhitelistingParams {
+    /// the whitelist
+    wl: Vec<AllowedUserParams>,
+    /// If true, it means no further registration
+    ready: bool,
+}
+
+#[derive(Debug, Serialize, SchemaType)]
+struct AllowedUserParams {
+    /// Users address to be whitelisted
+    user: Address,
+    /// Priority for participation in the sale
+    prior: Prior,
+}
+
+/// Whitelist users who can participate in the sale
+/// Note: All user can be allocated just one unit.
+///
+/// Caller: contract instance owner only
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is not the contract owner.
+/// - Status is not Prepare
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "whitelisting",
+    parameter = "WhitelistingParams",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_whitelisting<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(
+        ctx.sender().matches_account(&ctx.owner()),
+        ContractError::Unauthorized
+    );
+
+    let state = host.state_mut();
+    ensure_eq!(
+        state.status,
+        SaleStatus::Prepare,
+        CustomContractError::AlreadySaleStarted.into()
+    );
+
+    let params: WhitelistingParams = ctx.parameter_cursor().get()?;
+
+    // all can purchase only 1 unit;
+    for AllowedUserParams { user, prior } in params.wl {
+        if let Address::Account(_) = user {
+            state.whitelisting(&user, prior.clone());
+            logger.log(&SaleEvent::Whitelisted(WhitelistedEvent { user, prior }))?;
+        } else {
+            // [#TODO] Only support AccountAddress for now.
+            bail!(CustomContractError::AccountOnly.into())
+        };
+    }
+
+    if params.ready {
+        state.status = SaleStatus::Ready;
+        logger.log(&SaleEvent::StatusChanged(SaleStatus::Ready))?;
+    }
+
+    Ok(())
+}
+
+/// To claim sale fee for overlay team.
+/// Note: 5% for now.
+///
+/// Caller: contract instance owner only
+/// Reject if:
+/// - The sender is not the contract owner.
+/// - Status is not Fixed
+/// - Project admin has not yet registered the project token
+/// - Project admin has not yet registered the TGE
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "ovlClaim",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_ovl_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let (to, amount, inc) = rido::claim_fee(ctx, host, FeeReceiver::Ovl)?;
+
+    if amount.0 > 0 {
+        logger.log(&SaleEvent::FeeClaimed(FeeClaimedEvent { to, amount, inc }))?;
+    }
+
+    Ok(())
+}
+
+/// To claim sale fee for Buy Back Burn.
+/// Note: 5% for now.
+///
+/// Caller: contract instance owner only
+/// Reject if:
+/// - The sender is not the contract owner.
+/// - Status is not Fixed
+/// - Project admin has not yet registered the project token
+/// - Project admin has not yet registered the TGE
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "bbbClaim",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_bbb_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let (to, amount, inc) = rido::claim_fee(ctx, host, FeeReceiver::Bbb)?;
+
+    if amount.0 > 0 {
+        logger.log(&SaleEvent::FeeClaimed(FeeClaimedEvent { to, amount, inc }))?;
+    }
+
+    Ok(())
+}
+
+/// Callback function to call when CIS2 is called
+/// but no callback is needed.
+/// Caller: Anyone
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "callback",
+    parameter = "OnReceivingCis2Params<ContractTokenId, ContractTokenAmount>",
+    mutable
+)]
+fn callback<S: HasStateApi>(
+    _ctx: &impl HasReceiveContext,
+    _host: &mut impl HasHost<State<S>, StateApiType = S>,
+) -> ReceiveResult<()> {
+    Ok(())
+}
+
+// ==============================================
+// For project admin
+// ==========================================
+
+/// Set project token contract.
+///
+/// Caller: Project Admin only
+/// Reject if:
+/// - Fails to parse parameter
+/// - Already set the contract address
+/// - The sender is not the project admin
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "setPjtoken",
+    parameter = "ContractAddress",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_pjtoken<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(
+        ctx.sender().matches_account(&host.state().proj_admin),
+        ContractError::Unauthorized
+    );
+    let addr = rido::set_project_token(ctx, host.state_mut())?;
+    logger.log(&SaleEvent::ProjectTokenSet(addr))?;
+
+    Ok(())
+}
+
+/// Set TGE, which means it determines the beginning of the vesting period.
+///
+/// Caller: Project Admin only
+/// Reject if:
+/// - Fails to parse parameter
+/// - The sender is not the project admin
+/// - Already set the TGE
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "setTGE",
+    parameter = "Timestamp",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_set_tge<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    ensure!(
+        ctx.sender().matches_account(&host.state().proj_admin),
+        ContractError::Unauthorized
+    );
+    let ts = rido::set_tge(ctx, host.state_mut())?;
+    logger.log(&SaleEvent::TgeSet(ts))?;
+
+    Ok(())
+}
+
+/// Project Administrator should this function once project token are generated.
+/// The amount to be deposited must be the same as the amount sold at the sale
+/// Note: This contract is supposed to be called from a CIS2 contract
+///
+/// Caller: Project Token Contract only
+/// Invoker: Project Admin only
+/// Reject if:
+/// - Contract is paused
+/// - Fails to parse parameter
+/// - Status is not Fixed
+/// - The sender is not the project token contract
+/// - The quantity to be deposited differs from the quantity sold in the sale.
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "createPool",
+    parameter = "OnReceivingCis2Params<ContractTokenId, ContractTokenAmount>",
+    error = "ContractError"
+)]
+fn contract_create_pool<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &impl HasHost<State<S>, StateApiType = S>,
+) -> ContractResult<()> {
+    rido::create_pool(ctx, host.state())
+}
+
+/// Project admin can claim USDC sold at the sale.
+/// Note: No sale fee is charged to the project.
+///
+/// Caller: Project Admin only
+/// Reject if:
+/// - Contract is paused
+/// - Status is not Fixed
+/// - The sender is not the project admin
+/// - Already claimed
+/// - Fails to transfer USDC from this contract to the admin
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "projectClaim",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_project_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+    let proj_admin = rido::ensure_project_claimable(ctx, state)?;
+    ensure!(
+        !state.project_claimed,
+        CustomContractError::AlreadyClaimed.into()
+    );
+
+    // Unlike CCD, the contract cannot see its own USDC balance,
+    // so the amount is derived from the fixed units.
+    let amount = state.raised_usdc()?;
+    state.project_claimed = true;
+
+    let usdc_contract = state.usdc_contract;
+    transfer_token(ctx, host, usdc_contract, Address::Account(proj_admin), amount)?;
+
+    logger.log(&SaleEvent::ProjectClaimed(amount))?;
+
+    Ok(())
+}
+
+// ==============================================
+// For users
+// ==========================================
+
+/// Sale participant fix the right to purchase tokens by transferring
+/// USDC to this contract with this function as the receive hook.
+/// Note: This contract is supposed to be called from the USDC contract
+///
+/// Caller: USDC Contract only
+/// From: Anyone(Not limited to users on the whitelist)
+/// Reject if:
+/// - Contract is paused
+/// - The sender is not the configured USDC contract
+/// - Status is not Ready
+/// - Fails to parse parameter
+/// - The participant does not have valid priority
+/// - The participant have already deposited
+/// - Hardcap has already been reached
+/// - Transferred USDC not match Sale Amount
+///
+/// Rejecting here makes the USDC contract revert the transfer.
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "userDeposit",
+    parameter = "OnReceivingCis2Params<ContractTokenId, UsdcAmount>",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_user_deposit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+
+    ensure!(!state.paused, CustomContractError::ContractPaused.into());
+
+    ensure!(
+        ctx.sender() == Address::Contract(state.usdc_contract),
+        ContractError::Unauthorized
+    );
+
+    ensure!(
+        state.status == SaleStatus::Ready,
+        CustomContractError::SaleNotReady.into()
+    );
+
+    let params: OnReceivingCis2Params<ContractTokenId, UsdcAmount> =
+        ctx.parameter_cursor().get()?;
+
+    // get current priority
+    let current_priority = state
+        .schedule
+        .check_sale_priority(ctx.metadata().slot_time())
+        .ok_or(ContractError::from(CustomContractError::InvalidSchedule))?;
+
+    let room = state.saleinfo.check_room_to_apply();
+    ensure!(room > 0, CustomContractError::AlreadySaleClosed.into());
+
+    let participant = params.from;
+    let user = state.get_user_any(&participant)?;
+
+    // check already deposited
+    ensure!(
+        user.win_units == 0,
+        CustomContractError::AlreadyDeposited.into()
+    );
+
+    // check priority the user have
+    if user.prior > current_priority {
+        bail!(ContractError::Unauthorized)
+    }
+
+    // update userstate
+    let win_units: u8 = user.tgt_units;
+    ensure!(
+        room >= win_units as u32,
+        CustomContractError::AlreadySaleClosed.into()
+    );
+
+    let calculated_price = state
+        .saleinfo
+        .calc_price_per_unit_usdc()?
+        .0
+        .checked_mul(win_units as u64)
+        .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+    ensure!(
+        params.amount == UsdcAmount::from(calculated_price),
+        CustomContractError::InvalidUsdcInput.into()
+    );
+    state.deposit(&participant, params.amount, win_units)?;
+
+    logger.log(&SaleEvent::Deposited(DepositedEvent {
+        user: participant,
+        amount: params.amount,
+        units: win_units,
+    }))?;
+
+    Ok(())
+}
+
+/// Sale participants call this function to be refunded their USDC
+/// when the sale has been cancelled(not reached softcap).
+///
+/// Caller: Anyone who deposited
+/// Reject if:
+/// - Contract is paused
+/// - Status is not Suspend
+/// - The sender is not on the whitelist
+/// - The sender has not deposited.
+/// - Fails to transfer USDC from this contract to the sender
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "userQuit",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_user_quit<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+    ensure!(!state.paused, CustomContractError::ContractPaused.into());
+
+    // currently no one can quit unless the sale is cancelled
+    ensure_eq!(
+        state.status,
+        SaleStatus::Suspend,
+        CustomContractError::DisabledForNow.into()
+    );
+
+    let sender = ctx.sender();
+    let user = state.get_user(&sender)?;
+
+    ensure!(user.win_units > 0, CustomContractError::NotDeposited.into());
+
+    state.remove_participant(&sender, user.win_units);
+
+    let usdc_contract = state.usdc_contract;
+    transfer_token(ctx, host, usdc_contract, sender, user.deposit_usdc)?;
+
+    logger.log(&SaleEvent::Refunded(RefundedEvent {
+        user: sender,
+        amount: user.deposit_usdc,
+    }))?;
+
+    Ok(())
+}
+
+/// Sale participants can claim project token when the vesting period arrives.
+/// Note: If a user claims many times within a certain period of time,
+/// they will just get 0 back.
+///
+/// Caller: Anyone on the whitelist
+/// Reject if:
+/// - Contract is paused
+/// - Status is not Fixed
+/// - Project admin has not yet registered the project token
+/// - Project admin has not yet registered the TGE
+/// - The sender is not on the whitelist
+#[receive(
+    contract = "pub_rido_usdc",
+    name = "userClaim",
+    error = "ContractError",
+    event = "SaleEvent",
+    mutable,
+    enable_logger
+)]
+fn contract_user_claim<S: HasStateApi>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<State<S>, StateApiType = S>,
+    logger: &mut impl HasLogger,
+) -> ContractResult<()> {
+    let state = host.state_mut();
+
+    ensure!(!state.paused, CustomContractError::ContractPaused.into());
+    let (project_token, vesting_start) = rido::ensure_claimable(state)?;
+
+    let user = ctx.sender();
+    let user_state = state.get_user(&user)?;
+
+    let (amount, inc): (ContractTokenAmount, u8) = state.calc_vesting_amount(
+        ctx.metadata().slot_time(),
+        vesting_start,
+        user_state.win_units as u64,
+        BPS_DENOMINATOR - PUBLIC_RIDO_FEE,
+        user_state.claimed_inc,
+    )?;
+
+    if inc > user_state.claimed_inc {
+        state.increment_user_claimed(&user, inc)?;
+    }
+
+    if amount.0 > 0 {
+        transfer_token(ctx, host, project_token, user, amount)?;
+        logger.log(&SaleEvent::Claimed(ClaimedEvent { user, amount, inc }))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,3 @@
+mod overlay_team;
+mod participant;
+mod project_admin;
@@ -0,0 +1,168 @@
+use concordium_std::concordium_cfg_test;
+
+#[concordium_cfg_test]
+mod tests {
+    use crate::*;
+    use concordium_std::test_infrastructure::*;
+
+    #[concordium_test]
+    /// Test that init succeeds.
+    fn test_init() {
+        let mut state_builder = TestStateBuilder::new();
+        let invoker = AccountAddress([0u8; 32]);
+        let slot_time = Timestamp::from_timestamp_millis(1);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
+        let addr_bbb = Address::Contract(ContractAddress {
+            index: 100,
+            subindex: 0,
+        });
+        let usdc_contract = ContractAddress {
+            index: 2000,
+            subindex: 0,
+        };
+        let open_at = BTreeMap::from([
+            (Timestamp::from_timestamp_millis(10), Prior::TOP),
+            (Timestamp::from_timestamp_millis(20), Prior::SECOND),
+        ]);
+        let close_at = Timestamp::from_timestamp_millis(30);
+        let vesting_period = BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]);
+        let max_units = 100;
+        let min_units = 50;
+        let price_per_token = 50_000;
+        let token_per_unit = 200.into();
+
+        let expected_state = State {
+            proj_admin,
+            status: SaleStatus::Prepare,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            usdc_contract,
+            project_claimed: false,
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: open_at.clone(),
+                close_at,
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(vesting_period.clone()),
+            },
+            saleinfo: SaleInfo {
+                price_per_token,
+                token_per_unit,
+                max_units,
+                min_units,
+                applied_units: 0,
+            },
+            participants: state_builder.new_map(),
+        };
+
+        // set init context
+        let mut ctx = TestInitContext::empty();
+        ctx.set_init_origin(invoker);
+        ctx.set_metadata_slot_time(slot_time);
+
+        // create params
+        let params = InitParams {
+            proj_admin,
+            addr_ovl,
+            addr_bbb,
+            usdc_contract,
+            open_at,
+            close_at,
+            max_units,
+            min_units,
+            price_per_token,
+            token_per_unit,
+            vesting_period,
+        };
+        let params_byte = to_bytes(&params);
+        ctx.set_parameter(&params_byte);
+
+        // execute init
+        let result = contract_init(&ctx, &mut state_builder);
+        claim!(result.is_ok());
+        let actual_state = result.unwrap();
+        claim_eq!(
+            actual_state,
+            expected_state,
+            "state has been initialized unexpectedly..."
+        );
+    }
+
+    #[concordium_test]
+    /// Test that setFixed cancels a sale below the softcap only once.
+    fn test_set_fixed() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
+        let addr_bbb = Address::Account(AccountAddress([3u8; 32]));
+        let usdc_contract = ContractAddress {
+            index: 2000,
+            subindex: 0,
+        };
+        let initial_state = State {
+            proj_admin,
+            status: SaleStatus::Ready,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            usdc_contract,
+            project_claimed: false,
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: BTreeMap::from([(Timestamp::from_timestamp_millis(10), Prior::TOP)]),
+                close_at: Timestamp::from_timestamp_millis(30),
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(BTreeMap::from([(
+                    Duration::from_days(1),
+                    10_000,
+                )])),
+            },
+            saleinfo: SaleInfo {
+                price_per_token: 50_000,
+                token_per_unit: 200.into(),
+                max_units: 100,
+                min_units: 50,
+                applied_units: 49,
+            },
+            participants: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(admin));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(31));
+        let mut logger = TestLogger::init();
+
+        let result = contract_set_fixed(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok(), "Results in rejection");
+        claim_eq!(host.state().status, SaleStatus::Suspend);
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::StatusChanged(SaleStatus::Suspend))],
+            "events have been logged unexpectedly..."
+        );
+
+        // the cancelled sale cannot be fixed afterwards
+        host.state_mut().saleinfo.applied_units = 50;
+        let result = contract_set_fixed(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("setFixed should reject a sale already cancelled");
+        claim_eq!(
+            err,
+            CustomContractError::SaleNotReady.into(),
+            "setFixed should reject a sale already cancelled"
+        );
+        claim_eq!(host.state().status, SaleStatus::Suspend);
+    }
+}
@@ -0,0 +1,322 @@
+use concordium_std::concordium_cfg_test;
+
+#[concordium_cfg_test]
+mod tests {
+    use crate::*;
+    use concordium_cis2::{AdditionalData, Receiver, TokenIdUnit, Transfer, TransferParams};
+    use concordium_std::test_infrastructure::*;
+
+    #[concordium_test]
+    /// Test that userDeposit accepts USDC only from the configured token contract
+    /// and updates user state.
+    fn test_user_deposit() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let first_user = AccountAddress([10u8; 32]);
+        let deposit_amount = UsdcAmount::from(50_000 * 200 * 1);
+        let usdc_contract = ContractAddress {
+            index: 2000,
+            subindex: 0,
+        };
+        let other_token = ContractAddress {
+            index: 3000,
+            subindex: 0,
+        };
+        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
+        let addr_bbb = Address::Contract(ContractAddress {
+            index: 100,
+            subindex: 0,
+        });
+        let open_at = BTreeMap::from([
+            (Timestamp::from_timestamp_millis(10), Prior::TOP),
+            (Timestamp::from_timestamp_millis(20), Prior::SECOND),
+        ]);
+        let close_at = Timestamp::from_timestamp_millis(30);
+        let vesting_period = BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]);
+        let max_units = 100;
+        let min_units = 50;
+        let price_per_token = 50_000;
+        let token_per_unit = 200.into();
+
+        let mut participants = state_builder.new_map();
+        participants.insert(
+            Address::Account(first_user),
+            UserState::new(Prior::TOP, UsdcAmount::from(0), TARGET_UNITS),
+        );
+        let initial_state = State {
+            proj_admin,
+            status: SaleStatus::Ready,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            usdc_contract,
+            project_claimed: false,
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: open_at.clone(),
+                close_at,
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(vesting_period.clone()),
+            },
+            saleinfo: SaleInfo {
+                price_per_token,
+                token_per_unit,
+                max_units,
+                min_units,
+                applied_units: 0,
+            },
+            participants,
+        };
+        let mut expected_participants = state_builder.new_map();
+        expected_participants.insert(
+            Address::Account(first_user),
+            UserState {
+                prior: Prior::TOP,
+                deposit_usdc: deposit_amount,
+                tgt_units: TARGET_UNITS,
+                win_units: 1,
+                claimed_inc: 0,
+            },
+        );
+        let expected_state = State {
+            proj_admin,
+            status: SaleStatus::Ready,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            usdc_contract,
+            project_claimed: false,
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: open_at.clone(),
+                close_at,
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(vesting_period.clone()),
+            },
+            saleinfo: SaleInfo {
+                price_per_token,
+                token_per_unit,
+                max_units,
+                min_units,
+                applied_units: 1,
+            },
+            participants: expected_participants,
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+
+        let params = OnReceivingCis2Params {
+            token_id: TokenIdUnit(),
+            amount: deposit_amount,
+            from: Address::Account(first_user),
+            data: AdditionalData::empty(),
+        };
+        let params_byes = to_bytes(&params);
+
+        // tokens from other contracts are rejected
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Contract(other_token));
+        ctx.set_invoker(first_user);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(15));
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_user_deposit(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("userDeposit should reject other tokens");
+        claim_eq!(
+            err,
+            ContractError::Unauthorized,
+            "userDeposit should reject other tokens"
+        );
+
+        // amount must match the unit price
+        let wrong_params = OnReceivingCis2Params {
+            token_id: TokenIdUnit(),
+            amount: UsdcAmount::from(1),
+            from: Address::Account(first_user),
+            data: AdditionalData::empty(),
+        };
+        let wrong_params_byes = to_bytes(&wrong_params);
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Contract(usdc_contract));
+        ctx.set_invoker(first_user);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(15));
+        ctx.set_parameter(&wrong_params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_user_deposit(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("userDeposit should reject wrong amount");
+        claim_eq!(
+            err,
+            CustomContractError::InvalidUsdcInput.into(),
+            "userDeposit should reject wrong amount"
+        );
+
+        // deposit
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Contract(usdc_contract));
+        ctx.set_invoker(first_user);
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(15));
+        ctx.set_parameter(&params_byes);
+        let mut logger = TestLogger::init();
+        let result = contract_user_deposit(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim_eq!(*host.state(), expected_state);
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::Deposited(DepositedEvent {
+                user: Address::Account(first_user),
+                amount: deposit_amount,
+                units: 1,
+            }))],
+            "Deposited event should be logged."
+        );
+    }
+
+    #[concordium_test]
+    /// Test that userQuit refunds USDC through CIS-2 transfer when the sale is suspended.
+    fn test_user_quit() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let first_user = AccountAddress([10u8; 32]);
+        let self_address = ContractAddress::new(500, 0);
+        let deposit_amount = UsdcAmount::from(50_000 * 200 * 1);
+        let usdc_contract = ContractAddress {
+            index: 2000,
+            subindex: 0,
+        };
+        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
+        let addr_bbb = Address::Contract(ContractAddress {
+            index: 100,
+            subindex: 0,
+        });
+        let open_at = BTreeMap::from([
+            (Timestamp::from_timestamp_millis(10), Prior::TOP),
+            (Timestamp::from_timestamp_millis(20), Prior::SECOND),
+        ]);
+        let close_at = Timestamp::from_timestamp_millis(30);
+        let vesting_period = BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]);
+        let max_units = 100;
+        let min_units = 50;
+        let price_per_token = 50_000;
+        let token_per_unit = 200.into();
+
+        let mut participants = state_builder.new_map();
+        participants.insert(
+            Address::Account(first_user),
+            UserState {
+                prior: Prior::TOP,
+                deposit_usdc: deposit_amount,
+                tgt_units: TARGET_UNITS,
+                win_units: 1,
+                claimed_inc: 0,
+            },
+        );
+        let initial_state = State {
+            proj_admin,
+            status: SaleStatus::Suspend,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            usdc_contract,
+            project_claimed: false,
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: open_at.clone(),
+                close_at,
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(vesting_period.clone()),
+            },
+            saleinfo: SaleInfo {
+                price_per_token,
+                token_per_unit,
+                max_units,
+                min_units,
+                applied_units: 1,
+            },
+            participants,
+        };
+        let expected_state = State {
+            proj_admin,
+            status: SaleStatus::Suspend,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            usdc_contract,
+            project_claimed: false,
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: open_at.clone(),
+                close_at,
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(vesting_period.clone()),
+            },
+            saleinfo: SaleInfo {
+                price_per_token,
+                token_per_unit,
+                max_units,
+                min_units,
+                applied_units: 0,
+            },
+            participants: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.setup_mock_entrypoint(
+            usdc_contract,
+            OwnedEntrypointName::new_unchecked("transfer".into()),
+            MockFn::new_v1(move |parameter, _amount, _balance, _state| {
+                let transfer = Transfer {
+                    from: Address::from(self_address),
+                    to: Receiver::Account(first_user),
+                    token_id: TokenIdUnit(),
+                    amount: deposit_amount,
+                    data: AdditionalData::empty(),
+                };
+                let transfer_params = TransferParams::from(vec![transfer]);
+                let expected_bytes = to_bytes(&transfer_params);
+                let param_bytes = parameter.as_ref();
+                claim_eq!(param_bytes, expected_bytes);
+                Ok((false, ()))
+            }),
+        );
+
+        // create params
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_self_address(self_address);
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(first_user));
+        ctx.set_metadata_slot_time(Timestamp::from_timestamp_millis(40));
+
+        // execute function
+        let mut logger = TestLogger::init();
+        let result = contract_user_quit(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim_eq!(*host.state(), expected_state);
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::Refunded(RefundedEvent {
+                user: Address::Account(first_user),
+                amount: deposit_amount,
+            }))],
+            "Refunded event should be logged."
+        );
+    }
+}
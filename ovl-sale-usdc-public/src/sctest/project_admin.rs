@@ -0,0 +1,143 @@
+use concordium_std::concordium_cfg_test;
+
+#[concordium_cfg_test]
+mod tests {
+    use crate::*;
+    use concordium_cis2::{AdditionalData, Receiver, TokenIdUnit, Transfer, TransferParams};
+    use concordium_std::test_infrastructure::*;
+
+    #[concordium_test]
+    /// Test that projectClaim pays the raised USDC to the project admin only once.
+    fn test_project_claim() {
+        let mut state_builder = TestStateBuilder::new();
+        let admin = AccountAddress([0u8; 32]);
+        let proj_admin = AccountAddress([1u8; 32]);
+        let self_address = ContractAddress::new(500, 0);
+        let usdc_contract = ContractAddress {
+            index: 2000,
+            subindex: 0,
+        };
+        let addr_ovl = Address::Account(AccountAddress([2u8; 32]));
+        let addr_bbb = Address::Contract(ContractAddress {
+            index: 100,
+            subindex: 0,
+        });
+        let open_at = BTreeMap::from([
+            (Timestamp::from_timestamp_millis(10), Prior::TOP),
+            (Timestamp::from_timestamp_millis(20), Prior::SECOND),
+        ]);
+        let close_at = Timestamp::from_timestamp_millis(30);
+        let vesting_period = BTreeMap::from([
+            (Duration::from_days(1), 2_500),
+            (Duration::from_days(2), 4_000),
+            (Duration::from_days(3), 3_500),
+        ]);
+        let max_units = 100;
+        let min_units = 50;
+        let price_per_token = 50_000;
+        let token_per_unit = 200.into();
+        let amount_to_claim = UsdcAmount::from(50_000 * 200 * min_units as u64);
+        let initial_state = State {
+            proj_admin,
+            status: SaleStatus::Fixed,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            usdc_contract,
+            project_claimed: false,
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: open_at.clone(),
+                close_at,
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(vesting_period.clone()),
+            },
+            saleinfo: SaleInfo {
+                price_per_token,
+                token_per_unit,
+                max_units,
+                min_units,
+                applied_units: min_units,
+            },
+            participants: state_builder.new_map(),
+        };
+        let expected_state = State {
+            proj_admin,
+            status: SaleStatus::Fixed,
+            paused: false,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            usdc_contract,
+            project_claimed: true,
+            project_token: None,
+            schedule: SaleSchedule {
+                open_at: open_at.clone(),
+                close_at,
+                vesting_start: None,
+                vesting_period: VestingSchedule::Stepped(vesting_period.clone()),
+            },
+            saleinfo: SaleInfo {
+                price_per_token,
+                token_per_unit,
+                max_units,
+                min_units,
+                applied_units: min_units,
+            },
+            participants: state_builder.new_map(),
+        };
+        let mut host = TestHost::new(initial_state, state_builder);
+        host.setup_mock_entrypoint(
+            usdc_contract,
+            OwnedEntrypointName::new_unchecked("transfer".into()),
+            MockFn::new_v1(move |parameter, _amount, _balance, _state| {
+                let transfer = Transfer {
+                    from: Address::from(self_address),
+                    to: Receiver::Account(proj_admin),
+                    token_id: TokenIdUnit(),
+                    amount: amount_to_claim,
+                    data: AdditionalData::empty(),
+                };
+                let transfer_params = TransferParams::from(vec![transfer]);
+                let expected_bytes = to_bytes(&transfer_params);
+                let param_bytes = parameter.as_ref();
+                claim_eq!(param_bytes, expected_bytes);
+                Ok((false, ()))
+            }),
+        );
+
+        // create params
+        let mut ctx = TestReceiveContext::empty();
+        ctx.set_self_address(self_address);
+        ctx.set_owner(admin);
+        ctx.set_sender(Address::Account(proj_admin));
+
+        // execute func
+        let mut logger = TestLogger::init();
+        let result = contract_project_claim(&ctx, &mut host, &mut logger);
+        claim!(result.is_ok());
+        claim_eq!(
+            *host.state(),
+            expected_state,
+            "state has been changed unexpectedly..."
+        );
+        claim_eq!(
+            logger.logs,
+            vec![to_bytes(&SaleEvent::ProjectClaimed(amount_to_claim))],
+            "ProjectClaimed event should be logged."
+        );
+
+        // second claim is rejected
+        let mut logger = TestLogger::init();
+        let result = contract_project_claim(&ctx, &mut host, &mut logger);
+        let err = result.expect_err_report("projectClaim should reject the second claim");
+        claim_eq!(
+            err,
+            CustomContractError::AlreadyClaimed.into(),
+            "projectClaim should reject the second claim"
+        );
+    }
+}
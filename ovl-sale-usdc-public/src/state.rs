@@ -0,0 +1,285 @@
+use concordium_std::{SchemaType, Serialize, *};
+pub use sale_utils::{
+    error::{ContractError, ContractResult, CustomContractError},
+    sale::*,
+    types::*,
+};
+use sale_utils::{
+    rido::{FeeReceiver, RidoFees, RidoState},
+    PUBLIC_RIDO_FEE_BBB, PUBLIC_RIDO_FEE_OVL,
+};
+
+/// All participants can purchase only 1 unit.
+pub const TARGET_UNITS: u8 = 1;
+
+/// The contract state
+#[derive(Debug, Serial, DeserialWithState, StateClone)]
+#[concordium(state_parameter = "S")]
+pub struct State<S: HasStateApi> {
+    /// Account of the administrator of the entity running the IDO
+    pub(crate) proj_admin: AccountAddress,
+    /// Enum for sale status
+    pub(crate) status: SaleStatus,
+    /// If `true`, some functions will stop working
+    pub(crate) paused: bool,
+    /// Address of Overlay for receiving sale fee
+    pub(crate) addr_ovl: Address,
+    /// Address of Overlay for buy back burn
+    pub(crate) addr_bbb: Address,
+    /// Number of how many fee received
+    pub(crate) ovl_claimed_inc: u8,
+    /// Number of how many fee for BBB received
+    pub(crate) bbb_claimed_inc: u8,
+    /// CIS-2 stablecoin contract participants pay with
+    pub(crate) usdc_contract: ContractAddress,
+    /// If `true`, the project admin has already received the raised USDC
+    pub(crate) project_claimed: bool,
+    /// Project token contract address for RIDO
+    pub(crate) project_token: Option<ContractAddress>,
+    /// Sale schedule
+    pub(crate) schedule: SaleSchedule,
+    /// Information about sale
+    pub(crate) saleinfo: SaleInfo,
+    /// Sale participants
+    pub(crate) participants: StateMap<Address, UserState, S>,
+}
+
+impl<S: HasStateApi> State<S> {
+    pub(crate) fn new(
+        state_builder: &mut StateBuilder<S>,
+        proj_admin: AccountAddress,
+        addr_ovl: Address,
+        addr_bbb: Address,
+        usdc_contract: ContractAddress,
+        schedule: SaleSchedule,
+        saleinfo: SaleInfo,
+    ) -> Self {
+        State {
+            proj_admin,
+            paused: false,
+            status: SaleStatus::Prepare,
+            addr_ovl,
+            addr_bbb,
+            ovl_claimed_inc: 0,
+            bbb_claimed_inc: 0,
+            usdc_contract,
+            project_claimed: false,
+            project_token: None,
+            schedule,
+            saleinfo,
+            participants: state_builder.new_map(),
+        }
+    }
+
+    pub(crate) fn calc_vesting_amount(
+        &self,
+        now: Timestamp,
+        vesting_start: Timestamp,
+        total_units: u64,
+        shared: BasisPoints,
+        cur_inc: u8,
+    ) -> ContractResult<(ContractTokenAmount, u8)> {
+        self.schedule.calc_vesting_amount(
+            now,
+            vesting_start,
+            self.saleinfo.token_per_unit,
+            total_units,
+            shared,
+            cur_inc,
+        )
+    }
+
+    /// Total USDC paid by the participants who fixed their units.
+    pub(crate) fn raised_usdc(&self) -> ContractResult<UsdcAmount> {
+        let raised = self
+            .saleinfo
+            .calc_price_per_unit_usdc()?
+            .0
+            .checked_mul(self.saleinfo.applied_units as u64)
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+        Ok(UsdcAmount::from(raised))
+    }
+
+    pub(crate) fn whitelisting(&mut self, user: &Address, prior: Prior) {
+        self.participants
+            .entry(*user)
+            .or_insert_with(|| UserState::new(prior, UsdcAmount::from(0), TARGET_UNITS));
+    }
+
+    pub(crate) fn get_user_any(&mut self, user: &Address) -> ContractResult<UserState> {
+        let user = self
+            .participants
+            .entry(*user)
+            .or_insert_with(|| UserState::new(Prior::ANY, UsdcAmount::from(0), TARGET_UNITS));
+        let user = user.get_ref();
+        Ok(user.clone())
+    }
+
+    pub(crate) fn get_user(&self, user: &Address) -> ContractResult<UserState> {
+        let user = self
+            .participants
+            .get(user)
+            .ok_or(ContractError::Unauthorized)?;
+        Ok(user.clone())
+    }
+
+    pub(crate) fn deposit(
+        &mut self,
+        user: &Address,
+        amount: UsdcAmount,
+        win_units: u8,
+    ) -> ContractResult<()> {
+        let mut user = self
+            .participants
+            .get_mut(user)
+            .ok_or(ContractError::Unauthorized)?;
+        user.deposit_usdc = amount;
+        user.win_units = win_units;
+
+        self.saleinfo.applied_units += win_units as UnitsAmount;
+        Ok(())
+    }
+
+    pub(crate) fn increment_user_claimed(&mut self, user: &Address, n: u8) -> ContractResult<()> {
+        let mut user = self
+            .participants
+            .get_mut(user)
+            .ok_or(ContractError::Unauthorized)?;
+        user.claimed_inc = n;
+        Ok(())
+    }
+
+    pub(crate) fn remove_participant(&mut self, user: &Address, tgt_units: u8) {
+        self.participants.remove(user);
+        self.saleinfo.applied_units -= tgt_units as UnitsAmount;
+    }
+}
+
+impl<S: HasStateApi> RidoState for State<S> {
+    fn proj_admin(&self) -> AccountAddress {
+        self.proj_admin
+    }
+
+    fn status(&self) -> &SaleStatus {
+        &self.status
+    }
+
+    fn paused(&self) -> bool {
+        self.paused
+    }
+
+    fn paused_mut(&mut self) -> &mut bool {
+        &mut self.paused
+    }
+
+    fn project_token(&self) -> Option<ContractAddress> {
+        self.project_token
+    }
+
+    fn project_token_mut(&mut self) -> &mut Option<ContractAddress> {
+        &mut self.project_token
+    }
+
+    fn schedule(&self) -> &SaleSchedule {
+        &self.schedule
+    }
+
+    fn schedule_mut(&mut self) -> &mut SaleSchedule {
+        &mut self.schedule
+    }
+}
+
+impl<S: HasStateApi> RidoFees for State<S> {
+    fn sold_tokens(&self) -> ContractResult<ContractTokenAmount> {
+        Ok(self.saleinfo.amount_of_pjtoken()?)
+    }
+
+    fn fee_receiver(&self, fee: FeeReceiver) -> Address {
+        match fee {
+            FeeReceiver::Ovl => self.addr_ovl,
+            FeeReceiver::Bbb => self.addr_bbb,
+        }
+    }
+
+    fn fee_share(&self, fee: FeeReceiver) -> BasisPoints {
+        match fee {
+            FeeReceiver::Ovl => PUBLIC_RIDO_FEE_OVL,
+            FeeReceiver::Bbb => PUBLIC_RIDO_FEE_BBB,
+        }
+    }
+
+    fn fee_claimed_inc(&self, fee: FeeReceiver) -> u8 {
+        match fee {
+            FeeReceiver::Ovl => self.ovl_claimed_inc,
+            FeeReceiver::Bbb => self.bbb_claimed_inc,
+        }
+    }
+
+    fn set_fee_claimed_inc(&mut self, fee: FeeReceiver, inc: u8) {
+        match fee {
+            FeeReceiver::Ovl => self.ovl_claimed_inc = inc,
+            FeeReceiver::Bbb => self.bbb_claimed_inc = inc,
+        }
+    }
+}
+
+#[cfg(any(feature = "wasm-test", test))]
+/// implements PartialEq for `claim_eq` inside test functions.
+/// this implementation will be build only when `concordium-std/wasm-test` feature is active.
+/// (e.g. when launched by `cargo concordium test`)
+impl<S: HasStateApi> PartialEq for State<S> {
+    fn eq(&self, other: &Self) -> bool {
+        if self.proj_admin != other.proj_admin
+            || self.status != other.status
+            || self.paused != other.paused
+            || self.addr_ovl != other.addr_ovl
+            || self.addr_bbb != other.addr_bbb
+            || self.ovl_claimed_inc != other.ovl_claimed_inc
+            || self.bbb_claimed_inc != other.bbb_claimed_inc
+            || self.usdc_contract != other.usdc_contract
+            || self.project_claimed != other.project_claimed
+            || self.project_token != other.project_token
+            || self.schedule != other.schedule
+            || self.saleinfo != other.saleinfo
+        {
+            return false;
+        }
+        if self.participants.iter().count() != other.participants.iter().count() {
+            return false;
+        }
+        for (addr, user_state) in self.participants.iter() {
+            match other.participants.get(&addr) {
+                Some(other_user_state) if user_state.clone() == other_user_state.clone() => {},
+                _ => return false,
+            }
+        }
+        true
+    }
+}
+
+/// About sale participants
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct UserState {
+    /// Priority to participate in the sale
+    pub(crate) prior: Prior,
+    /// If deposited, their right to receive tokens will be confirmed.
+    pub(crate) deposit_usdc: UsdcAmount,
+    /// Number of unit desired(or available) to be purchased
+    pub(crate) tgt_units: u8,
+    /// Number actually determined to be purchased
+    pub(crate) win_units: u8,
+    /// Number of tokens received during the vesting period(neither Amount or number of claim)
+    pub(crate) claimed_inc: u8,
+}
+
+impl UserState {
+    pub fn new(prior: Prior, deposit_usdc: UsdcAmount, tgt_units: u8) -> Self {
+        UserState {
+            prior,
+            deposit_usdc,
+            tgt_units,
+            win_units: 0,
+            claimed_inc: 0,
+        }
+    }
+}
@@ -1,6 +1,7 @@
 use concordium_cis2::Cis2Error;
 use concordium_std::{
-    num, CallContractError, ParseError, Reject, SchemaType, Serialize, UnwrapAbort, UpgradeError,
+    num, CallContractError, LogError, ParseError, Reject, SchemaType, Serialize, UnwrapAbort,
+    UpgradeError,
 };
 use core::num::TryFromIntError;
 
@@ -41,7 +42,46 @@ pub enum CustomContractError {
     NotSetTge,                             //
     NotSetProjectToken,                    //
     Inappropriate,                         //
-    DisabledForNow,                        //
+    DisabledForNow,                        //30
+    LogFull,                               //
+    LogMalformed,                          //
+    UnknownProposal,                       //
+    AlreadyApproved,                       //
+    NotEnoughApprovals,                    //35
+    UnknownChange,                         //
+    NotEffectiveYet,                       //
+    AlreadyEffective,                      //
+    InvalidUsdcInput,                      //
+    AlreadyClaimed,                        //40
+    UnknownTier,                           //
+    InvalidTierUnits,                      //
+    NotLottery,                            //
+    AlreadyDrawn,                          //
+    NotDrawn,                              //45
+    InvalidSeed,                           //
+    NotOverflow,                           //
+    NotSettled,                            //
+    InvalidSaleMode,                       //
+    ClaimsOutstanding,                     //50
+    InvalidFeeTable,                       //
+    UnknownFeeRecipient,                   //
+    NotEscrow,                             //
+    InvalidEscrow,                         //
+    MilestoneNotReached,                   //55
+    AlreadyVoted,                          //
+    EscrowFailed,                          //
+    NotDelivered,                          //
+    MissingRefundHook,                     //
+    InvalidTicket,                         //60
+    TicketExpired,                         //
+    InvalidProof,                          //
+    InvalidPermit,                         //
+    PermitExpired,                         //
+    NonceMismatch,                         //65
+    WrongSignature,                        //
+    InvalidPage,                           //
+    RevealOverdue,                         //
+    TooManyEntries,                        //
 }
 
 impl From<CustomContractError> for ContractError {
@@ -67,6 +107,16 @@ impl<T> From<CallContractError<T>> for CustomContractError {
     }
 }
 
+impl From<LogError> for CustomContractError {
+    #[inline(always)]
+    fn from(le: LogError) -> Self {
+        match le {
+            LogError::Full => Self::LogFull,
+            LogError::Malformed => Self::LogMalformed,
+        }
+    }
+}
+
 impl From<UpgradeError> for CustomContractError {
     #[inline(always)]
     fn from(ue: UpgradeError) -> Self {
@@ -1,7 +1,18 @@
 pub mod error;
+pub mod merkle;
+pub mod rido;
+pub mod sale;
+pub mod token;
 pub mod types;
 
-pub const PRIVATE_RIDO_FEE: u8 = 10;
-pub const PUBLIC_RIDO_FEE: u8 = 10;
-pub const PUBLIC_RIDO_FEE_OVL: u8 = 5;
-pub const PUBLIC_RIDO_FEE_BBB: u8 = 5;
+use types::BasisPoints;
+
+/// 100% in `BasisPoints`
+pub const BPS_DENOMINATOR: BasisPoints = 10_000;
+
+pub const PRIVATE_RIDO_FEE: BasisPoints = 1_000;
+pub const PRIVATE_RIDO_FEE_OVL: BasisPoints = 500;
+pub const PRIVATE_RIDO_FEE_BBB: BasisPoints = 500;
+pub const PUBLIC_RIDO_FEE: BasisPoints = 1_000;
+pub const PUBLIC_RIDO_FEE_OVL: BasisPoints = 500;
+pub const PUBLIC_RIDO_FEE_BBB: BasisPoints = 500;
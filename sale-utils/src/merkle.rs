@@ -0,0 +1,166 @@
+//! Merkle tree committing to a whitelist, so that the sale contracts store only its root.
+//! The Overlay team builds the tree off-chain with `MerkleTree` and hands each user
+//! their proof, which `MerkleRoot::verify` checks on-chain.
+//!
+//! The same code hashes on both sides, through `HasCryptoPrimitives`.
+//! Off-chain, use `TestCryptoPrimitives` with the `crypto-primitives` feature enabled.
+use crate::{error::CustomContractError, types::*};
+use concordium_std::{collections::BTreeMap, *};
+
+/// Node of the tree
+pub type MerkleNode = [u8; 32];
+
+/// Domain separation of leaves and inner nodes, so that a node cannot be passed off as a leaf.
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+
+/// Hash function of the tree.
+#[derive(Debug, Serialize, SchemaType, Clone, Copy, PartialEq, Eq)]
+pub enum MerkleHash {
+    Sha2_256,
+    Keccak256,
+}
+
+impl MerkleHash {
+    fn digest(self, crypto_primitives: &impl HasCryptoPrimitives, data: &[u8]) -> MerkleNode {
+        match self {
+            MerkleHash::Sha2_256 => crypto_primitives.hash_sha2_256(data).0,
+            MerkleHash::Keccak256 => crypto_primitives.hash_keccak_256(data).0,
+        }
+    }
+
+    fn leaf(
+        self,
+        crypto_primitives: &impl HasCryptoPrimitives,
+        leaf: &WhitelistLeaf,
+    ) -> MerkleNode {
+        let mut data = vec![LEAF_PREFIX];
+        data.extend(to_bytes(leaf));
+        self.digest(crypto_primitives, &data)
+    }
+
+    /// Children are sorted, so that proofs need not tell on which side each sibling is.
+    fn node(
+        self,
+        crypto_primitives: &impl HasCryptoPrimitives,
+        a: &MerkleNode,
+        b: &MerkleNode,
+    ) -> MerkleNode {
+        let (left, right) = if a <= b { (a, b) } else { (b, a) };
+        let mut data = vec![NODE_PREFIX];
+        data.extend_from_slice(left);
+        data.extend_from_slice(right);
+        self.digest(crypto_primitives, &data)
+    }
+}
+
+/// Leaf of the tree: a user on the whitelist and the units they can purchase.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct WhitelistLeaf {
+    pub user: Address,
+    pub prior: Prior,
+    pub units: u8,
+}
+
+/// Commitment to a whitelist stored by the sale contracts.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct MerkleRoot {
+    pub hash: MerkleHash,
+    pub root: MerkleNode,
+}
+
+impl MerkleRoot {
+    /// Whether `leaf` is in the tree, given the siblings on the path from it to the root.
+    pub fn verify(
+        &self,
+        crypto_primitives: &impl HasCryptoPrimitives,
+        leaf: &WhitelistLeaf,
+        proof: &[MerkleNode],
+    ) -> bool {
+        let computed = proof
+            .iter()
+            .fold(self.hash.leaf(crypto_primitives, leaf), |node, sibling| {
+                self.hash.node(crypto_primitives, &node, sibling)
+            });
+        computed == self.root
+    }
+}
+
+/// Tree over a whitelist, built off-chain.
+/// A node without sibling is moved up to the next level as it is.
+pub struct MerkleTree {
+    hash: MerkleHash,
+    leaves: Vec<WhitelistLeaf>,
+    /// Nodes of each level, from the leaves up to the root
+    levels: Vec<Vec<MerkleNode>>,
+}
+
+impl MerkleTree {
+    /// Builds the tree of `users`, each allocated the units configured for their tier
+    /// (the `tier_units` given to the sale at init).
+    /// Fails if the whitelist is empty or a tier has no units configured.
+    pub fn new(
+        crypto_primitives: &impl HasCryptoPrimitives,
+        hash: MerkleHash,
+        users: &[AllowedUserParams],
+        tier_units: &BTreeMap<Tier, u8>,
+    ) -> Result<Self, CustomContractError> {
+        ensure!(!users.is_empty(), CustomContractError::Inappropriate);
+        let mut leaves = Vec::with_capacity(users.len());
+        for AllowedUserParams { user, prior, tier } in users {
+            let units = tier_units
+                .get(tier)
+                .copied()
+                .ok_or(CustomContractError::UnknownTier)?;
+            leaves.push(WhitelistLeaf {
+                user: *user,
+                prior: prior.clone(),
+                units,
+            });
+        }
+
+        let mut levels: Vec<Vec<MerkleNode>> = vec![leaves
+            .iter()
+            .map(|leaf| hash.leaf(crypto_primitives, leaf))
+            .collect()];
+        while levels[levels.len() - 1].len() > 1 {
+            let next = levels[levels.len() - 1]
+                .chunks(2)
+                .map(|pair| match pair {
+                    [a, b] => hash.node(crypto_primitives, a, b),
+                    [a] => *a,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(next);
+        }
+
+        Ok(MerkleTree {
+            hash,
+            leaves,
+            levels,
+        })
+    }
+
+    /// Root to be committed to the sale contract.
+    pub fn root(&self) -> MerkleRoot {
+        MerkleRoot {
+            hash: self.hash,
+            root: self.levels[self.levels.len() - 1][0],
+        }
+    }
+
+    /// Leaf of `user` and its proof to be passed to `userDeposit`.
+    pub fn proof(&self, user: &Address) -> Option<(WhitelistLeaf, Vec<MerkleNode>)> {
+        let position = self.leaves.iter().position(|leaf| leaf.user == *user)?;
+        let mut index = position;
+        let mut proof = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            if let Some(sibling) = level.get(index ^ 1) {
+                proof.push(*sibling);
+            }
+            index /= 2;
+        }
+        Some((self.leaves[position].clone(), proof))
+    }
+}
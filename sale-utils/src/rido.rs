@@ -0,0 +1,233 @@
+//! Entrypoint logic shared by the RIDO sale contracts.
+//! Each contract keeps its own entrypoints(authorization and events)
+//! and implements `RidoState` so that the checks and bookkeeping behind them are written once.
+use crate::{error::*, sale::SaleSchedule, token::transfer_token, types::*};
+use concordium_cis2::OnReceivingCis2Params;
+use concordium_std::*;
+
+/// State every RIDO sale contract keeps.
+pub trait RidoState {
+    /// Account of the administrator of the entity running the IDO
+    fn proj_admin(&self) -> AccountAddress;
+    fn status(&self) -> &SaleStatus;
+    fn paused(&self) -> bool;
+    fn paused_mut(&mut self) -> &mut bool;
+    fn project_token(&self) -> Option<ContractAddress>;
+    fn project_token_mut(&mut self) -> &mut Option<ContractAddress>;
+    fn schedule(&self) -> &SaleSchedule;
+    fn schedule_mut(&mut self) -> &mut SaleSchedule;
+}
+
+/// Receivers of the sale fee charged in project tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeReceiver {
+    /// Overlay team
+    Ovl,
+    /// Buy back burn
+    Bbb,
+}
+
+/// State of the RIDO sale contracts selling a fixed amount of project tokens,
+/// which the project deposits at once and of which fixed fees are charged.
+pub trait RidoFees: RidoState {
+    /// Project tokens sold at the sale, to be deposited by `createPool`
+    fn sold_tokens(&self) -> ContractResult<ContractTokenAmount>;
+    fn fee_receiver(&self, fee: FeeReceiver) -> Address;
+    /// Share of the tokens sold paid as the fee, in basis points
+    fn fee_share(&self, fee: FeeReceiver) -> BasisPoints;
+    /// Number of how many fee received
+    fn fee_claimed_inc(&self, fee: FeeReceiver) -> u8;
+    fn set_fee_claimed_inc(&mut self, fee: FeeReceiver, inc: u8);
+}
+
+/// Pause or unpause the contract(`setPaused` and `setUnpaused`).
+/// Reject if the sender is not the contract owner.
+pub fn set_paused<T: RidoState>(
+    ctx: &impl HasReceiveContext,
+    state: &mut T,
+    paused: bool,
+) -> ContractResult<()> {
+    ensure!(
+        ctx.sender().matches_account(&ctx.owner()),
+        ContractError::Unauthorized
+    );
+    *state.paused_mut() = paused;
+    Ok(())
+}
+
+/// Check that the result of the sale can be fixed(`setFixed`).
+/// Reject if the status is not Ready or if called before the end of the sale.
+pub fn ensure_sale_closed<T: RidoState>(
+    ctx: &impl HasReceiveContext,
+    state: &T,
+) -> ContractResult<()> {
+    // Once fixed, suspended or failed, the sale cannot be fixed again.
+    ensure_eq!(
+        *state.status(),
+        SaleStatus::Ready,
+        CustomContractError::SaleNotReady.into()
+    );
+
+    // This func does not work until the sale is closed.
+    ensure!(
+        state.schedule().is_sale_closed(ctx.metadata().slot_time()),
+        CustomContractError::InvalidSchedule.into()
+    );
+    Ok(())
+}
+
+/// Register the project token contract given as parameter(`setPjtoken`).
+/// Reject if it fails to parse parameter or if it has already been set.
+pub fn set_project_token<T: RidoState>(
+    ctx: &impl HasReceiveContext,
+    state: &mut T,
+) -> ContractResult<ContractAddress> {
+    let addr: ContractAddress = ctx.parameter_cursor().get()?;
+
+    ensure!(
+        state.project_token().is_none(),
+        CustomContractError::Inappropriate.into()
+    );
+
+    *state.project_token_mut() = Some(addr);
+    Ok(addr)
+}
+
+/// Register the TGE given as parameter(`setTGE`).
+/// Reject if it fails to parse parameter or if it has already been set.
+pub fn set_tge<T: RidoState>(
+    ctx: &impl HasReceiveContext,
+    state: &mut T,
+) -> ContractResult<Timestamp> {
+    let ts: Timestamp = ctx.parameter_cursor().get()?;
+
+    ensure!(
+        state.schedule().vesting_start.is_none(),
+        CustomContractError::Inappropriate.into()
+    );
+
+    state.schedule_mut().vesting_start = Some(ts);
+    Ok(ts)
+}
+
+/// Check that project tokens can be handed out,
+/// returning the project token contract and TGE.
+pub fn ensure_claimable<T: RidoState>(state: &T) -> ContractResult<(ContractAddress, Timestamp)> {
+    ensure_eq!(
+        *state.status(),
+        SaleStatus::Fixed,
+        CustomContractError::SaleNotFixed.into()
+    );
+    let project_token = state
+        .project_token()
+        .ok_or(ContractError::from(CustomContractError::NotSetProjectToken))?;
+    let vesting_start = state
+        .schedule()
+        .vesting_start
+        .ok_or(ContractError::from(CustomContractError::NotSetTge))?;
+    Ok((project_token, vesting_start))
+}
+
+/// Check that project tokens are deposited by the project admin
+/// through the project token contract(`createPool`), returning the project token contract.
+/// Reject if the contract is paused, the status is not Fixed
+/// or the sender is not the project token contract.
+pub fn ensure_pool_sender<T: RidoState>(
+    ctx: &impl HasReceiveContext,
+    state: &T,
+) -> ContractResult<ContractAddress> {
+    ensure!(!state.paused(), CustomContractError::ContractPaused.into());
+    ensure_eq!(
+        *state.status(),
+        SaleStatus::Fixed,
+        CustomContractError::SaleNotFixed.into()
+    );
+
+    let sender = if let Address::Contract(contract) = ctx.sender() {
+        contract
+    } else {
+        bail!(CustomContractError::ContractOnly.into())
+    };
+
+    ensure!(
+        Some(sender) == state.project_token() && ctx.invoker() == state.proj_admin(),
+        ContractError::Unauthorized
+    );
+    Ok(sender)
+}
+
+/// Check the deposit of the project tokens sold at the sale(`createPool`).
+/// Reject as `ensure_pool_sender`, if it fails to parse parameter
+/// or if the quantity deposited differs from the quantity sold.
+pub fn create_pool<T: RidoFees>(ctx: &impl HasReceiveContext, state: &T) -> ContractResult<()> {
+    ensure_pool_sender(ctx, state)?;
+
+    let params: OnReceivingCis2Params<ContractTokenId, ContractTokenAmount> =
+        ctx.parameter_cursor().get()?;
+
+    ensure!(
+        state.sold_tokens()? == params.amount,
+        CustomContractError::NotMatchAmount.into()
+    );
+    Ok(())
+}
+
+/// Check that the project admin can claim the funds raised(`projectClaim`),
+/// returning the project admin.
+/// Reject if the contract is paused, the status is not Fixed
+/// or the sender is not the project admin.
+pub fn ensure_project_claimable<T: RidoState>(
+    ctx: &impl HasReceiveContext,
+    state: &T,
+) -> ContractResult<AccountAddress> {
+    ensure!(!state.paused(), CustomContractError::ContractPaused.into());
+    ensure_eq!(
+        *state.status(),
+        SaleStatus::Fixed,
+        CustomContractError::SaleNotFixed.into()
+    );
+
+    let proj_admin = state.proj_admin();
+    ensure!(
+        ctx.sender().matches_account(&proj_admin),
+        ContractError::Unauthorized
+    );
+    Ok(proj_admin)
+}
+
+/// Pay the part of the fee vested since the last claim(`ovlClaim` and `bbbClaim`),
+/// returning the receiver, the amount paid and the number of passed vesting steps.
+/// Reject if the sender is not the contract owner, as `ensure_claimable`
+/// or if it fails to transfer the tokens.
+pub fn claim_fee<T: RidoFees>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<T>,
+    fee: FeeReceiver,
+) -> ContractResult<(Address, ContractTokenAmount, u8)> {
+    ensure!(
+        ctx.sender().matches_account(&ctx.owner()),
+        ContractError::Unauthorized
+    );
+
+    let state = host.state_mut();
+    let (project_token, vesting_start) = ensure_claimable(state)?;
+
+    let cur_inc = state.fee_claimed_inc(fee);
+    let (amount, inc): (ContractTokenAmount, u8) = state.schedule().calc_vesting_amount_of(
+        ctx.metadata().slot_time(),
+        vesting_start,
+        u128::from(state.sold_tokens()?.0),
+        state.fee_share(fee),
+        cur_inc,
+    )?;
+
+    if inc > cur_inc {
+        state.set_fee_claimed_inc(fee, inc);
+    }
+
+    let to = state.fee_receiver(fee);
+    if amount.0 > 0 {
+        transfer_token(ctx, host, project_token, to, amount)?;
+    }
+    Ok((to, amount, inc))
+}
@@ -0,0 +1,423 @@
+//! Sale schedule, sale information and vesting math shared by the sale contracts.
+use crate::{
+    error::{ContractError, ContractResult, CustomContractError},
+    types::*,
+    BPS_DENOMINATOR,
+};
+use concordium_std::{collections::BTreeMap, *};
+
+/// Sale Schedule
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct SaleSchedule {
+    /// IDO schedule(The process is split into some phases)
+    pub open_at: BTreeMap<Timestamp, Prior>,
+    /// Sale End Time
+    pub close_at: Timestamp,
+    /// Actual vesting_period is calculated based on this start time
+    pub vesting_start: Option<Timestamp>,
+    /// User(sale particicants) can withdraw assets according to the vesting period
+    pub vesting_period: VestingSchedule,
+}
+
+/// How project tokens are released from TGE(`vesting_start`).
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub enum VestingSchedule {
+    /// Discrete unlocks of the share when each duration from TGE has passed
+    Stepped(BTreeMap<Duration, AllowedPercentage>),
+    /// A share unlocked at TGE, then the rest released per second after a cliff
+    Linear(LinearVesting),
+}
+
+/// Cliff and linear release of `VestingSchedule::Linear`
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct LinearVesting {
+    /// Share unlocked at TGE, in basis points
+    pub tge_percentage: AllowedPercentage,
+    /// Nothing more is released until this duration from TGE has passed
+    pub cliff: Duration,
+    /// The rest is released linearly over this duration from the end of the cliff
+    pub duration: Duration,
+}
+
+impl VestingSchedule {
+    /// Stepped unlocks must add up to 100%(`BPS_DENOMINATOR`), and a linear release
+    /// needs some duration unless everything is unlocked at TGE.
+    pub fn validate(&self) -> Result<(), CustomContractError> {
+        match self {
+            VestingSchedule::Stepped(steps) => {
+                let mut total_per: u32 = 0;
+                for (_, per) in steps.iter() {
+                    total_per += u32::from(*per);
+                }
+                ensure!(
+                    total_per == u32::from(BPS_DENOMINATOR),
+                    CustomContractError::Inappropriate
+                );
+            },
+            VestingSchedule::Linear(linear) => {
+                ensure!(
+                    linear.tge_percentage <= BPS_DENOMINATOR,
+                    CustomContractError::Inappropriate
+                );
+                ensure!(
+                    linear.tge_percentage == BPS_DENOMINATOR || linear.duration.millis() > 0,
+                    CustomContractError::InvalidSchedule
+                );
+            },
+        }
+        Ok(())
+    }
+
+    pub fn is_linear(&self) -> bool {
+        matches!(self, VestingSchedule::Linear(_))
+    }
+
+    /// Number of stepped unlocks passed by `now`(always 0 for linear vesting).
+    pub fn passed_steps(&self, now: Timestamp, vesting_start: Timestamp) -> ContractResult<u8> {
+        let mut inc: u8 = 0;
+        if let VestingSchedule::Stepped(steps) = self {
+            for (duration, _) in steps.iter() {
+                let ts = vesting_start
+                    .checked_add(*duration)
+                    .ok_or(ContractError::from(CustomContractError::InvalidSchedule))?;
+                if now < ts {
+                    break;
+                }
+                inc += 1;
+            }
+        }
+        Ok(inc)
+    }
+
+    /// Time at which everything has been released, `None` if it does not fit in a timestamp.
+    pub fn end(&self, vesting_start: Timestamp) -> Option<Timestamp> {
+        match self {
+            VestingSchedule::Stepped(steps) => match steps.last_key_value() {
+                Some((duration, _)) => vesting_start.checked_add(*duration),
+                None => Some(vesting_start),
+            },
+            VestingSchedule::Linear(linear) => vesting_start
+                .checked_add(linear.cliff)?
+                .checked_add(linear.duration),
+        }
+    }
+
+    /// Amount out of `total_amount` released by `now`, whether claimed or not.
+    pub fn vested_amount(
+        &self,
+        now: Timestamp,
+        vesting_start: Timestamp,
+        total_amount: u128,
+    ) -> ContractResult<u128> {
+        match self {
+            VestingSchedule::Stepped(steps) => {
+                let mut per_total: u128 = 0;
+                for (duration, per) in steps.iter() {
+                    let ts = vesting_start
+                        .checked_add(*duration)
+                        .ok_or(ContractError::from(CustomContractError::InvalidSchedule))?;
+                    if now < ts {
+                        break;
+                    }
+                    per_total += u128::from(*per);
+                }
+                Ok(mul_div(
+                    total_amount,
+                    per_total,
+                    u128::from(BPS_DENOMINATOR),
+                )?)
+            },
+            VestingSchedule::Linear(linear) => {
+                if now < vesting_start {
+                    return Ok(0);
+                }
+                let at_tge = mul_div(
+                    total_amount,
+                    u128::from(linear.tge_percentage),
+                    u128::from(BPS_DENOMINATOR),
+                )?;
+                let cliff_end = vesting_start
+                    .checked_add(linear.cliff)
+                    .ok_or(ContractError::from(CustomContractError::InvalidSchedule))?;
+                let elapsed = match now.duration_since(cliff_end) {
+                    Some(elapsed) => u128::from(elapsed.seconds()),
+                    None => return Ok(at_tge),
+                };
+                let duration = u128::from(linear.duration.seconds());
+                if elapsed >= duration {
+                    return Ok(total_amount);
+                }
+                Ok(at_tge + mul_div(total_amount - at_tge, elapsed, duration)?)
+            },
+        }
+    }
+}
+
+/// `amount * num / den` without overflowing in the middle.
+fn mul_div(amount: u128, num: u128, den: u128) -> Result<u128, CustomContractError> {
+    Ok(amount
+        .checked_mul(num)
+        .ok_or(CustomContractError::OverflowError)?
+        / den)
+}
+
+impl SaleSchedule {
+    pub fn new(
+        now: Timestamp,
+        open_at: BTreeMap<Timestamp, Prior>,
+        close_at: Timestamp,
+        vesting_period: VestingSchedule,
+    ) -> Result<Self, CustomContractError> {
+        ensure!(!open_at.is_empty(), CustomContractError::InvalidSchedule);
+
+        ensure!(
+            now < *open_at.first_key_value().unwrap().0,
+            CustomContractError::InvalidSchedule
+        );
+
+        ensure!(
+            *open_at.last_key_value().unwrap().0 < close_at,
+            CustomContractError::InvalidSchedule
+        );
+
+        vesting_period.validate()?;
+
+        Ok(SaleSchedule {
+            open_at,
+            close_at,
+            vesting_start: None,
+            vesting_period,
+        })
+    }
+
+    pub fn is_sale_opened(&self, now: Timestamp) -> bool {
+        if now < *self.open_at.first_key_value().unwrap().0 {
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_sale_closed(&self, now: Timestamp) -> bool {
+        if self.close_at < now {
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_on_sale(&self, now: Timestamp) -> bool {
+        if *self.open_at.first_key_value().unwrap().0 <= now && now < self.close_at {
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn check_sale_priority(&self, now: Timestamp) -> Option<Prior> {
+        if !self.is_on_sale(now) {
+            return None;
+        }
+
+        let mut current = Prior::TOP;
+        for (ts, priority) in self.open_at.iter() {
+            if priority == &current {
+                continue;
+            }
+            if now < *ts {
+                return Some(current);
+            } else {
+                current = priority.clone();
+            }
+        }
+        return Some(current);
+    }
+
+    /// Amount of project tokens vested between the `cur_inc`-th and the latest
+    /// passed step of the vesting period, and the number of passed steps.
+    /// `shared` is the part of `total_units` the receiver is entitled to, in basis points.
+    pub fn calc_vesting_amount(
+        &self,
+        now: Timestamp,
+        vesting_start: Timestamp,
+        token_per_unit: ContractTokenAmount,
+        total_units: u64,
+        shared: BasisPoints,
+        cur_inc: u8,
+    ) -> ContractResult<(ContractTokenAmount, u8)> {
+        let total_amount: u128 = (token_per_unit.0 as u128)
+            .checked_mul(u128::from(total_units))
+            .ok_or(ContractError::from(CustomContractError::OverflowError))?;
+
+        self.calc_vesting_amount_of(now, vesting_start, total_amount, shared, cur_inc)
+    }
+
+    /// Project tokens newly claimable out of `total_amount`, and the claim progress to record.
+    /// Claims are tracked by the tokens already `claimed` rather than per step,
+    /// so rounding is applied to the cumulative vested amount only
+    /// and the last unlock pays exactly the rest.
+    /// The progress is the number of passed steps of stepped vesting(`cur_inc` for linear one).
+    /// `shared` is the part of `total_amount` the receiver is entitled to, in basis points.
+    pub fn calc_claimable_amount(
+        &self,
+        now: Timestamp,
+        vesting_start: Timestamp,
+        total_amount: u128,
+        shared: BasisPoints,
+        cur_inc: u8,
+        claimed: ContractTokenAmount,
+    ) -> ContractResult<(ContractTokenAmount, u8)> {
+        let total_claimable = mul_div(
+            total_amount,
+            u128::from(shared),
+            u128::from(BPS_DENOMINATOR),
+        )?;
+        let vested = self
+            .vesting_period
+            .vested_amount(now, vesting_start, total_claimable)?;
+        let amount = vested.saturating_sub(u128::from(claimed.0));
+        let amount = u64::try_from(amount).map_err(CustomContractError::from)?;
+        let inc = cmp::max(
+            cur_inc,
+            self.vesting_period.passed_steps(now, vesting_start)?,
+        );
+        Ok((ContractTokenAmount::from(amount), inc))
+    }
+
+    /// Same as `calc_vesting_amount`, but for an allocation given as a token amount
+    /// rather than a number of units.
+    /// Only for stepped vesting, and every step is rounded down separately,
+    /// see `calc_claimable_amount` for the cumulative one.
+    pub fn calc_vesting_amount_of(
+        &self,
+        now: Timestamp,
+        vesting_start: Timestamp,
+        total_amount: u128,
+        shared: BasisPoints,
+        cur_inc: u8,
+    ) -> ContractResult<(ContractTokenAmount, u8)> {
+        let steps = match &self.vesting_period {
+            VestingSchedule::Stepped(steps) => steps,
+            VestingSchedule::Linear(_) => bail!(CustomContractError::Inappropriate.into()),
+        };
+
+        let mut amount: u128 = 0;
+        let mut inc: u8 = 0;
+
+        for (duration, per) in steps.iter() {
+            let ts = match vesting_start.checked_add(*duration) {
+                Some(v) => v,
+                None => bail!(CustomContractError::InvalidSchedule.into()),
+            };
+
+            if now < ts {
+                break;
+            }
+
+            if cur_inc > inc {
+                inc += 1;
+                continue;
+            }
+
+            let total_claimable: u128 = total_amount
+                .checked_mul(u128::from(shared))
+                .ok_or(ContractError::from(CustomContractError::OverflowError))?
+                / u128::from(BPS_DENOMINATOR);
+
+            let allocation: u128 = total_claimable
+                .checked_mul(u128::from(*per))
+                .ok_or(ContractError::from(CustomContractError::OverflowError))?
+                / u128::from(BPS_DENOMINATOR);
+
+            amount += allocation;
+            inc += 1;
+        }
+
+        if amount > u64::MAX as u128 {
+            bail!(ContractError::from(CustomContractError::OverflowError))
+        } else {
+            Ok((ContractTokenAmount::from(amount as u64), inc))
+        }
+    }
+}
+
+/// Information about sale
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct SaleInfo {
+    /// Price per a project token in the smallest unit of the sale currency
+    /// (micro CCD or micro USDC)
+    pub price_per_token: MicroCcd,
+    /// Amount of tokens contained in a unit
+    pub token_per_unit: ContractTokenAmount,
+    /// Maximum quantity to be issued in this sale
+    pub max_units: UnitsAmount,
+    /// Minimum quantity to be issued for this sale
+    pub min_units: UnitsAmount,
+    /// Amount of sales completed at that point
+    pub applied_units: UnitsAmount,
+}
+
+impl SaleInfo {
+    pub fn new(
+        price_per_token: MicroCcd,
+        token_per_unit: ContractTokenAmount,
+        max_units: UnitsAmount,
+        min_units: UnitsAmount,
+    ) -> Result<Self, CustomContractError> {
+        ensure!(min_units < max_units, CustomContractError::Inappropriate);
+
+        // Price_per_unit must not exceed 18_446_744_073_709_551_615
+        if price_per_token.checked_mul(token_per_unit.0).is_none() {
+            bail!(CustomContractError::OverflowError);
+        }
+
+        Ok(SaleInfo {
+            price_per_token,
+            token_per_unit,
+            max_units,
+            min_units,
+            applied_units: 0,
+        })
+    }
+
+    pub fn check_room_to_apply(&self) -> UnitsAmount {
+        if self.applied_units < self.max_units {
+            self.max_units - self.applied_units
+        } else {
+            0
+        }
+    }
+
+    pub fn is_reached_sc(&self) -> bool {
+        if self.min_units <= self.applied_units {
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn amount_of_pjtoken(&self) -> Result<ContractTokenAmount, CustomContractError> {
+        let token_amount = self.token_per_unit.0.checked_mul(self.applied_units as u64);
+        if token_amount.is_none() {
+            bail!(CustomContractError::OverflowError);
+        }
+        Ok(ContractTokenAmount::from(token_amount.unwrap()))
+    }
+
+    pub fn calc_price_per_unit(&self) -> Result<Amount, CustomContractError> {
+        Ok(Amount::from_micro_ccd(self.price_per_unit()?))
+    }
+
+    pub fn calc_price_per_unit_usdc(&self) -> Result<UsdcAmount, CustomContractError> {
+        Ok(UsdcAmount::from(self.price_per_unit()?))
+    }
+
+    fn price_per_unit(&self) -> Result<u64, CustomContractError> {
+        // Price_per_unit must not exceed 18_446_744_073_709_551_615
+        let price = self.price_per_token.checked_mul(self.token_per_unit.0);
+        if price.is_none() {
+            bail!(CustomContractError::OverflowError);
+        }
+        Ok(price.unwrap())
+    }
+}
@@ -0,0 +1,75 @@
+//! Payouts of CIS-2 tokens held by the sale contracts.
+use crate::{error::ContractResult, types::*};
+use concordium_cis2::{AdditionalData, IsTokenId, Receiver, TokenIdUnit, Transfer, TransferParams};
+use concordium_std::*;
+
+/// Entrypoint through which contracts receive CIS-2 tokens unless another one is configured.
+pub const DEFAULT_RECEIVE_HOOK: &str = "callback";
+
+/// Transfer CIS-2 tokens(project tokens or a stablecoin) held by the calling contract.
+/// Contracts receive them through their `callback` entrypoint.
+pub fn transfer_token<T>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<T>,
+    token: ContractAddress,
+    to: Address,
+    amount: ContractTokenAmount,
+) -> ContractResult<()> {
+    transfer_token_of(ctx, host, token, TokenIdUnit(), to, amount)
+}
+
+/// Same as `transfer_token`, for tokens of any id(e.g. tokens sent to the contract by mistake).
+pub fn transfer_token_of<T, I: IsTokenId>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<T>,
+    token: ContractAddress,
+    token_id: I,
+    to: Address,
+    amount: ContractTokenAmount,
+) -> ContractResult<()> {
+    let hook = OwnedEntrypointName::new_unchecked(DEFAULT_RECEIVE_HOOK.to_owned());
+    transfer(ctx, host, token, token_id, to, amount, hook)
+}
+
+/// Same as `transfer_token`, with contracts receiving the tokens through `hook`.
+pub fn transfer_token_via<T>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<T>,
+    token: ContractAddress,
+    to: Address,
+    amount: ContractTokenAmount,
+    hook: OwnedEntrypointName,
+) -> ContractResult<()> {
+    transfer(ctx, host, token, TokenIdUnit(), to, amount, hook)
+}
+
+fn transfer<T, I: IsTokenId>(
+    ctx: &impl HasReceiveContext,
+    host: &mut impl HasHost<T>,
+    token: ContractAddress,
+    token_id: I,
+    to: Address,
+    amount: ContractTokenAmount,
+    hook: OwnedEntrypointName,
+) -> ContractResult<()> {
+    let to = match to {
+        Address::Account(account_addr) => Receiver::from_account(account_addr),
+        Address::Contract(contract_addr) => Receiver::from_contract(contract_addr, hook),
+    };
+
+    let transfer = Transfer {
+        from: Address::from(ctx.self_address()),
+        to,
+        token_id,
+        amount,
+        data: AdditionalData::empty(),
+    };
+    let _ = host.invoke_contract(
+        &token,
+        &TransferParams::from(vec![transfer]),
+        EntrypointName::new_unchecked("transfer"),
+        Amount::zero(),
+    )?;
+
+    Ok(())
+}
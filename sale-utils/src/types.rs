@@ -1,5 +1,5 @@
 use concordium_cis2::{TokenAmountU64, TokenIdUnit};
-use concordium_std::{SchemaType, Serialize};
+use concordium_std::{Address, SchemaType, Serialize};
 
 pub type ContractTokenId = TokenIdUnit;
 pub type ContractTokenAmount = TokenAmountU64;
@@ -7,7 +7,10 @@ pub type OvlCreditAmount = u64;
 pub type MicroCcd = u64;
 pub type UnitsAmount = u32;
 
-pub type AllowedPercentage = u8;
+/// 1/100 of a percent, `BPS_DENOMINATOR` being 100%
+pub type BasisPoints = u16;
+/// Share of vesting unlocks, in basis points(e.g. 1_250 for 12.5%)
+pub type AllowedPercentage = BasisPoints;
 pub type UsdcAmount = TokenAmountU64;
 pub type MicroUsdc = u64;
 
@@ -17,6 +20,7 @@ pub enum SaleStatus {
     Ready,
     Fixed,
     Suspend,
+    Failed,
 }
 
 #[derive(Debug, Serialize, SchemaType, PartialEq, Eq, PartialOrd, Ord, Clone)]
@@ -61,3 +65,14 @@ pub enum Prior {
     SECOND,
     ANY = 99,
 }
+
+/// Entry of the whitelist given by the Overlay team.
+#[derive(Debug, Serialize, SchemaType, Clone, PartialEq, Eq)]
+pub struct AllowedUserParams {
+    /// Users address to be whitelisted
+    pub user: Address,
+    /// Priority for participation in the sale
+    pub prior: Prior,
+    /// Tier deciding how many units the user can purchase
+    pub tier: Tier,
+}